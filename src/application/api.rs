@@ -0,0 +1,284 @@
+use std::fmt::Write as _;
+use std::ops::Range;
+
+use regex::Regex;
+
+use super::AppError;
+use super::playground::{MATCH_LIMIT, PatternFlags};
+
+/// Inputs of one `regex` crate API demonstration.
+pub struct ApiCall<'a> {
+    pub pattern: &'a str,
+    pub flags: PatternFlags,
+    pub haystack: &'a str,
+    /// Replacement passed to `replace` and `replace_all`.
+    pub replacement: &'a str,
+    /// Limit passed to `splitn`.
+    pub limit: usize,
+    /// Patterns added to the `RegexSet` after `pattern`.
+    pub set_patterns: &'a [&'a str],
+}
+
+/// Results of the `regex` crate methods for an [`ApiCall`].
+#[derive(Debug)]
+pub struct ApiResults {
+    pub is_match: bool,
+    pub find_iter: Vec<(Range<usize>, String)>,
+    pub captures_iter: Vec<Vec<Option<String>>>,
+    pub split: Vec<String>,
+    pub splitn: Vec<String>,
+    pub replace: String,
+    pub replace_all: String,
+    /// Indices of the set patterns that match, or the first invalid one.
+    pub set_matches: Result<Vec<usize>, AppError>,
+}
+
+impl ApiCall<'_> {
+    /// Calls every demonstrated method with `re`, the compiled `pattern`.
+    pub fn run(&self, re: &Regex) -> ApiResults {
+        let owned = |s: &str| s.to_string();
+        ApiResults {
+            is_match: re.is_match(self.haystack),
+            find_iter: re
+                .find_iter(self.haystack)
+                .take(MATCH_LIMIT)
+                .map(|m| (m.range(), m.as_str().to_string()))
+                .collect(),
+            captures_iter: re
+                .captures_iter(self.haystack)
+                .take(MATCH_LIMIT)
+                .map(|caps| caps.iter().map(|g| g.map(|g| owned(g.as_str()))).collect())
+                .collect(),
+            split: re
+                .split(self.haystack)
+                .take(MATCH_LIMIT)
+                .map(owned)
+                .collect(),
+            splitn: re.splitn(self.haystack, self.limit).map(owned).collect(),
+            replace: re.replace(self.haystack, self.replacement).into_owned(),
+            replace_all: re.replace_all(self.haystack, self.replacement).into_owned(),
+            set_matches: self.set_matches(),
+        }
+    }
+
+    fn set_patterns(&self) -> Vec<&str> {
+        std::iter::once(self.pattern)
+            .chain(self.set_patterns.iter().copied())
+            .collect()
+    }
+
+    fn set_matches(&self) -> Result<Vec<usize>, AppError> {
+        let patterns = self.set_patterns();
+        // Compile one by one first so that errors point into a single pattern.
+        for pattern in &patterns[1..] {
+            self.flags.compile(pattern)?;
+        }
+        let set = self.flags.compile_set(&patterns)?;
+        Ok(set.matches(self.haystack).into_iter().collect())
+    }
+
+    /// Generates a self-contained Rust program performing the same calls.
+    pub fn snippet(&self) -> String {
+        let calls = builder_calls(&self.flags);
+        let set = self.set_patterns();
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "use regex::{{{}}};\n",
+            if calls.is_empty() {
+                "Regex, RegexSet"
+            } else {
+                "RegexBuilder, RegexSetBuilder"
+            }
+        );
+        out.push_str("fn main() {\n");
+        if calls.is_empty() {
+            let _ = writeln!(
+                out,
+                "    let re = Regex::new({}).unwrap();",
+                raw_string_literal(self.pattern)
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "    let re = RegexBuilder::new({})",
+                raw_string_literal(self.pattern)
+            );
+            write_builder_calls(&mut out, &calls);
+        }
+        let _ = writeln!(
+            out,
+            "    let haystack = \"{}\";\n",
+            self.haystack.escape_debug()
+        );
+
+        out.push_str("    println!(\"is_match: {}\", re.is_match(haystack));\n");
+        out.push_str("    for m in re.find_iter(haystack) {\n");
+        out.push_str(
+            "        println!(\"find: {:?} at {}..{}\", m.as_str(), m.start(), m.end());\n",
+        );
+        out.push_str("    }\n");
+        out.push_str("    for caps in re.captures_iter(haystack) {\n");
+        out.push_str(
+            "        let groups: Vec<_> = caps.iter().map(|g| g.map(|g| g.as_str())).collect();\n",
+        );
+        out.push_str("        println!(\"captures: {groups:?}\");\n");
+        out.push_str("    }\n");
+        out.push_str("    println!(\"split: {:?}\", re.split(haystack).collect::<Vec<_>>());\n");
+        let _ = writeln!(
+            out,
+            "    println!(\"splitn: {{:?}}\", re.splitn(haystack, {}).collect::<Vec<_>>());",
+            self.limit
+        );
+        let replacement = raw_string_literal(self.replacement);
+        let _ = writeln!(
+            out,
+            "    println!(\"replace: {{}}\", re.replace(haystack, {replacement}));"
+        );
+        let _ = writeln!(
+            out,
+            "    println!(\"replace_all: {{}}\", re.replace_all(haystack, {replacement}));\n"
+        );
+
+        let patterns: Vec<String> = set.iter().map(|p| raw_string_literal(p)).collect();
+        if calls.is_empty() {
+            let _ = writeln!(
+                out,
+                "    let set = RegexSet::new([{}]).unwrap();",
+                patterns.join(", ")
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "    let set = RegexSetBuilder::new([{}])",
+                patterns.join(", ")
+            );
+            write_builder_calls(&mut out, &calls);
+        }
+        out.push_str(
+            "    println!(\"set matches: {:?}\", set.matches(haystack).into_iter().collect::<Vec<_>>());\n",
+        );
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Builder methods whose value differs from the `regex` crate's default.
+fn builder_calls(flags: &PatternFlags) -> Vec<(&'static str, bool)> {
+    let defaults = PatternFlags::default();
+    [
+        (
+            "case_insensitive",
+            flags.case_insensitive,
+            defaults.case_insensitive,
+        ),
+        ("multi_line", flags.multi_line, defaults.multi_line),
+        (
+            "dot_matches_new_line",
+            flags.dot_matches_new_line,
+            defaults.dot_matches_new_line,
+        ),
+        (
+            "ignore_whitespace",
+            flags.ignore_whitespace,
+            defaults.ignore_whitespace,
+        ),
+        ("swap_greed", flags.swap_greed, defaults.swap_greed),
+        ("unicode", flags.unicode, defaults.unicode),
+        ("crlf", flags.crlf, defaults.crlf),
+    ]
+    .into_iter()
+    .filter(|(_, value, default)| value != default)
+    .map(|(method, value, _)| (method, value))
+    .collect()
+}
+
+fn write_builder_calls(out: &mut String, calls: &[(&str, bool)]) {
+    for (method, value) in calls {
+        let _ = writeln!(out, "        .{method}({value})");
+    }
+    out.push_str("        .build()\n        .unwrap();\n");
+}
+
+/// Quotes `text` as a Rust raw string literal with as few `#` as possible.
+/// Falls back to an escaped literal for text a raw string cannot hold (a
+/// carriage return).
+fn raw_string_literal(text: &str) -> String {
+    if text.contains('\r') {
+        return format!("\"{}\"", text.escape_debug());
+    }
+    // The literal must not contain `"` followed by as many `#` as it uses.
+    let hashes = text
+        .match_indices('"')
+        .map(|(i, _)| text[i + 1..].bytes().take_while(|&b| b == b'#').count() + 1)
+        .max()
+        .unwrap_or(0);
+    let fence = "#".repeat(hashes);
+    format!("r{fence}\"{text}\"{fence}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call<'a>(pattern: &'a str, haystack: &'a str, set: &'a [&'a str]) -> ApiCall<'a> {
+        ApiCall {
+            pattern,
+            flags: PatternFlags::default(),
+            haystack,
+            replacement: "<$1>",
+            limit: 2,
+            set_patterns: set,
+        }
+    }
+
+    #[test]
+    fn raw_literals_use_minimal_hashes() {
+        assert_eq!(raw_string_literal(r"\d+"), r#"r"\d+""#);
+        assert_eq!(raw_string_literal(r#"say "hi""#), r##"r#"say "hi""#"##);
+        assert_eq!(raw_string_literal(r##"a"#b"##), r###"r##"a"#b"##"###);
+        assert_eq!(raw_string_literal("a\rb"), r#""a\rb""#);
+    }
+
+    #[test]
+    fn runs_every_method() {
+        let api = call(r"(\d)", "a1b2c", &[r"[a-z]", "z"]);
+        let results = api.run(&Regex::new(api.pattern).unwrap());
+        assert!(results.is_match);
+        assert_eq!(
+            results.find_iter,
+            vec![(1..2, "1".to_string()), (3..4, "2".to_string())]
+        );
+        assert_eq!(
+            results.captures_iter[1],
+            vec![Some("2".into()), Some("2".into())]
+        );
+        assert_eq!(results.split, vec!["a", "b", "c"]);
+        assert_eq!(results.splitn, vec!["a", "b2c"]);
+        assert_eq!(results.replace, "a<1>b2c");
+        assert_eq!(results.replace_all, "a<1>b<2>c");
+        assert_eq!(results.set_matches.unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn invalid_set_pattern_is_reported() {
+        let api = call("a", "a", &["(b"]);
+        let err = api.run(&Regex::new("a").unwrap()).set_matches.unwrap_err();
+        assert_eq!(err.span(), Some(0..1));
+    }
+
+    #[test]
+    fn snippet_uses_builder_only_for_non_default_flags() {
+        let mut api = call(r#"a"b"#, "x\ny", &[]);
+        let plain = api.snippet();
+        assert!(plain.contains(r##"let re = Regex::new(r#"a"b"#).unwrap();"##));
+        assert!(plain.contains(r#"let haystack = "x\ny";"#));
+
+        api.flags.case_insensitive = true;
+        api.flags.unicode = false;
+        let built = api.snippet();
+        assert!(built.contains("use regex::{RegexBuilder, RegexSetBuilder};"));
+        assert!(built.contains(".case_insensitive(true)\n        .unicode(false)\n"));
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::SkipReason;
+
+/// Where a batch may touch the file system.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Confinement {
+    /// Refuse sources and destinations that resolve outside `root`.
+    pub enabled: bool,
+    pub root: PathBuf,
+    /// Paths that, with everything below them, are never a source or a
+    /// destination, whether or not `enabled` is set.
+    pub protected: Vec<PathBuf>,
+}
+
+impl Default for Confinement {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            root: PathBuf::from("."),
+            protected: vec![PathBuf::from(".git")],
+        }
+    }
+}
+
+impl Confinement {
+    /// Resolves the root and the protected paths against the working
+    /// directory, once per batch.
+    pub fn guard(&self) -> Guard {
+        let base = std::env::current_dir().unwrap_or_default();
+        Guard {
+            root: self.enabled.then(|| resolve(&base, &self.root)),
+            protected: self
+                .protected
+                .iter()
+                .filter(|path| !path.as_os_str().is_empty())
+                .map(|path| resolve(&base, path))
+                .collect(),
+            base,
+        }
+    }
+}
+
+/// A [`Confinement`] with its paths resolved.
+#[derive(Clone, Debug)]
+pub struct Guard {
+    base: PathBuf,
+    root: Option<PathBuf>,
+    protected: Vec<PathBuf>,
+}
+
+impl Guard {
+    /// Why moving `source` to `destination` is refused, if it is. Rewrites
+    /// in place pass the same path twice.
+    pub fn check(&self, source: &Path, destination: &Path) -> Option<SkipReason> {
+        let source = resolve(&self.base, source);
+        let destination = resolve(&self.base, destination);
+        // Moving a directory also moves the protected paths inside it.
+        let touches = |protected: &PathBuf| {
+            source.starts_with(protected)
+                || protected.starts_with(&source)
+                || destination.starts_with(protected)
+        };
+        if self.protected.iter().any(touches) {
+            return Some(SkipReason::Protected);
+        }
+        // The walk always starts at the working directory, so sources are
+        // checked too: a root below it must not pull outside files in.
+        let root = self.root.as_ref()?;
+        let outside = |path: &PathBuf| path == root || !path.starts_with(root);
+        (outside(&source) || outside(&destination)).then_some(SkipReason::OutsideRoot)
+    }
+}
+
+/// Joins `path` to `base` and removes `.` and `..` without touching the
+/// file system, so symbolic links are not followed.
+fn resolve(base: &Path, path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            // Popping the root itself is a no-op, as `/..` is `/`.
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(protected: &[&str]) -> Guard {
+        Confinement {
+            protected: protected.iter().map(PathBuf::from).collect(),
+            ..Confinement::default()
+        }
+        .guard()
+    }
+
+    #[test]
+    fn resolves_dot_segments_lexically() {
+        let base = Path::new("/work/tree");
+        assert_eq!(
+            resolve(base, Path::new("./a/../b")),
+            Path::new("/work/tree/b")
+        );
+        assert_eq!(resolve(base, Path::new("../../../etc")), Path::new("/etc"));
+        assert_eq!(resolve(base, Path::new("/tmp/./x")), Path::new("/tmp/x"));
+    }
+
+    #[test]
+    fn refuses_destinations_leaving_the_root() {
+        let guard = guard(&[]);
+        let source = Path::new("./photos/a.jpg");
+        assert_eq!(guard.check(source, Path::new("./sorted/a.jpg")), None);
+        assert_eq!(guard.check(source, Path::new("./photos/../a.jpg")), None);
+        for escape in ["../../etc/a.jpg", "/tmp/a.jpg", "./photos/../..", "."] {
+            assert_eq!(
+                guard.check(source, Path::new(escape)),
+                Some(SkipReason::OutsideRoot),
+                "{escape}"
+            );
+        }
+
+        let unconfined = Confinement {
+            enabled: false,
+            ..Confinement::default()
+        }
+        .guard();
+        assert_eq!(unconfined.check(source, Path::new("/tmp/a.jpg")), None);
+    }
+
+    #[test]
+    fn refuses_sources_outside_a_subdirectory_root() {
+        let guard = Confinement {
+            root: PathBuf::from("./photos"),
+            ..Confinement::default()
+        }
+        .guard();
+        let outside = Some(SkipReason::OutsideRoot);
+        assert_eq!(
+            guard.check(Path::new("./photos/a.jpg"), Path::new("./photos/b.jpg")),
+            None
+        );
+        assert_eq!(
+            guard.check(Path::new("./notes/a.jpg"), Path::new("./photos/a.jpg")),
+            outside
+        );
+        assert_eq!(
+            guard.check(Path::new("./a.txt"), Path::new("./a.txt")),
+            outside
+        );
+        assert_eq!(
+            guard.check(Path::new("./photos"), Path::new("./pictures")),
+            outside
+        );
+    }
+
+    #[test]
+    fn protected_paths_are_never_touched() {
+        let guard = guard(&[".git", "keep/me.txt", ""]);
+        let protected = Some(SkipReason::Protected);
+        assert_eq!(
+            guard.check(Path::new("./.git/HEAD"), Path::new("./HEAD")),
+            protected
+        );
+        assert_eq!(
+            guard.check(Path::new("./a"), Path::new("./.git/a")),
+            protected
+        );
+        assert_eq!(
+            guard.check(Path::new("./keep"), Path::new("./kept")),
+            protected
+        );
+        assert_eq!(
+            guard.check(Path::new("./keep/me.txt"), Path::new("./keep/me.txt")),
+            protected
+        );
+        assert_eq!(
+            guard.check(Path::new("./keep/other.txt"), Path::new("./x")),
+            None
+        );
+        assert_eq!(guard.check(Path::new("./.github"), Path::new("./gh")), None);
+    }
+}
@@ -0,0 +1,222 @@
+use std::io;
+use std::path::PathBuf;
+
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use similar::TextDiff;
+
+use super::{AppError, PatternEngine, SkipReason, Template, display_path};
+
+/// Number of leading bytes inspected for NUL bytes, as `git` does.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Encoding a text file was read with, reused when writing it back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark.
+    pub bom: bool,
+}
+
+impl TextEncoding {
+    pub fn name(&self) -> String {
+        if self.bom {
+            format!("{} with BOM", self.encoding.name())
+        } else {
+            self.encoding.name().to_string()
+        }
+    }
+
+    /// Encodes `text`, failing when it contains characters the encoding
+    /// cannot represent.
+    pub fn encode(&self, text: &str) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        let encoding = self.encoding;
+        // encoding_rs only decodes UTF-16, so it is encoded by hand.
+        if encoding == UTF_16LE || encoding == UTF_16BE {
+            let little = encoding == UTF_16LE;
+            let unit = |u: u16| {
+                if little {
+                    u.to_le_bytes()
+                } else {
+                    u.to_be_bytes()
+                }
+            };
+            if self.bom {
+                bytes.extend(unit(0xFEFF));
+            }
+            text.encode_utf16().for_each(|u| bytes.extend(unit(u)));
+            return Ok(bytes);
+        }
+        if self.bom && encoding == UTF_8 {
+            bytes.extend([0xEF, 0xBB, 0xBF]);
+        }
+        let (encoded, _, unmappable) = encoding.encode(text);
+        if unmappable {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the new contents cannot be represented in {}", self.name()),
+            ));
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+/// Detects the encoding of `bytes` and decodes them. Byte order marks win,
+/// then UTF-8, then the guess of `chardetng`. Files with NUL bytes and files
+/// that do not decode cleanly are skipped.
+pub fn decode(bytes: &[u8]) -> Result<(String, TextEncoding), SkipReason> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        if had_errors {
+            return Err(SkipReason::Undecodable);
+        }
+        return Ok((
+            text.into_owned(),
+            TextEncoding {
+                encoding,
+                bom: true,
+            },
+        ));
+    }
+    if bytes.iter().take(BINARY_SNIFF_LEN).any(|&b| b == 0) {
+        return Err(SkipReason::Binary);
+    }
+    let encoding = match std::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        Err(_) => {
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(bytes, true);
+            detector.guess(None, true)
+        }
+    };
+    let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+    if had_errors {
+        return Err(SkipReason::Undecodable);
+    }
+    Ok((
+        text.into_owned(),
+        TextEncoding {
+            encoding,
+            bom: false,
+        },
+    ))
+}
+
+/// Pending rewrite of one file by a content rule.
+#[derive(Clone, Debug)]
+pub struct ContentChange {
+    pub path: PathBuf,
+    pub encoding: TextEncoding,
+    pub replacements: usize,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+impl ContentChange {
+    /// Replaces every match of `regex` in `old_text`, the contents
+    /// [decoded](decode) from `encoding`. Returns `Ok(None)` when nothing
+    /// matches.
+    pub fn new(
+        path: PathBuf,
+        old_text: String,
+        encoding: TextEncoding,
+        regex: &dyn PatternEngine,
+        template: &Template,
+    ) -> Result<Option<Self>, AppError> {
+        let replacements = regex.find_all(&old_text)?.len();
+        if replacements == 0 {
+            return Ok(None);
+        }
+        let new_text = template.replace_all(regex, &old_text)?;
+        if new_text == old_text {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            path,
+            encoding,
+            replacements,
+            old_text,
+            new_text,
+        }))
+    }
+
+    /// The new contents in the file's original encoding.
+    pub fn encoded(&self) -> io::Result<Vec<u8>> {
+        self.encoding.encode(&self.new_text)
+    }
+
+    /// Unified diff of the change with three lines of context.
+    pub fn diff(&self) -> String {
+        let path = display_path(&self.path);
+        TextDiff::from_lines(&self.old_text, &self.new_text)
+            .unified_diff()
+            .context_radius(3)
+            .header(&format!("a/{path}"), &format!("b/{path}"))
+            .to_string()
+    }
+}
+
+/// Dry-run result of a content rule: the files it would rewrite and the
+/// files it had to leave alone.
+#[derive(Clone, Debug, Default)]
+pub struct ContentPreview {
+    pub changes: Vec<ContentChange>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+    /// Files that could not be read, with the error message.
+    pub unreadable: Vec<(PathBuf, String)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn change(bytes: &[u8]) -> Result<Option<ContentChange>, SkipReason> {
+        let regex = Regex::new("colou?r").unwrap();
+        let template = Template::parse("hue").unwrap();
+        let (text, encoding) = decode(bytes)?;
+        Ok(ContentChange::new("notes.txt".into(), text, encoding, &regex, &template).unwrap())
+    }
+
+    #[test]
+    fn skips_binary_and_unmatched_files() {
+        assert_eq!(change(b"color\0\x01").unwrap_err(), SkipReason::Binary);
+        assert!(change(b"nothing here").unwrap().is_none());
+    }
+
+    #[test]
+    fn round_trips_utf16_with_bom() {
+        let mut bytes = vec![0xFF, 0xFE];
+        "a colour\n"
+            .encode_utf16()
+            .for_each(|u| bytes.extend(u.to_le_bytes()));
+        let change = change(&bytes).unwrap().unwrap();
+        assert_eq!(change.encoding.name(), "UTF-16LE with BOM");
+        let mut expected = vec![0xFF, 0xFE];
+        "a hue\n"
+            .encode_utf16()
+            .for_each(|u| expected.extend(u.to_le_bytes()));
+        assert_eq!(change.encoded().unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_legacy_encodings() {
+        // "Farbe: colour – ü" in windows-1252.
+        let bytes = b"Farbe: colour \x96 \xfc\n";
+        let change = change(bytes).unwrap().unwrap();
+        assert_eq!(change.encoding.encoding, encoding_rs::WINDOWS_1252);
+        assert_eq!(change.new_text, "Farbe: hue – ü\n");
+        assert_eq!(change.encoded().unwrap(), b"Farbe: hue \x96 \xfc\n");
+    }
+
+    #[test]
+    fn diff_is_unified() {
+        let change = change(b"one\ncolor\nthree\n").unwrap().unwrap();
+        assert_eq!(change.replacements, 1);
+        assert_eq!(
+            change.diff(),
+            "--- a/notes.txt\n+++ b/notes.txt\n@@ -1,3 +1,3 @@\n one\n-color\n+hue\n three\n"
+        );
+    }
+}
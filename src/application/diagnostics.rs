@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use regex::Regex;
+use regex_automata::{
+    Input,
+    dfa::onepass,
+    hybrid, meta,
+    nfa::thompson::{self, NFA},
+};
+use web_time::Instant;
+
+use super::AppError;
+use super::error::compile_with_limits;
+use crate::domain::RegexLimits;
+
+/// How long the benchmark keeps matching the path list.
+pub const BENCHMARK_BUDGET: Duration = Duration::from_millis(200);
+
+/// Size and engine report for one rule's pattern.
+#[derive(Clone, Debug)]
+pub struct Diagnostics {
+    /// Time taken to build the `regex::Regex` used for renaming.
+    pub compile_time: Duration,
+    pub nfa_states: usize,
+    /// Heap usage of the compiled regex, as reported by `regex-automata`.
+    pub regex_memory: usize,
+    /// Heap usage of the scratch space each search needs.
+    pub cache_memory: usize,
+    /// Why the lazy DFA cannot be built; `None` when it can.
+    pub lazy_dfa_error: Option<String>,
+    /// Paths on which the lazy DFA quit or gave up, so the search fell back
+    /// to an NFA engine.
+    pub dfa_fallbacks: usize,
+    /// Whether the one-pass DFA can resolve capture groups.
+    pub one_pass: bool,
+    pub benchmark: Benchmark,
+}
+
+/// Result of matching the pattern against every path repeatedly.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Benchmark {
+    pub paths: usize,
+    pub matched: usize,
+    pub rounds: usize,
+    pub elapsed: Duration,
+}
+
+impl Benchmark {
+    /// Runs `regex` over `paths` until [`BENCHMARK_BUDGET`] has elapsed,
+    /// completing at least one round.
+    pub fn run(regex: &Regex, paths: &[String]) -> Self {
+        let mut benchmark = Benchmark {
+            paths: paths.len(),
+            ..Benchmark::default()
+        };
+        if paths.is_empty() {
+            return benchmark;
+        }
+        let start = Instant::now();
+        while benchmark.rounds == 0 || start.elapsed() < BENCHMARK_BUDGET {
+            benchmark.matched = paths.iter().filter(|p| regex.is_match(p)).count();
+            benchmark.rounds += 1;
+        }
+        benchmark.elapsed = start.elapsed();
+        benchmark
+    }
+
+    /// Paths searched per second.
+    pub fn searches_per_second(&self) -> f64 {
+        self.rate(self.paths)
+    }
+
+    /// Matching paths found per second.
+    pub fn matches_per_second(&self) -> f64 {
+        self.rate(self.matched)
+    }
+
+    fn rate(&self, per_round: usize) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        (per_round * self.rounds) as f64 / seconds
+    }
+}
+
+/// Compiles `pattern` with `limits`, inspects the engines the `regex` crate
+/// would pick for it and benchmarks it against `paths`.
+pub fn diagnose(
+    pattern: &str,
+    limits: RegexLimits,
+    paths: &[String],
+) -> Result<Diagnostics, AppError> {
+    let start = Instant::now();
+    let regex = compile_with_limits(pattern, limits)?;
+    let compile_time = start.elapsed();
+
+    // Mirror the configuration `regex::RegexBuilder` hands to the meta regex.
+    let internal = |e: &dyn std::error::Error| AppError::InvalidPattern {
+        pattern: pattern.to_string(),
+        message: e.to_string(),
+        span: None,
+    };
+    let meta = meta::Regex::builder()
+        .configure(
+            meta::Config::new()
+                .nfa_size_limit(Some(limits.size_limit))
+                .hybrid_cache_capacity(limits.dfa_size_limit)
+                .utf8_empty(true),
+        )
+        .build(pattern)
+        .map_err(|e| internal(&e))?;
+    let nfa = NFA::compiler()
+        .configure(thompson::Config::new().nfa_size_limit(Some(limits.size_limit)))
+        .build(pattern)
+        .map_err(|e| internal(&e))?;
+
+    let lazy_dfa = hybrid::dfa::DFA::builder()
+        .configure(
+            hybrid::dfa::Config::new()
+                .cache_capacity(limits.dfa_size_limit)
+                .unicode_word_boundary(true)
+                .minimum_cache_clear_count(Some(3))
+                .minimum_bytes_per_state(Some(10)),
+        )
+        .build_from_nfa(nfa.clone());
+    let (lazy_dfa_error, dfa_fallbacks) = match lazy_dfa {
+        Ok(dfa) => {
+            let mut cache = dfa.create_cache();
+            let fallbacks = paths
+                .iter()
+                .filter(|p| dfa.try_search_fwd(&mut cache, &Input::new(p)).is_err())
+                .count();
+            (None, fallbacks)
+        }
+        Err(e) => (Some(e.to_string()), 0),
+    };
+
+    Ok(Diagnostics {
+        compile_time,
+        nfa_states: nfa.states().len(),
+        regex_memory: meta.memory_usage(),
+        cache_memory: meta.create_cache().memory_usage(),
+        lazy_dfa_error,
+        dfa_fallbacks,
+        one_pass: onepass::DFA::builder().build_from_nfa(nfa).is_ok(),
+        benchmark: Benchmark::run(&regex, paths),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn reports_sizes_and_benchmark() {
+        let paths = paths(&["./a.txt", "./b.rs", "./c.txt"]);
+        let report = diagnose(r"^\./(\w)\.txt$", RegexLimits::default(), &paths).unwrap();
+        assert!(report.nfa_states > 0);
+        assert!(report.regex_memory > 0);
+        assert!(report.lazy_dfa_error.is_none());
+        assert_eq!(report.dfa_fallbacks, 0);
+        assert!(report.one_pass);
+        assert_eq!(report.benchmark.matched, 2);
+        assert!(report.benchmark.rounds >= 1);
+        assert!(report.benchmark.searches_per_second() > 0.0);
+    }
+
+    #[test]
+    fn unicode_word_boundary_falls_back_on_non_ascii_paths() {
+        let paths = paths(&["./plain.txt", "./café.txt"]);
+        let report = diagnose(r"\bcaf", RegexLimits::default(), &paths).unwrap();
+        assert_eq!(report.dfa_fallbacks, 1);
+    }
+
+    #[test]
+    fn tiny_dfa_limit_disables_the_lazy_dfa() {
+        let limits = RegexLimits {
+            dfa_size_limit: 16,
+            ..RegexLimits::default()
+        };
+        let report = diagnose(r"\w+", limits, &[]).unwrap();
+        assert!(report.lazy_dfa_error.is_some());
+        assert_eq!(report.benchmark.rounds, 0);
+    }
+}
@@ -0,0 +1,533 @@
+use std::fmt;
+use std::ops::Range;
+
+use super::AppError;
+use crate::domain::{Engine, RegexLimits};
+
+/// Byte ranges of the capture groups of one match. Index 0 is the whole
+/// match; `None` means the group did not participate.
+pub type GroupRanges = Vec<Option<Range<usize>>>;
+
+/// A compiled pattern, independent of the regex engine that compiled it.
+/// Searches fail only on backtracking engines that give up, such as
+/// fancy-regex past its backtrack limit, with [`AppError::Match`].
+pub trait PatternEngine: fmt::Debug + Send + Sync {
+    fn is_match(&self, haystack: &str) -> Result<bool, AppError>;
+
+    /// Groups of the leftmost match in `haystack`.
+    fn captures(&self, haystack: &str) -> Result<Option<GroupRanges>, AppError>;
+
+    /// Groups of every non-overlapping match in `haystack`, in order.
+    fn captures_all(&self, haystack: &str) -> Result<Vec<GroupRanges>, AppError>;
+
+    /// Number of groups, including group 0.
+    fn captures_len(&self) -> usize;
+
+    /// Name of every group, starting with the always unnamed group 0.
+    fn capture_names(&self) -> Vec<Option<String>>;
+
+    /// Whether the raw bytes `haystack` match. Engines without byte
+    /// support only match haystacks that are valid UTF-8.
+    fn is_match_bytes(&self, haystack: &[u8]) -> Result<bool, AppError> {
+        match std::str::from_utf8(haystack) {
+            Ok(text) => self.is_match(text),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Groups of the leftmost match in the raw bytes `haystack`.
+    fn captures_bytes(&self, haystack: &[u8]) -> Result<Option<GroupRanges>, AppError> {
+        match std::str::from_utf8(haystack) {
+            Ok(text) => self.captures(text),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Groups of every non-overlapping match in the raw bytes `haystack`.
+    fn captures_all_bytes(&self, haystack: &[u8]) -> Result<Vec<GroupRanges>, AppError> {
+        match std::str::from_utf8(haystack) {
+            Ok(text) => self.captures_all(text),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Ranges of every non-overlapping match in `haystack`.
+    fn find_all(&self, haystack: &str) -> Result<Vec<Range<usize>>, AppError> {
+        Ok(self
+            .captures_all(haystack)?
+            .into_iter()
+            .filter_map(|groups| groups.into_iter().next().flatten())
+            .collect())
+    }
+}
+
+/// Engines in the order the rule editor offers them.
+pub const ENGINES: [Engine; 3] = [Engine::Regex, Engine::Fancy, Engine::Lite];
+
+/// A syntax feature and the engines supporting it, indexed like [`ENGINES`].
+pub struct SyntaxFeature {
+    pub name: &'static str,
+    pub example: &'static str,
+    pub supported: [bool; 3],
+}
+
+/// Differences between the engines, shown next to the engine selector.
+pub const SYNTAX_FEATURES: [SyntaxFeature; 6] = [
+    SyntaxFeature {
+        name: "Look-ahead and look-behind",
+        example: r"(?=…) (?<!…)",
+        supported: [false, true, false],
+    },
+    SyntaxFeature {
+        name: "Backreferences",
+        example: r"\1 \k<name>",
+        supported: [false, true, false],
+    },
+    SyntaxFeature {
+        name: "Atomic groups and possessive repeats",
+        example: r"(?>…) a++",
+        supported: [false, true, false],
+    },
+    SyntaxFeature {
+        name: "Unicode classes and case folding",
+        example: r"\p{Greek} (?i)ß",
+        supported: [true, true, false],
+    },
+    SyntaxFeature {
+        name: "Guaranteed linear-time matching",
+        example: "",
+        supported: [true, false, true],
+    },
+    SyntaxFeature {
+        name: "Size limits",
+        example: "size_limit",
+        supported: [true, true, true],
+    },
+];
+
+pub fn engine_name(engine: Engine) -> &'static str {
+    match engine {
+        Engine::Regex => "regex",
+        Engine::Fancy => "fancy-regex",
+        Engine::Lite => "regex-lite",
+    }
+}
+
+/// Whether `engine` was compiled into this build.
+pub fn engine_available(engine: Engine) -> bool {
+    match engine {
+        Engine::Regex => true,
+        Engine::Fancy => cfg!(feature = "fancy"),
+        Engine::Lite => cfg!(feature = "lite"),
+    }
+}
+
+/// Compiles `pattern` with `engine`, applying `limits` where the engine
+/// supports them.
+pub fn compile_engine(
+    pattern: &str,
+    engine: Engine,
+    limits: RegexLimits,
+) -> Result<Box<dyn PatternEngine>, AppError> {
+    match engine {
+        Engine::Regex => {
+            let text = super::compile_with_limits(pattern, limits)?;
+            let bytes = regex::bytes::RegexBuilder::new(pattern)
+                .size_limit(limits.size_limit)
+                .dfa_size_limit(limits.dfa_size_limit)
+                .build()
+                .map_err(|error| AppError::InvalidPattern {
+                    pattern: pattern.to_string(),
+                    message: error.to_string(),
+                    span: None,
+                })?;
+            Ok(Box::new(RegexEngine { text, bytes }))
+        }
+        #[cfg(feature = "fancy")]
+        Engine::Fancy => fancy_regex::RegexBuilder::new(pattern)
+            .delegate_size_limit(limits.size_limit)
+            .delegate_dfa_size_limit(limits.dfa_size_limit)
+            .build()
+            .map(|re| Box::new(re) as Box<dyn PatternEngine>)
+            .map_err(|error| fancy_error(pattern, error)),
+        #[cfg(feature = "lite")]
+        Engine::Lite => regex_lite::RegexBuilder::new(pattern)
+            .size_limit(limits.size_limit)
+            .build()
+            .map(|re| Box::new(re) as Box<dyn PatternEngine>)
+            .map_err(|error| AppError::InvalidPattern {
+                pattern: pattern.to_string(),
+                message: error.to_string(),
+                span: None,
+            }),
+        #[allow(unreachable_patterns)]
+        _ => Err(AppError::InvalidPattern {
+            pattern: pattern.to_string(),
+            message: format!(
+                "the {} engine is not enabled in this build",
+                engine_name(engine)
+            ),
+            span: None,
+        }),
+    }
+}
+
+/// Wraps `engine` so it only searches the last component of each path:
+/// `^` anchors at the start of the file name and directories never match.
+pub fn name_only(engine: Box<dyn PatternEngine>) -> Box<dyn PatternEngine> {
+    Box::new(NameOnlyEngine { inner: engine })
+}
+
+/// Searches the text after the last `/` of each haystack. Group ranges
+/// refer to the whole haystack.
+#[derive(Debug)]
+struct NameOnlyEngine {
+    inner: Box<dyn PatternEngine>,
+}
+
+/// Byte offset of the file name in `haystack`.
+fn name_start(haystack: &[u8]) -> usize {
+    haystack
+        .iter()
+        .rposition(|b| *b == b'/')
+        .map_or(0, |slash| slash + 1)
+}
+
+/// Moves the ranges of `groups` forward by `offset`.
+fn shifted(groups: GroupRanges, offset: usize) -> GroupRanges {
+    groups
+        .into_iter()
+        .map(|group| group.map(|range| range.start + offset..range.end + offset))
+        .collect()
+}
+
+impl PatternEngine for NameOnlyEngine {
+    fn is_match(&self, haystack: &str) -> Result<bool, AppError> {
+        let start = name_start(haystack.as_bytes());
+        self.inner.is_match(&haystack[start..])
+    }
+
+    fn captures(&self, haystack: &str) -> Result<Option<GroupRanges>, AppError> {
+        let start = name_start(haystack.as_bytes());
+        Ok(self
+            .inner
+            .captures(&haystack[start..])?
+            .map(|groups| shifted(groups, start)))
+    }
+
+    fn captures_all(&self, haystack: &str) -> Result<Vec<GroupRanges>, AppError> {
+        let start = name_start(haystack.as_bytes());
+        Ok(self
+            .inner
+            .captures_all(&haystack[start..])?
+            .into_iter()
+            .map(|groups| shifted(groups, start))
+            .collect())
+    }
+
+    fn captures_len(&self) -> usize {
+        self.inner.captures_len()
+    }
+
+    fn capture_names(&self) -> Vec<Option<String>> {
+        self.inner.capture_names()
+    }
+
+    fn is_match_bytes(&self, haystack: &[u8]) -> Result<bool, AppError> {
+        self.inner.is_match_bytes(&haystack[name_start(haystack)..])
+    }
+
+    fn captures_bytes(&self, haystack: &[u8]) -> Result<Option<GroupRanges>, AppError> {
+        let start = name_start(haystack);
+        Ok(self
+            .inner
+            .captures_bytes(&haystack[start..])?
+            .map(|groups| shifted(groups, start)))
+    }
+
+    fn captures_all_bytes(&self, haystack: &[u8]) -> Result<Vec<GroupRanges>, AppError> {
+        let start = name_start(haystack);
+        Ok(self
+            .inner
+            .captures_all_bytes(&haystack[start..])?
+            .into_iter()
+            .map(|groups| shifted(groups, start))
+            .collect())
+    }
+}
+
+/// The `regex` engine, compiled once for text and once for raw bytes such
+/// as Unix paths that are not valid UTF-8.
+#[derive(Debug)]
+struct RegexEngine {
+    text: regex::Regex,
+    bytes: regex::bytes::Regex,
+}
+
+impl PatternEngine for RegexEngine {
+    fn is_match(&self, haystack: &str) -> Result<bool, AppError> {
+        Ok(self.text.is_match(haystack))
+    }
+
+    fn captures(&self, haystack: &str) -> Result<Option<GroupRanges>, AppError> {
+        PatternEngine::captures(&self.text, haystack)
+    }
+
+    fn captures_all(&self, haystack: &str) -> Result<Vec<GroupRanges>, AppError> {
+        self.text.captures_all(haystack)
+    }
+
+    fn captures_len(&self) -> usize {
+        self.text.captures_len()
+    }
+
+    fn capture_names(&self) -> Vec<Option<String>> {
+        PatternEngine::capture_names(&self.text)
+    }
+
+    fn is_match_bytes(&self, haystack: &[u8]) -> Result<bool, AppError> {
+        Ok(self.bytes.is_match(haystack))
+    }
+
+    fn captures_bytes(&self, haystack: &[u8]) -> Result<Option<GroupRanges>, AppError> {
+        Ok(self
+            .bytes
+            .captures(haystack)
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect()))
+    }
+
+    fn captures_all_bytes(&self, haystack: &[u8]) -> Result<Vec<GroupRanges>, AppError> {
+        Ok(self
+            .bytes
+            .captures_iter(haystack)
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect())
+            .collect())
+    }
+
+    fn find_all(&self, haystack: &str) -> Result<Vec<Range<usize>>, AppError> {
+        self.text.find_all(haystack)
+    }
+}
+
+impl PatternEngine for regex::Regex {
+    fn is_match(&self, haystack: &str) -> Result<bool, AppError> {
+        Ok(regex::Regex::is_match(self, haystack))
+    }
+
+    fn captures(&self, haystack: &str) -> Result<Option<GroupRanges>, AppError> {
+        Ok(regex::Regex::captures(self, haystack)
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect()))
+    }
+
+    fn captures_all(&self, haystack: &str) -> Result<Vec<GroupRanges>, AppError> {
+        Ok(regex::Regex::captures_iter(self, haystack)
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect())
+            .collect())
+    }
+
+    fn captures_len(&self) -> usize {
+        regex::Regex::captures_len(self)
+    }
+
+    fn capture_names(&self) -> Vec<Option<String>> {
+        regex::Regex::capture_names(self)
+            .map(|n| n.map(str::to_string))
+            .collect()
+    }
+
+    fn find_all(&self, haystack: &str) -> Result<Vec<Range<usize>>, AppError> {
+        Ok(regex::Regex::find_iter(self, haystack)
+            .map(|m| m.range())
+            .collect())
+    }
+}
+
+#[cfg(feature = "fancy")]
+impl PatternEngine for fancy_regex::Regex {
+    fn is_match(&self, haystack: &str) -> Result<bool, AppError> {
+        fancy_regex::Regex::is_match(self, haystack).map_err(|e| match_error(self, e))
+    }
+
+    fn captures(&self, haystack: &str) -> Result<Option<GroupRanges>, AppError> {
+        Ok(fancy_regex::Regex::captures(self, haystack)
+            .map_err(|e| match_error(self, e))?
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect()))
+    }
+
+    fn captures_all(&self, haystack: &str) -> Result<Vec<GroupRanges>, AppError> {
+        fancy_regex::Regex::captures_iter(self, haystack)
+            .map(|caps| {
+                caps.map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect())
+                    .map_err(|e| match_error(self, e))
+            })
+            .collect()
+    }
+
+    fn captures_len(&self) -> usize {
+        fancy_regex::Regex::captures_len(self)
+    }
+
+    fn capture_names(&self) -> Vec<Option<String>> {
+        fancy_regex::Regex::capture_names(self)
+            .map(|n| n.map(str::to_string))
+            .collect()
+    }
+}
+
+#[cfg(feature = "lite")]
+impl PatternEngine for regex_lite::Regex {
+    fn is_match(&self, haystack: &str) -> Result<bool, AppError> {
+        Ok(regex_lite::Regex::is_match(self, haystack))
+    }
+
+    fn captures(&self, haystack: &str) -> Result<Option<GroupRanges>, AppError> {
+        Ok(regex_lite::Regex::captures(self, haystack)
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect()))
+    }
+
+    fn captures_all(&self, haystack: &str) -> Result<Vec<GroupRanges>, AppError> {
+        Ok(regex_lite::Regex::captures_iter(self, haystack)
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect())
+            .collect())
+    }
+
+    fn captures_len(&self) -> usize {
+        regex_lite::Regex::captures_len(self)
+    }
+
+    fn capture_names(&self) -> Vec<Option<String>> {
+        regex_lite::Regex::capture_names(self)
+            .map(|n| n.map(str::to_string))
+            .collect()
+    }
+
+    fn find_all(&self, haystack: &str) -> Result<Vec<Range<usize>>, AppError> {
+        Ok(regex_lite::Regex::find_iter(self, haystack)
+            .map(|m| m.range())
+            .collect())
+    }
+}
+
+/// A search that fancy-regex gave up on, such as one exceeding the
+/// backtrack limit.
+#[cfg(feature = "fancy")]
+fn match_error(re: &fancy_regex::Regex, error: fancy_regex::Error) -> AppError {
+    AppError::Match {
+        pattern: re.as_str().to_string(),
+        message: error.to_string(),
+    }
+}
+
+/// Locates fancy-regex parse errors; patterns without fancy features are
+/// compiled by the `regex` crate, whose errors are located as usual.
+#[cfg(feature = "fancy")]
+fn fancy_error(pattern: &str, error: fancy_regex::Error) -> AppError {
+    use super::error::pattern_error;
+    use fancy_regex::{CompileError, Error};
+    match error {
+        Error::ParseError(position, kind) => AppError::InvalidPattern {
+            pattern: pattern.to_string(),
+            message: kind.to_string(),
+            span: Some(position..(position + 1).min(pattern.len())),
+        },
+        Error::CompileError(CompileError::InnerError(error)) => {
+            pattern_error(pattern, &error, regex_syntax::Parser::new())
+        }
+        other => AppError::InvalidPattern {
+            pattern: pattern.to_string(),
+            message: other.to_string(),
+            span: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(pattern: &str, engine: Engine) -> Box<dyn PatternEngine> {
+        compile_engine(pattern, engine, RegexLimits::default()).unwrap()
+    }
+
+    #[test]
+    fn engines_agree_on_plain_patterns() {
+        for engine in ENGINES.into_iter().filter(|e| engine_available(*e)) {
+            let re = compile(r"(?P<stem>\w+)\.(txt)", engine);
+            assert_eq!(re.captures_len(), 3, "{engine:?}");
+            assert_eq!(
+                re.capture_names(),
+                vec![None, Some("stem".to_string()), None]
+            );
+            assert_eq!(
+                re.captures("./a.txt").unwrap(),
+                Some(vec![Some(2..7), Some(2..3), Some(4..7)])
+            );
+            let found = re.find_all("a.txt b.txt").unwrap();
+            assert_eq!(found, vec![0..5, 6..11], "{engine:?}");
+        }
+    }
+
+    #[test]
+    fn only_the_regex_engine_matches_invalid_utf8() {
+        for engine in ENGINES.into_iter().filter(|e| engine_available(*e)) {
+            let re = compile(r"(\d+)\.txt$", engine);
+            assert_eq!(
+                re.captures_bytes(b"./caf\xE9/1.txt").unwrap().is_some(),
+                engine == Engine::Regex,
+                "{engine:?}"
+            );
+            assert_eq!(
+                re.captures_bytes(b"./cafe/1.txt").unwrap(),
+                Some(vec![Some(7..12), Some(7..8)])
+            );
+        }
+    }
+
+    #[test]
+    fn regex_engine_rejects_look_around() {
+        let err = compile_engine(r"foo(?=bar)", Engine::Regex, RegexLimits::default());
+        assert!(err.is_err());
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn fancy_supports_look_around_and_backreferences() {
+        let re = compile(r"(\w)\1(?=\.)", Engine::Fancy);
+        assert!(re.is_match("./aa.txt").unwrap());
+        assert!(!re.is_match("./ab.txt").unwrap());
+        let err = compile_engine(r"(?<=a", Engine::Fancy, RegexLimits::default()).unwrap_err();
+        assert!(err.span().is_some());
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn fancy_reports_the_backtrack_limit() {
+        let re = fancy_regex::RegexBuilder::new(r"(a+)+\1b")
+            .backtrack_limit(1000)
+            .build()
+            .unwrap();
+        let haystack = "a".repeat(30);
+        let error = PatternEngine::is_match(&re, &haystack).unwrap_err();
+        assert_eq!(error.kind(), "match");
+        assert!(re.captures_all(&haystack).is_err());
+    }
+
+    #[test]
+    fn name_only_engines_skip_directories() {
+        let re = name_only(compile(r"^(\w)_", Engine::Regex));
+        assert!(!re.is_match("./a_b/cd").unwrap());
+        assert_eq!(
+            re.captures("./a_b/c_d").unwrap(),
+            Some(vec![Some(6..8), Some(6..7)])
+        );
+        let re = name_only(compile("_", Engine::Regex));
+        assert_eq!(re.find_all("./a_b/c_d_e").unwrap(), vec![7..8, 9..10]);
+        assert_eq!(re.captures_all_bytes(b"./a_\xFF/c_\xFF").unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "lite")]
+    #[test]
+    fn lite_has_no_unicode_classes() {
+        assert!(compile_engine(r"\p{Greek}", Engine::Lite, RegexLimits::default()).is_err());
+    }
+}
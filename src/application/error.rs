@@ -0,0 +1,353 @@
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use super::os_path::display_path;
+use crate::domain::RegexLimits;
+
+/// Errors produced by the application layer.
+#[derive(Debug)]
+pub enum AppError {
+    /// A rule's `from` field is not a valid regular expression. `span` is the
+    /// byte range of the offending syntax when the parser reports one.
+    InvalidPattern {
+        pattern: String,
+        message: String,
+        span: Option<Range<usize>>,
+    },
+    /// A rule's `to` field is not a valid replacement template.
+    InvalidTemplate {
+        template: String,
+        message: String,
+        span: Option<Range<usize>>,
+    },
+    /// Walking the directory tree failed, optionally at a known path.
+    Walk {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// Moving a single entry failed.
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+        source: io::Error,
+    },
+    /// Reading, writing or restoring the contents of a file failed.
+    Rewrite { path: PathBuf, source: io::Error },
+    /// Removing an emptied directory, or recreating it on undo, failed.
+    Directory { path: PathBuf, source: io::Error },
+    /// A rule set file could not be read, parsed or written.
+    RuleFile { path: PathBuf, message: String },
+    /// Several entries would be moved to the same destination.
+    Conflict {
+        destination: PathBuf,
+        sources: Vec<PathBuf>,
+    },
+    /// A transcode rule names an encoding `encoding_rs` does not know.
+    UnknownEncoding { label: String },
+    /// A backtracking engine gave up on a search, e.g. at its backtrack
+    /// limit.
+    #[cfg(feature = "fancy")]
+    Match { pattern: String, message: String },
+}
+
+impl AppError {
+    /// Short machine-friendly name of the variant, used as a log field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::InvalidPattern { .. } => "invalid_pattern",
+            AppError::InvalidTemplate { .. } => "invalid_template",
+            AppError::Walk { .. } => "walk",
+            AppError::Move { .. } => "move",
+            AppError::Rewrite { .. } => "rewrite",
+            AppError::Directory { .. } => "directory",
+            AppError::RuleFile { .. } => "rule_file",
+            AppError::Conflict { .. } => "conflict",
+            AppError::UnknownEncoding { .. } => "unknown_encoding",
+            #[cfg(feature = "fancy")]
+            AppError::Match { .. } => "match",
+        }
+    }
+
+    /// Context of the error as key/value pairs, such as the pattern and
+    /// span of an invalid pattern or the paths and io kind of a failed move,
+    /// which structured loggers attach as fields.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let io_kind = |source: &io::Error| ("io_kind", format!("{:?}", source.kind()));
+        let mut fields = match self {
+            AppError::InvalidPattern { pattern, .. } => vec![("pattern", pattern.clone())],
+            AppError::InvalidTemplate { template, .. } => vec![("template", template.clone())],
+            AppError::Walk { path, source } => path
+                .iter()
+                .map(|path| ("path", display_path(path)))
+                .chain([io_kind(source)])
+                .collect(),
+            AppError::Move { from, to, source } => vec![
+                ("from", display_path(from)),
+                ("to", display_path(to)),
+                io_kind(source),
+            ],
+            AppError::Rewrite { path, source } | AppError::Directory { path, source } => {
+                vec![("path", display_path(path)), io_kind(source)]
+            }
+            AppError::RuleFile { path, .. } => vec![("path", display_path(path))],
+            AppError::Conflict {
+                destination,
+                sources,
+            } => vec![
+                ("destination", display_path(destination)),
+                ("sources", sources.len().to_string()),
+            ],
+            AppError::UnknownEncoding { label } => vec![("label", label.clone())],
+            #[cfg(feature = "fancy")]
+            AppError::Match { pattern, .. } => vec![("pattern", pattern.clone())],
+        };
+        if let Some(span) = self.span() {
+            fields.push(("span", format!("{span:?}")));
+        }
+        fields
+    }
+
+    /// Byte range within the rule field that caused the error, if known.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            AppError::InvalidPattern { span, .. } | AppError::InvalidTemplate { span, .. } => {
+                span.clone()
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders the offending field with a `^^^` marker under [`Self::span`],
+    /// suitable for a monospace tooltip.
+    pub fn pointer(&self) -> Option<String> {
+        let text = match self {
+            AppError::InvalidPattern { pattern, .. } => pattern,
+            AppError::InvalidTemplate { template, .. } => template,
+            _ => return None,
+        };
+        let span = self.span()?;
+        let start = text.get(..span.start)?.chars().count();
+        let width = text.get(span.clone())?.chars().count().max(1);
+        Some(format!(
+            "{text}\n{}{}",
+            " ".repeat(start),
+            "^".repeat(width)
+        ))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::InvalidPattern { message, .. } => write!(f, "invalid pattern: {message}"),
+            AppError::InvalidTemplate { message, .. } => {
+                write!(f, "invalid template: {message}")
+            }
+            AppError::Walk {
+                path: Some(path),
+                source,
+            } => write!(f, "cannot read '{}': {source}", display_path(path)),
+            AppError::Walk { path: None, source } => write!(f, "cannot walk tree: {source}"),
+            AppError::Move { from, to, source } => write!(
+                f,
+                "cannot move '{}' -> '{}': {source}",
+                display_path(from),
+                display_path(to)
+            ),
+            AppError::Rewrite { path, source } => {
+                write!(f, "cannot rewrite '{}': {source}", display_path(path))
+            }
+            AppError::Directory { path, source } => {
+                write!(
+                    f,
+                    "cannot update directory '{}': {source}",
+                    display_path(path)
+                )
+            }
+            AppError::Conflict {
+                destination,
+                sources,
+            } => write!(
+                f,
+                "{} entries would be moved to '{}'",
+                sources.len(),
+                display_path(destination)
+            ),
+            AppError::UnknownEncoding { label } => write!(f, "unknown encoding '{label}'"),
+            #[cfg(feature = "fancy")]
+            AppError::Match { pattern, message } => {
+                write!(f, "matching '{pattern}' failed: {message}")
+            }
+            AppError::RuleFile { path, message } => {
+                write!(f, "rule set '{}': {message}", display_path(path))
+            }
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Walk { source, .. }
+            | AppError::Move { source, .. }
+            | AppError::Rewrite { source, .. }
+            | AppError::Directory { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<walkdir::Error> for AppError {
+    fn from(error: walkdir::Error) -> Self {
+        let path = error.path().map(|p| p.to_path_buf());
+        AppError::Walk {
+            path,
+            source: error.into(),
+        }
+    }
+}
+
+/// An [`AppError`] attributed to the rule (by index) that caused it.
+#[derive(Debug)]
+pub struct RuleError {
+    pub rule: usize,
+    pub error: AppError,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rule #{}: {}", self.rule + 1, self.error)
+    }
+}
+
+impl std::error::Error for RuleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Compiles `pattern`, turning a failure into [`AppError::InvalidPattern`]
+/// with the span reported by `regex-syntax` where available.
+pub fn compile_pattern(pattern: &str) -> Result<regex::Regex, AppError> {
+    regex::Regex::new(pattern)
+        .map_err(|error| pattern_error(pattern, &error, regex_syntax::Parser::new()))
+}
+
+/// Compiles `pattern` like [`compile_pattern`] with the given size limits.
+pub fn compile_with_limits(pattern: &str, limits: RegexLimits) -> Result<regex::Regex, AppError> {
+    regex::RegexBuilder::new(pattern)
+        .size_limit(limits.size_limit)
+        .dfa_size_limit(limits.dfa_size_limit)
+        .build()
+        .map_err(|error| pattern_error(pattern, &error, regex_syntax::Parser::new()))
+}
+
+/// Converts a compile error into [`AppError::InvalidPattern`], re-parsing the
+/// pattern with `parser` (configured like the failed build) to locate it.
+pub(super) fn pattern_error(
+    pattern: &str,
+    error: &dyn fmt::Display,
+    mut parser: regex_syntax::Parser,
+) -> AppError {
+    let (message, span) = match parser.parse(pattern) {
+        Err(regex_syntax::Error::Parse(e)) => (
+            e.kind().to_string(),
+            Some(e.span().start.offset..e.span().end.offset),
+        ),
+        Err(regex_syntax::Error::Translate(e)) => (
+            e.kind().to_string(),
+            Some(e.span().start.offset..e.span().end.offset),
+        ),
+        // Syntactically valid but rejected by the compiler (e.g. size limits).
+        _ => (error.to_string(), None),
+    };
+    AppError::InvalidPattern {
+        pattern: pattern.to_string(),
+        message,
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_pattern_reports_span() {
+        let err = compile_pattern("foo(bar").unwrap_err();
+        assert_eq!(err.kind(), "invalid_pattern");
+        assert_eq!(err.span(), Some(3..4));
+        assert_eq!(err.to_string(), "invalid pattern: unclosed group");
+    }
+
+    #[test]
+    fn invalid_class_range_reports_span() {
+        let err = compile_pattern("[z-a]").unwrap_err();
+        assert_eq!(err.span(), Some(1..4));
+    }
+
+    #[test]
+    fn pointer_marks_offending_span() {
+        let err = compile_pattern("a{2,1}").unwrap_err();
+        assert_eq!(err.pointer().as_deref(), Some("a{2,1}\n ^^^^^"));
+    }
+
+    #[test]
+    fn fields_describe_the_context() {
+        let err = compile_pattern("foo(bar").unwrap_err();
+        assert_eq!(
+            err.fields(),
+            [
+                ("pattern", "foo(bar".to_string()),
+                ("span", "3..4".to_string())
+            ]
+        );
+        let err = AppError::Move {
+            from: PathBuf::from("./a"),
+            to: PathBuf::from("./b"),
+            source: io::ErrorKind::PermissionDenied.into(),
+        };
+        assert_eq!(
+            err.fields(),
+            [
+                ("from", "./a".to_string()),
+                ("to", "./b".to_string()),
+                ("io_kind", "PermissionDenied".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_pattern_compiles() {
+        assert!(compile_pattern(r"^(\w+)\.txt$").is_ok());
+    }
+
+    #[test]
+    fn size_limit_errors_have_no_span() {
+        let limits = RegexLimits {
+            size_limit: 1000,
+            ..RegexLimits::default()
+        };
+        let err = compile_with_limits(r"\w{50}", limits).unwrap_err();
+        assert_eq!(err.span(), None);
+        assert!(err.to_string().contains("size limit"), "{err}");
+        assert!(compile_with_limits(r"\w{50}", RegexLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn rule_error_display_is_one_based() {
+        let err = RuleError {
+            rule: 0,
+            error: AppError::Conflict {
+                destination: "out.txt".into(),
+                sources: vec!["a.txt".into(), "b.txt".into()],
+            },
+        };
+        assert_eq!(
+            err.to_string(),
+            "rule #1: 2 entries would be moved to 'out.txt'"
+        );
+    }
+}
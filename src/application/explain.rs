@@ -0,0 +1,358 @@
+use std::ops::Range;
+
+use regex_syntax::ast::{
+    self, Assertion, AssertionKind, Ast, ClassAsciiKind, ClassBracketed, ClassPerlKind, ClassSet,
+    ClassSetBinaryOpKind, ClassSetItem, ClassUnicodeKind, Flag, Flags, FlagsItemKind, GroupKind,
+    LiteralKind, RepetitionKind, RepetitionRange, SpecialLiteralKind,
+};
+
+use super::AppError;
+
+/// One node of a pattern's syntax tree with a plain-English description.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainNode {
+    pub description: String,
+    /// Byte range of the node in the pattern.
+    pub span: Range<usize>,
+    pub children: Vec<ExplainNode>,
+}
+
+impl ExplainNode {
+    fn leaf(description: impl Into<String>, span: &ast::Span) -> Self {
+        Self {
+            description: description.into(),
+            span: range(span),
+            children: Vec::new(),
+        }
+    }
+
+    fn branch(description: impl Into<String>, span: &ast::Span, children: Vec<Self>) -> Self {
+        Self {
+            description: description.into(),
+            span: range(span),
+            children,
+        }
+    }
+}
+
+/// Parses `pattern` with `regex-syntax` and describes every node of its AST.
+pub fn explain(pattern: &str) -> Result<ExplainNode, AppError> {
+    parse_ast(pattern).map(|ast| describe(&ast))
+}
+
+/// Parses `pattern` into its `regex-syntax` AST.
+pub(super) fn parse_ast(pattern: &str) -> Result<Ast, AppError> {
+    ast::parse::Parser::new()
+        .parse(pattern)
+        .map_err(|e| AppError::InvalidPattern {
+            pattern: pattern.to_string(),
+            message: e.kind().to_string(),
+            span: Some(range(e.span())),
+        })
+}
+
+fn range(span: &ast::Span) -> Range<usize> {
+    span.start.offset..span.end.offset
+}
+
+fn describe(node: &Ast) -> ExplainNode {
+    match node {
+        Ast::Empty(span) => ExplainNode::leaf("empty (matches the empty string)", span),
+        Ast::Flags(set) => ExplainNode::leaf(
+            format!("set flags for the rest of the group: {}", flags(&set.flags)),
+            &set.span,
+        ),
+        Ast::Literal(lit) => ExplainNode::leaf(literal(lit), &lit.span),
+        Ast::Dot(span) => ExplainNode::leaf("any character except a newline", span),
+        Ast::Assertion(assertion) => ExplainNode::leaf(self::assertion(assertion), &assertion.span),
+        Ast::ClassUnicode(class) => ExplainNode::leaf(
+            negate(class.is_negated(), unicode_class(&class.kind)),
+            &class.span,
+        ),
+        Ast::ClassPerl(class) => {
+            ExplainNode::leaf(negate(class.negated, perl_class(&class.kind)), &class.span)
+        }
+        Ast::ClassBracketed(class) => bracketed(class),
+        Ast::Repetition(rep) => {
+            let greed = if rep.greedy {
+                "as many times as possible"
+            } else {
+                "as few times as possible (lazy)"
+            };
+            ExplainNode::branch(
+                format!("repeat {}, {greed}", repetition(&rep.op.kind)),
+                &rep.span,
+                vec![describe(&rep.ast)],
+            )
+        }
+        Ast::Group(group) => {
+            let description = match &group.kind {
+                GroupKind::CaptureIndex(index) => format!("capture group {index}"),
+                GroupKind::CaptureName { name, .. } => {
+                    format!("capture group {} named '{}'", name.index, name.name)
+                }
+                GroupKind::NonCapturing(f) if f.items.is_empty() => {
+                    "non-capturing group".to_string()
+                }
+                GroupKind::NonCapturing(f) => {
+                    format!("non-capturing group with flags: {}", flags(f))
+                }
+            };
+            ExplainNode::branch(description, &group.span, vec![describe(&group.ast)])
+        }
+        Ast::Alternation(alt) => ExplainNode::branch(
+            format!("either of {} alternatives", alt.asts.len()),
+            &alt.span,
+            alt.asts.iter().map(describe).collect(),
+        ),
+        Ast::Concat(concat) => ExplainNode::branch(
+            format!("sequence of {} items", concat.asts.len()),
+            &concat.span,
+            concat.asts.iter().map(describe).collect(),
+        ),
+    }
+}
+
+fn negate(negated: bool, description: String) -> String {
+    if negated {
+        format!("any character that is not {description}")
+    } else {
+        description
+    }
+}
+
+fn literal(lit: &ast::Literal) -> String {
+    let named = match lit.kind {
+        LiteralKind::Special(SpecialLiteralKind::Bell) => Some("bell (\\x07)"),
+        LiteralKind::Special(SpecialLiteralKind::FormFeed) => Some("form feed"),
+        LiteralKind::Special(SpecialLiteralKind::Tab) => Some("tab"),
+        LiteralKind::Special(SpecialLiteralKind::LineFeed) => Some("newline"),
+        LiteralKind::Special(SpecialLiteralKind::CarriageReturn) => Some("carriage return"),
+        LiteralKind::Special(SpecialLiteralKind::VerticalTab) => Some("vertical tab"),
+        LiteralKind::Special(SpecialLiteralKind::Space) => Some("space"),
+        _ => None,
+    };
+    match (named, &lit.kind) {
+        (Some(name), _) => format!("the character {name}"),
+        (None, LiteralKind::Meta | LiteralKind::Superfluous) => {
+            format!("the literal character '{}' (escaped)", lit.c)
+        }
+        (None, LiteralKind::HexFixed(_) | LiteralKind::HexBrace(_) | LiteralKind::Octal) => {
+            format!(
+                "the character U+{:04X} '{}'",
+                lit.c as u32,
+                lit.c.escape_debug()
+            )
+        }
+        (None, _) => format!("the literal character '{}'", lit.c.escape_debug()),
+    }
+}
+
+fn assertion(assertion: &Assertion) -> String {
+    match assertion.kind {
+        AssertionKind::StartLine => "start of the text (or line, with the m flag)",
+        AssertionKind::EndLine => "end of the text (or line, with the m flag)",
+        AssertionKind::StartText => "start of the text",
+        AssertionKind::EndText => "end of the text",
+        AssertionKind::WordBoundary => "word boundary",
+        AssertionKind::NotWordBoundary => "not a word boundary",
+        AssertionKind::WordBoundaryStart | AssertionKind::WordBoundaryStartAngle => {
+            "start of a word"
+        }
+        AssertionKind::WordBoundaryEnd | AssertionKind::WordBoundaryEndAngle => "end of a word",
+        AssertionKind::WordBoundaryStartHalf => "start half of a word boundary",
+        AssertionKind::WordBoundaryEndHalf => "end half of a word boundary",
+    }
+    .to_string()
+}
+
+fn perl_class(kind: &ClassPerlKind) -> String {
+    match kind {
+        ClassPerlKind::Digit => "a digit",
+        ClassPerlKind::Space => "a whitespace character",
+        ClassPerlKind::Word => "a word character (letter, digit or underscore)",
+    }
+    .to_string()
+}
+
+fn ascii_class(kind: &ClassAsciiKind) -> &'static str {
+    match kind {
+        ClassAsciiKind::Alnum => "an ASCII letter or digit",
+        ClassAsciiKind::Alpha => "an ASCII letter",
+        ClassAsciiKind::Ascii => "an ASCII character",
+        ClassAsciiKind::Blank => "a space or tab",
+        ClassAsciiKind::Cntrl => "an ASCII control character",
+        ClassAsciiKind::Digit => "an ASCII digit",
+        ClassAsciiKind::Graph => "a visible ASCII character",
+        ClassAsciiKind::Lower => "a lowercase ASCII letter",
+        ClassAsciiKind::Print => "a printable ASCII character",
+        ClassAsciiKind::Punct => "ASCII punctuation",
+        ClassAsciiKind::Space => "ASCII whitespace",
+        ClassAsciiKind::Upper => "an uppercase ASCII letter",
+        ClassAsciiKind::Word => "an ASCII word character",
+        ClassAsciiKind::Xdigit => "a hexadecimal digit",
+    }
+}
+
+fn unicode_class(kind: &ClassUnicodeKind) -> String {
+    match kind {
+        ClassUnicodeKind::OneLetter(c) => format!("a character in Unicode category {c}"),
+        ClassUnicodeKind::Named(name) => format!("a character with Unicode property {name}"),
+        ClassUnicodeKind::NamedValue { name, value, .. } => {
+            format!("a character whose Unicode {name} is {value}")
+        }
+    }
+}
+
+fn bracketed(class: &ClassBracketed) -> ExplainNode {
+    let (description, children) = match &class.kind {
+        ClassSet::Item(item) => ("one character from the set", set_items(item)),
+        ClassSet::BinaryOp(op) => ("one character from the combined set", vec![set_op(op)]),
+    };
+    let description = if class.negated {
+        format!("{description}, negated: any character not listed")
+    } else {
+        description.to_string()
+    };
+    ExplainNode::branch(description, &class.span, children)
+}
+
+fn set_items(item: &ClassSetItem) -> Vec<ExplainNode> {
+    match item {
+        ClassSetItem::Union(union) => union.items.iter().map(set_item).collect(),
+        other => vec![set_item(other)],
+    }
+}
+
+fn set_item(item: &ClassSetItem) -> ExplainNode {
+    match item {
+        ClassSetItem::Empty(span) => ExplainNode::leaf("nothing", span),
+        ClassSetItem::Literal(lit) => ExplainNode::leaf(literal(lit), &lit.span),
+        ClassSetItem::Range(r) => ExplainNode::leaf(
+            format!(
+                "a character from '{}' to '{}'",
+                r.start.c.escape_debug(),
+                r.end.c.escape_debug()
+            ),
+            &r.span,
+        ),
+        ClassSetItem::Ascii(class) => ExplainNode::leaf(
+            negate(class.negated, ascii_class(&class.kind).to_string()),
+            &class.span,
+        ),
+        ClassSetItem::Unicode(class) => ExplainNode::leaf(
+            negate(class.is_negated(), unicode_class(&class.kind)),
+            &class.span,
+        ),
+        ClassSetItem::Perl(class) => {
+            ExplainNode::leaf(negate(class.negated, perl_class(&class.kind)), &class.span)
+        }
+        ClassSetItem::Bracketed(class) => bracketed(class),
+        ClassSetItem::Union(union) => ExplainNode::branch(
+            "any of",
+            &union.span,
+            union.items.iter().map(set_item).collect(),
+        ),
+    }
+}
+
+fn set_op(op: &ast::ClassSetBinaryOp) -> ExplainNode {
+    let description = match op.kind {
+        ClassSetBinaryOpKind::Intersection => "characters in both sets (&&)",
+        ClassSetBinaryOpKind::Difference => "characters in the first set but not the second (--)",
+        ClassSetBinaryOpKind::SymmetricDifference => "characters in exactly one of the sets (~~)",
+    };
+    let side = |set: &ClassSet| match set {
+        ClassSet::Item(item) => set_item(item),
+        ClassSet::BinaryOp(op) => set_op(op),
+    };
+    ExplainNode::branch(description, &op.span, vec![side(&op.lhs), side(&op.rhs)])
+}
+
+fn repetition(kind: &RepetitionKind) -> String {
+    match kind {
+        RepetitionKind::ZeroOrOne => "optionally (zero or one time)".to_string(),
+        RepetitionKind::ZeroOrMore => "zero or more times".to_string(),
+        RepetitionKind::OneOrMore => "one or more times".to_string(),
+        RepetitionKind::Range(RepetitionRange::Exactly(n)) => format!("exactly {n} time(s)"),
+        RepetitionKind::Range(RepetitionRange::AtLeast(n)) => format!("at least {n} time(s)"),
+        RepetitionKind::Range(RepetitionRange::Bounded(m, n)) => {
+            format!("between {m} and {n} times")
+        }
+    }
+}
+
+fn flags(flags: &Flags) -> String {
+    let mut out = Vec::new();
+    let mut negated = false;
+    for item in &flags.items {
+        match &item.kind {
+            FlagsItemKind::Negation => negated = true,
+            FlagsItemKind::Flag(flag) => {
+                let name = match flag {
+                    Flag::CaseInsensitive => "case-insensitive",
+                    Flag::MultiLine => "multi-line (^ and $ match at lines)",
+                    Flag::DotMatchesNewLine => "dot matches newline",
+                    Flag::SwapGreed => "swap greed",
+                    Flag::Unicode => "Unicode",
+                    Flag::CRLF => "CRLF line terminators",
+                    Flag::IgnoreWhitespace => "ignore whitespace",
+                };
+                out.push(if negated {
+                    format!("not {name}")
+                } else {
+                    name.to_string()
+                });
+            }
+        }
+    }
+    out.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_groups_and_repetitions() {
+        let tree = explain(r"(?P<year>\d{4})-(\w+)?").unwrap();
+        assert_eq!(tree.description, "sequence of 3 items");
+        let year = &tree.children[0];
+        assert_eq!(year.description, "capture group 1 named 'year'");
+        assert_eq!(year.span, 0..15);
+        assert_eq!(
+            year.children[0].description,
+            "repeat exactly 4 time(s), as many times as possible"
+        );
+        assert_eq!(year.children[0].children[0].description, "a digit");
+        assert_eq!(tree.children[1].description, "the literal character '-'");
+        assert_eq!(
+            tree.children[2].description,
+            "repeat optionally (zero or one time), as many times as possible"
+        );
+    }
+
+    #[test]
+    fn explains_alternations_classes_and_anchors() {
+        let tree = explain(r"^(?:jpe?g|[^a-z.])$").unwrap();
+        assert_eq!(
+            tree.children[0].description,
+            "start of the text (or line, with the m flag)"
+        );
+        let alternation = &tree.children[1].children[0];
+        assert_eq!(alternation.description, "either of 2 alternatives");
+        let class = &alternation.children[1];
+        assert_eq!(
+            class.description,
+            "one character from the set, negated: any character not listed"
+        );
+        assert_eq!(class.children[0].description, "a character from 'a' to 'z'");
+        assert_eq!(class.children[0].span, 12..15);
+    }
+
+    #[test]
+    fn reports_parse_errors_with_span() {
+        let err = explain("a(b").unwrap_err();
+        assert_eq!(err.span(), Some(1..2));
+    }
+}
@@ -0,0 +1,284 @@
+use std::borrow::Cow;
+
+use super::AppError;
+use crate::domain::RuleKind;
+
+/// The regex a rule pattern of the given kind stands for.
+pub fn rule_regex(pattern: &str, kind: RuleKind) -> Result<Cow<'_, str>, AppError> {
+    match kind {
+        RuleKind::Regex => Ok(Cow::Borrowed(pattern)),
+        RuleKind::Glob => glob_to_regex(pattern).map(Cow::Owned),
+    }
+}
+
+/// Translates a glob into an anchored regex over the walked paths (which
+/// start with `./`). Every wildcard becomes a numbered capture group, in
+/// order: `*` matches within one path component, `**/` any number of whole
+/// directories, `?` one character other than `/`, `[abc]`, `[a-z]` and
+/// `[!a]` one character of a class and `{jpg,jpeg}` one of the alternatives.
+/// `\` escapes the following character.
+pub fn glob_to_regex(glob: &str) -> Result<String, AppError> {
+    let body = glob.strip_prefix("./").unwrap_or(glob);
+    let offset = glob.len() - body.len();
+    let mut translator = Translator {
+        glob,
+        chars: body.char_indices().map(|(i, c)| (i + offset, c)).collect(),
+        pos: 0,
+    };
+    let inner = translator.sequence(true, false)?;
+    Ok(format!(r"^\./{inner}$"))
+}
+
+struct Translator<'a> {
+    glob: &'a str,
+    chars: Vec<(usize, char)>,
+    pos: usize,
+}
+
+impl Translator<'_> {
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.pos + ahead).map(|&(_, c)| c)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars
+            .get(self.pos)
+            .map_or(self.glob.len(), |&(i, _)| i)
+    }
+
+    /// Translates until the end of the glob or, inside braces, until the
+    /// next `,` or `}`. Groups are only captured at the top level.
+    fn sequence(&mut self, capture: bool, in_braces: bool) -> Result<String, AppError> {
+        let group = |body: &str| {
+            if capture {
+                format!("({body})")
+            } else {
+                format!("(?:{body})")
+            }
+        };
+        let mut out = String::new();
+        while let Some(c) = self.peek(0) {
+            let at_component_start = self.pos == 0 || self.chars[self.pos - 1].1 == '/';
+            match c {
+                ',' | '}' if in_braces => break,
+                '*' if self.peek(1) == Some('*') && at_component_start => match self.peek(2) {
+                    Some('/') => {
+                        out.push_str(&group("(?:[^/]*/)*"));
+                        self.pos += 3;
+                    }
+                    None => {
+                        out.push_str(&group(".*"));
+                        self.pos += 2;
+                    }
+                    _ => {
+                        out.push_str(&group("[^/]*"));
+                        self.pos += 2;
+                    }
+                },
+                '*' => {
+                    // Consecutive stars inside a component act as one.
+                    while self.peek(0) == Some('*') {
+                        self.pos += 1;
+                    }
+                    out.push_str(&group("[^/]*"));
+                }
+                '?' => {
+                    out.push_str(&group("[^/]"));
+                    self.pos += 1;
+                }
+                '[' => {
+                    let class = self.class()?;
+                    out.push_str(&group(&class));
+                }
+                '{' => {
+                    let start = self.offset();
+                    self.pos += 1;
+                    let mut alternatives = vec![self.sequence(false, true)?];
+                    loop {
+                        match self.peek(0) {
+                            Some(',') => {
+                                self.pos += 1;
+                                alternatives.push(self.sequence(false, true)?);
+                            }
+                            Some('}') => {
+                                self.pos += 1;
+                                break;
+                            }
+                            _ => return Err(self.error("unclosed '{'", start..self.glob.len())),
+                        }
+                    }
+                    out.push_str(&group(&alternatives.join("|")));
+                }
+                '\\' => {
+                    let start = self.offset();
+                    let Some(escaped) = self.peek(1) else {
+                        return Err(self.error("dangling '\\'", start..start + 1));
+                    };
+                    out.push_str(&regex::escape(escaped.encode_utf8(&mut [0; 4])));
+                    self.pos += 2;
+                }
+                c => {
+                    out.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Translates a bracket class starting at `[`. Classes never match `/`.
+    fn class(&mut self) -> Result<String, AppError> {
+        let start = self.offset();
+        self.pos += 1;
+        let mut out = String::from("[");
+        if matches!(self.peek(0), Some('!' | '^')) {
+            out.push('^');
+            self.pos += 1;
+        }
+        let negated = out.len() > 1;
+        let mut first = true;
+        loop {
+            let Some(c) = self.peek(0) else {
+                return Err(self.error("unclosed '['", start..self.glob.len()));
+            };
+            // A leading `]` is a member, as in shells.
+            if c == ']' && !first {
+                self.pos += 1;
+                break;
+            }
+            first = false;
+            let member_start = self.offset();
+            let end = match (self.peek(1), self.peek(2)) {
+                (Some('-'), Some(end)) if end != ']' => {
+                    self.pos += 3;
+                    end
+                }
+                _ => {
+                    self.pos += 1;
+                    c
+                }
+            };
+            if end < c {
+                return Err(self.error("invalid range in class", member_start..self.offset()));
+            }
+            // Split ranges around the separator so they never match it.
+            for (low, high) in [(c, end.min('.')), (c.max('0'), end)] {
+                if low <= high {
+                    out.push_str(&escape_class_char(low));
+                    if low != high {
+                        out.push('-');
+                        out.push_str(&escape_class_char(high));
+                    }
+                }
+            }
+        }
+        if negated {
+            out.push('/');
+        } else if out.len() == 1 {
+            return Err(self.error("class only matches '/'", start..self.offset()));
+        }
+        out.push(']');
+        Ok(out)
+    }
+
+    fn error(&self, message: &str, span: std::ops::Range<usize>) -> AppError {
+        AppError::InvalidPattern {
+            pattern: self.glob.to_string(),
+            message: message.to_string(),
+            span: Some(span),
+        }
+    }
+}
+
+fn escape_class_char(c: char) -> String {
+    regex::escape(c.encode_utf8(&mut [0; 4]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn glob(glob: &str) -> Regex {
+        Regex::new(&glob_to_regex(glob).unwrap()).unwrap()
+    }
+
+    fn groups<'h>(re: &Regex, path: &'h str) -> Option<Vec<&'h str>> {
+        let caps = re.captures(path)?;
+        Some(
+            caps.iter()
+                .skip(1)
+                .map(|m| m.map_or("", |m| m.as_str()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn star_stays_within_a_component() {
+        let re = glob("*.JPG");
+        assert_eq!(groups(&re, "./IMG_1.JPG"), Some(vec!["IMG_1"]));
+        assert_eq!(groups(&re, "./photos/IMG_1.JPG"), None);
+        assert_eq!(
+            glob_to_regex("./*.txt").unwrap(),
+            glob_to_regex("*.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn double_star_spans_directories() {
+        let re = glob("**/*.JPG");
+        assert_eq!(groups(&re, "./IMG.JPG"), Some(vec!["", "IMG"]));
+        assert_eq!(groups(&re, "./a/b/IMG.JPG"), Some(vec!["a/b/", "IMG"]));
+        assert_eq!(
+            groups(&glob("src/**"), "./src/a/b.rs"),
+            Some(vec!["a/b.rs"])
+        );
+    }
+
+    #[test]
+    fn question_mark_classes_and_braces() {
+        let re = glob("IMG_??[0-9].{jpg,jpeg}");
+        assert_eq!(
+            groups(&re, "./IMG_ab7.jpeg"),
+            Some(vec!["a", "b", "7", "jpeg"])
+        );
+        assert_eq!(groups(&re, "./IMG_abc.jpg"), None);
+        assert!(glob("[!a]*").is_match("./b.txt"));
+        assert!(!glob("[!a]*").is_match("./a.txt"));
+        assert!(!glob("a[+-0]b").is_match("./a/b"));
+        assert!(glob("a[+-0]b").is_match("./a.b"));
+    }
+
+    #[test]
+    fn wildcards_inside_braces_are_not_numbered() {
+        let re = glob("{*.md,docs/*}");
+        assert_eq!(re.captures_len(), 2);
+        assert!(re.is_match("./docs/x"));
+    }
+
+    #[test]
+    fn escapes_and_regex_metacharacters_are_literal() {
+        let re = glob(r"a+b (1)\*.txt");
+        assert!(re.is_match("./a+b (1)*.txt"));
+        assert!(!re.is_match("./aab (1)x.txt"));
+    }
+
+    #[test]
+    fn translations_compile_with_every_engine() {
+        use crate::application::{ENGINES, compile_engine, engine_available};
+        use crate::domain::RegexLimits;
+        let regex = glob_to_regex("**/[!.]?[a-z-]*.{jpg,JPG}").unwrap();
+        for engine in ENGINES.into_iter().filter(|e| engine_available(*e)) {
+            let re = compile_engine(&regex, engine, RegexLimits::default()).unwrap();
+            assert!(re.is_match("./a/xy-z.JPG").unwrap(), "{engine:?}");
+        }
+    }
+
+    #[test]
+    fn reports_unclosed_brackets_and_bad_ranges() {
+        assert_eq!(glob_to_regex("a[bc").unwrap_err().span(), Some(1..4));
+        assert_eq!(glob_to_regex("{a,b").unwrap_err().span(), Some(0..4));
+        assert_eq!(glob_to_regex("[z-a]").unwrap_err().span(), Some(1..4));
+        assert_eq!(glob_to_regex("a[/]").unwrap_err().span(), Some(1..4));
+    }
+}
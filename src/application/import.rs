@@ -0,0 +1,914 @@
+use super::{compile_engine, compile_rule, engine_available};
+use crate::domain::{Engine, RegexLimits, Rule, RuleKind};
+
+/// Rules translated from shell commands, with what could not be carried
+/// over.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Import {
+    pub rules: Vec<Rule>,
+    /// Dropped commands and approximations, prefixed with their line.
+    pub warnings: Vec<String>,
+}
+
+/// Perl `rename` and its common packaged names.
+const RENAME_COMMANDS: [&str; 4] = ["rename", "prename", "perl-rename", "file-rename"];
+
+/// Translates every Perl `rename 's/…/…/flags'` and `mmv 'from' 'to'`
+/// command in `text`, one per line, into rules. A line may also be a bare
+/// `s///` expression. Empty lines and `#` comments are skipped.
+pub fn import_commands(text: &str) -> Import {
+    let mut import = Import::default();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut warn = |message: String| {
+            import
+                .warnings
+                .push(format!("line {}: {message}", index + 1))
+        };
+        match import_line(line, &mut warn) {
+            Ok(rules) => {
+                for rule in rules {
+                    if let Err(e) = compile_rule(&rule) {
+                        warn(format!("imported '{}' does not compile: {e}", rule.from));
+                    }
+                    import.rules.push(rule);
+                }
+            }
+            Err(message) => warn(message),
+        }
+    }
+    import
+}
+
+fn import_line(line: &str, warn: &mut dyn FnMut(String)) -> Result<Vec<Rule>, String> {
+    if is_substitution(line) {
+        return perl_substitution(line).map(|rule| vec![rule]);
+    }
+    let words = shell_words(line)?;
+    let command = words[0].rsplit('/').next().unwrap_or_default();
+    if RENAME_COMMANDS.contains(&command) {
+        perl_rename(&words[1..], warn)
+    } else if command == "mmv" {
+        mmv(&words[1..], warn).map(|rule| vec![rule])
+    } else {
+        Err(format!("'{command}' is not a rename or mmv command"))
+    }
+}
+
+/// Splits a command line like a POSIX shell: single quotes keep everything,
+/// double quotes and bare words honor `\`.
+fn shell_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("unclosed single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Inside double quotes, `\` only escapes these.
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("unclosed double quote".to_string()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("unclosed double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    word.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Rules of a Perl `rename` invocation: one per `-e` expression, or the
+/// first argument. File arguments are dropped, as rules apply to every
+/// walked path.
+fn perl_rename(args: &[String], warn: &mut dyn FnMut(String)) -> Result<Vec<Rule>, String> {
+    let mut expressions = Vec::new();
+    let mut operands = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-e" | "-E" => expressions.push(args.next().ok_or("-e needs an expression")?),
+            "--" => {
+                operands.extend(args.by_ref());
+            }
+            "-v" | "-n" | "-f" | "-i" | "--verbose" | "--nono" | "--dry-run" | "--force" => {}
+            option if option.starts_with('-') && option.len() > 1 => {
+                warn(format!("option {option} is ignored"));
+            }
+            _ => operands.push(arg),
+        }
+    }
+    if expressions.is_empty() {
+        if operands.is_empty() {
+            return Err("rename needs an expression".to_string());
+        }
+        expressions.push(operands.remove(0));
+    }
+    if !operands.is_empty() {
+        warn("file arguments are ignored; rules apply to every path they match".to_string());
+    }
+    expressions
+        .into_iter()
+        .map(|expression| perl_substitution(expression))
+        .collect()
+}
+
+fn is_substitution(expression: &str) -> bool {
+    let mut chars = expression.trim().chars();
+    chars.next() == Some('s')
+        && chars
+            .next()
+            .is_some_and(|c| !c.is_alphanumeric() && !c.is_whitespace())
+}
+
+/// A parsed `s/pattern/replacement/flags` expression.
+#[derive(Debug, PartialEq, Eq)]
+struct Substitution {
+    pattern: String,
+    replacement: String,
+    flags: String,
+}
+
+fn parse_substitution(expression: &str) -> Result<Substitution, String> {
+    let expression = expression.trim().trim_end_matches(';').trim_end();
+    if expression.starts_with("tr") || expression.starts_with('y') {
+        return Err("transliterations (tr/// and y///) are not supported".to_string());
+    }
+    let Some(rest) = expression.strip_prefix('s') else {
+        return Err(format!("'{expression}' is not an s/// substitution"));
+    };
+    let mut chars = rest.chars().peekable();
+    let pattern = delimited(&mut chars)?;
+    // Bracketing delimiters open the replacement anew: `s{a} {b}`.
+    if is_bracket(rest.chars().next()) {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let replacement = delimited(&mut chars)?;
+        let flags = chars.collect();
+        return Ok(Substitution {
+            pattern,
+            replacement,
+            flags,
+        });
+    }
+    let delimiter = rest.chars().next().expect("the pattern was delimited");
+    let replacement = until(&mut chars, delimiter, delimiter)?;
+    Ok(Substitution {
+        pattern,
+        replacement,
+        flags: chars.collect(),
+    })
+}
+
+fn is_bracket(c: Option<char>) -> bool {
+    matches!(c, Some('(' | '[' | '{' | '<'))
+}
+
+/// Reads a part opened by the next character, up to its closing delimiter.
+fn delimited(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    let open = match chars.next() {
+        Some(c) if !c.is_alphanumeric() && !c.is_whitespace() => c,
+        _ => return Err("expected a delimiter after 's'".to_string()),
+    };
+    let close = match open {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        c => c,
+    };
+    until(chars, open, close)
+}
+
+/// Reads up to the unescaped `close`, balancing nested `open` brackets.
+/// An escaped delimiter that is not a regex metacharacter loses its `\`.
+fn until(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    open: char,
+    close: char,
+) -> Result<String, String> {
+    let mut out = String::new();
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(d) if (d == open || d == close) && !is_meta(d) => out.push(d),
+                Some(d) => {
+                    out.push('\\');
+                    out.push(d);
+                }
+                None => break,
+            },
+            c if c == close && depth == 0 => return Ok(out),
+            c => {
+                if open != close {
+                    if c == open {
+                        depth += 1;
+                    } else if c == close {
+                        depth -= 1;
+                    }
+                }
+                out.push(c);
+            }
+        }
+    }
+    Err(format!("missing closing '{close}'"))
+}
+
+fn is_meta(c: char) -> bool {
+    r"\.+*?()|[]{}^$#&-~".contains(c)
+}
+
+/// Translates a Perl substitution into a rename rule.
+///
+/// Perl `rename` only sees the names it is given, never the directories the
+/// walk adds, so the translated pattern never matches `/` and only matches
+/// inside the last component:
+///
+/// - `s/^…//` becomes `^(.*/)?(…)([^/]*)$`, shifting Perl's groups by two.
+/// - `s/…//` becomes `^(.*/)?([^/]*?)(…)([^/]*)$`, shifting them by three.
+/// - `s/…//g` stays `…` and only matches the file name through
+///   [`Rule::name_only`].
+///
+/// The replacement puts the directory and the rest of the name back.
+fn perl_substitution(expression: &str) -> Result<Rule, String> {
+    let substitution = parse_substitution(expression)?;
+    let mut inline = String::new();
+    let mut replace_all = false;
+    for flag in substitution.flags.chars() {
+        match flag {
+            'g' => replace_all = true,
+            'i' | 'x' | 'm' | 's' => inline.push(flag),
+            'e' => return Err("the e flag evaluates Perl code and is not supported".to_string()),
+            other => return Err(format!("unsupported flag '{other}'")),
+        }
+    }
+    let (anchored, body) = match substitution.pattern.strip_prefix('^') {
+        Some(body) => (true, body),
+        None => match substitution.pattern.strip_prefix(r"\A") {
+            Some(body) => (true, body),
+            None => (false, substitution.pattern.as_str()),
+        },
+    };
+    // Every match of `s///g` is replaced, so the rule keeps to the name.
+    let global = replace_all && !anchored;
+    let shift = match (anchored, global) {
+        (true, _) => 2,
+        (false, false) => 3,
+        (false, true) => 0,
+    };
+    let (body, fancy) = translate_pattern(body, shift, inline.contains('x'))?;
+    let engine = if fancy {
+        if !engine_available(Engine::Fancy) {
+            return Err(
+                "this pattern needs the fancy-regex engine for look-around or backreferences"
+                    .to_string(),
+            );
+        }
+        Engine::Fancy
+    } else {
+        Engine::Regex
+    };
+    let inline = if inline.is_empty() {
+        inline
+    } else {
+        format!("(?{inline})")
+    };
+    // The group after the body holds the rest of the name. Empty groups
+    // stand in for the shifted ones so backreferences still compile.
+    let shifted = format!("{inline}{}{body}", "()".repeat(shift));
+    let rest = compile_engine(&shifted, engine, RegexLimits::default())
+        .map_or(shift, |re| re.captures_len() - 1)
+        + 1;
+    let replacement = translate_replacement(&substitution.replacement, shift)?;
+    let (from, to) = match (anchored, global) {
+        (true, _) => (
+            format!("{inline}^(.*/)?({body})([^/]*)$"),
+            format!("${{1}}{replacement}${{{rest}}}"),
+        ),
+        (false, false) => (
+            format!("{inline}^(.*/)?([^/]*?)({body})([^/]*)$"),
+            format!("${{1}}${{2}}{replacement}${{{rest}}}"),
+        ),
+        (false, true) => (format!("{inline}{body}"), replacement),
+    };
+    Ok(Rule {
+        from,
+        to,
+        engine,
+        replace_all: global,
+        name_only: global,
+        ..Rule::default()
+    })
+}
+
+/// Translates Perl-only pattern syntax, renumbering backreferences by
+/// `shift`. Wildcards, negated classes and classes with ranges, escapes or
+/// `/` are narrowed so they never match `/`, and a literal `/` is rejected.
+/// With `extended` (`/x`), `#` comments are dropped, as the translation
+/// ends up on one line. Returns whether the pattern needs the backtracking
+/// engine.
+fn translate_pattern(
+    pattern: &str,
+    shift: usize,
+    extended: bool,
+) -> Result<(String, bool), String> {
+    let mut out = String::new();
+    let mut fancy = false;
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let Some(escaped) = chars.next() else {
+                    return Err("pattern ends with a lone '\\'".to_string());
+                };
+                match escaped {
+                    'Q' => {
+                        let mut literal = String::new();
+                        while let Some(c) = chars.next() {
+                            if c == '\\' && chars.next_if_eq(&'E').is_some() {
+                                break;
+                            }
+                            literal.push(c);
+                        }
+                        out.push_str(&regex::escape(&literal));
+                    }
+                    'E' => {}
+                    'Z' => out.push_str(r"\z"),
+                    '1'..='9' => {
+                        let number = digits(escaped, &mut chars);
+                        out.push_str(&format!(r"\{}", number + shift));
+                        fancy = true;
+                    }
+                    'k' => {
+                        out.push_str(r"\k");
+                        fancy = true;
+                    }
+                    'G' | 'K' | 'X' | 'R' | 'C' | 'h' | 'H' | 'v' | 'V' | 'g' => {
+                        return Err(format!("'\\{escaped}' is not supported"));
+                    }
+                    'D' | 'W' | 'S' => {
+                        let class = escaped.to_ascii_lowercase();
+                        out.push_str(&format!(r"[^\{class}/]"));
+                    }
+                    '/' => return Err(SLASH.to_string()),
+                    'p' | 'P' => {
+                        let property = match chars.next_if_eq(&'{') {
+                            Some(_) => {
+                                let name: String =
+                                    chars.by_ref().take_while(|c| *c != '}').collect();
+                                format!("{{{name}}}")
+                            }
+                            None => chars.next().map(String::from).unwrap_or_default(),
+                        };
+                        out.push_str(&format!(r"[\{escaped}{property}&&[^/]]"));
+                    }
+                    other => {
+                        out.push('\\');
+                        out.push(other);
+                    }
+                }
+            }
+            '[' => {
+                let mut class = String::new();
+                // A `]` right after `[` or `[^` is a member.
+                let negated = chars.next_if_eq(&'^').is_some();
+                if let Some(bracket) = chars.next_if_eq(&']') {
+                    class.push('\\');
+                    class.push(bracket);
+                }
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some('\\') => {
+                            class.push('\\');
+                            class.extend(chars.next());
+                        }
+                        Some(c) => class.push(c),
+                        None => return Err("unclosed '['".to_string()),
+                    }
+                }
+                if negated {
+                    out.push_str(&format!("[[^{class}]&&[^/]]"));
+                } else if class.contains(['-', '\\', '/']) {
+                    out.push_str(&format!("[[{class}]&&[^/]]"));
+                } else {
+                    out.push_str(&format!("[{class}]"));
+                }
+            }
+            '(' if chars.peek() == Some(&'?') || chars.peek() == Some(&'*') => {
+                let ahead: String = chars.clone().take(3).collect();
+                if ahead.starts_with("?=")
+                    || ahead.starts_with("?!")
+                    || ahead.starts_with("?<=")
+                    || ahead.starts_with("?<!")
+                    || ahead.starts_with("?>")
+                {
+                    fancy = true;
+                } else if ahead.starts_with("?{")
+                    || ahead.starts_with("??")
+                    || ahead.starts_with("?R")
+                    || ahead.starts_with("?&")
+                    || ahead.starts_with("?|")
+                    || ahead.starts_with("?(")
+                    || ahead.starts_with('*')
+                    || ahead.chars().nth(1).is_some_and(|c| c.is_ascii_digit())
+                {
+                    return Err(format!("'({ahead}…' is not supported"));
+                }
+                out.push('(');
+            }
+            '$' if chars
+                .peek()
+                .is_some_and(|c| c.is_alphabetic() || *c == '{' || *c == '_') =>
+            {
+                return Err("variables in patterns are not supported".to_string());
+            }
+            '.' => out.push_str("[^/]"),
+            '/' => return Err(SLASH.to_string()),
+            '#' if extended => chars.by_ref().take_while(|c| *c != '\n').for_each(drop),
+            c => out.push(c),
+        }
+    }
+    Ok((out, fancy))
+}
+
+const SLASH: &str = "a '/' in the pattern can never match inside a file name";
+
+/// Reads the rest of a number whose first digit is `first`.
+fn digits(first: char, chars: &mut std::iter::Peekable<std::str::Chars>) -> usize {
+    let mut number = first.to_digit(10).expect("a digit") as usize;
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        number = number * 10 + digit.to_digit(10).expect("a digit") as usize;
+    }
+    number
+}
+
+/// Translates a Perl replacement into a template, renumbering groups by
+/// `shift`. `\L…\E` around a single group becomes `${lower(…)}`.
+fn translate_replacement(replacement: &str, shift: usize) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => match group_reference(&mut chars, shift)? {
+                Some(group) => out.push_str(&format!("${{{group}}}")),
+                None => out.push_str("$$"),
+            },
+            '\\' => match chars.next() {
+                Some(digit @ '1'..='9') => {
+                    out.push_str(&format!("${{{}}}", digits(digit, &mut chars) + shift))
+                }
+                Some('L') => {
+                    let mut inner = String::new();
+                    while let Some(c) = chars.next() {
+                        if c == '\\' && chars.next_if_eq(&'E').is_some() {
+                            break;
+                        }
+                        inner.push(c);
+                    }
+                    let mut inner_chars = inner.chars().peekable();
+                    let group = match inner_chars.next() {
+                        Some('$') => group_reference(&mut inner_chars, shift)?,
+                        Some('\\') => inner_chars
+                            .next()
+                            .filter(char::is_ascii_digit)
+                            .map(|digit| (digits(digit, &mut inner_chars) + shift).to_string()),
+                        _ => None,
+                    };
+                    match group {
+                        Some(group) if inner_chars.next().is_none() => {
+                            out.push_str(&format!("${{lower({group})}}"));
+                        }
+                        _ => return Err("\\L is only supported around a single group".to_string()),
+                    }
+                }
+                Some('E') => {}
+                Some(case @ ('U' | 'u' | 'l')) => {
+                    return Err(format!("'\\{case}' case changes are not supported"));
+                }
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('$') => out.push_str("$$"),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Reads what follows a `$` in a replacement: a numbered group, `$&` or
+/// `$+{name}`. Returns `None` for a lone `$`.
+fn group_reference(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    shift: usize,
+) -> Result<Option<String>, String> {
+    match chars.peek().copied() {
+        Some(digit @ '0'..='9') => {
+            chars.next();
+            Ok(Some((digits(digit, chars) + shift).to_string()))
+        }
+        Some('&') => {
+            chars.next();
+            // The whole Perl match is the group right before its own groups.
+            Ok(Some(shift.to_string()))
+        }
+        Some('{') => {
+            chars.next();
+            let inner: String = chars.by_ref().take_while(|c| *c != '}').collect();
+            match inner.parse::<usize>() {
+                Ok(number) => Ok(Some((number + shift).to_string())),
+                Err(_) => Err(format!("variable ${{{inner}}} is not supported")),
+            }
+        }
+        Some('+') => {
+            chars.next();
+            if chars.next() != Some('{') {
+                return Err("only $+{name} is supported after $+".to_string());
+            }
+            Ok(Some(chars.by_ref().take_while(|c| *c != '}').collect()))
+        }
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            let name: String = chars
+                .clone()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            Err(format!("variable ${name} is not supported"))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Translates an `mmv` invocation into a glob rule. mmv numbers its
+/// wildcards like globs number their groups, so `#1` becomes `${1}`.
+fn mmv(args: &[String], warn: &mut dyn FnMut(String)) -> Result<Rule, String> {
+    let mut patterns = Vec::new();
+    let mut rename_only = false;
+    for arg in args {
+        match arg.as_str() {
+            "-m" | "-x" | "-v" | "-n" => {}
+            "-r" => rename_only = true,
+            "-c" | "-o" | "-a" | "-l" | "-s" => {
+                return Err(format!(
+                    "mmv {arg} does not move files and is not supported"
+                ));
+            }
+            option if option.starts_with('-') && option.len() > 1 => {
+                warn(format!("option {option} is ignored"));
+            }
+            _ => patterns.push(arg),
+        }
+    }
+    let [from, to] = patterns.as_slice() else {
+        return Err("mmv needs a from and a to pattern".to_string());
+    };
+    // With -r the target is a new name in the directory of each match.
+    let to = if rename_only {
+        if to.contains('/') {
+            return Err("mmv -r does not allow a directory in the to pattern".to_string());
+        }
+        format!("{}{to}", mmv_directory(from))
+    } else {
+        to.to_string()
+    };
+    Ok(Rule {
+        from: mmv_glob(from)?,
+        to: mmv_template(&to)?,
+        kind: RuleKind::Glob,
+        ..Rule::default()
+    })
+}
+
+/// Rewrites an mmv `from` pattern as a glob: `;` becomes `**/` and brace
+/// characters are escaped.
+fn mmv_glob(pattern: &str) -> Result<String, String> {
+    if pattern.starts_with('/') {
+        return Err("absolute mmv patterns are not supported".to_string());
+    }
+    if pattern.contains("**") {
+        return Err("consecutive '*' wildcards are not supported".to_string());
+    }
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => out.push_str("**/"),
+            '{' | '}' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\\' => {
+                out.push('\\');
+                out.extend(chars.next());
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// The directory part of an mmv `from` pattern as a `to` pattern, with
+/// each wildcard replaced by its `#N`.
+fn mmv_directory(pattern: &str) -> String {
+    let Some(end) = pattern.rfind('/') else {
+        return String::new();
+    };
+    let mut out = String::new();
+    let mut wildcards = 0;
+    let mut chars = pattern[..=end].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '?' | ';' | '[' => {
+                if c == '[' {
+                    chars.by_ref().take_while(|c| *c != ']').for_each(drop);
+                }
+                wildcards += 1;
+                out.push_str(&format!("#{wildcards}"));
+            }
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Rewrites an mmv `to` pattern as a template: `#N` inserts a wildcard and
+/// `#lN` its lowercase text.
+fn mmv_template(pattern: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => {
+                let lower = chars.next_if_eq(&'l').is_some();
+                if chars.peek() == Some(&'u') {
+                    return Err("#u (uppercase) is not supported".to_string());
+                }
+                let Some(digit) = chars.next_if(char::is_ascii_digit) else {
+                    return Err("'#' must be followed by a wildcard number".to_string());
+                };
+                let number = digits(digit, &mut chars);
+                if lower {
+                    out.push_str(&format!("${{lower({number})}}"));
+                } else {
+                    out.push_str(&format!("${{{number}}}"));
+                }
+            }
+            '$' => out.push_str("$$"),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Where the single rule imported from `command` moves `path`.
+    fn renamed(command: &str, path: &str) -> String {
+        let import = import_commands(command);
+        assert_eq!(import.warnings, Vec::<String>::new());
+        let [rule] = import.rules.as_slice() else {
+            panic!("expected one rule from {command}");
+        };
+        let compiled = compile_rule(rule).unwrap();
+        compiled
+            .destination(Path::new(path), None)
+            .unwrap()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn translates_perl_substitutions() {
+        assert_eq!(
+            renamed(r"rename 's/foo(\d+)/bar$1/'", "./a/foo12.txt"),
+            "./a/bar12.txt"
+        );
+        assert_eq!(renamed("rename 's/_/ /g'", "./a_b_c"), "./a b c");
+        assert_eq!(renamed("rename -v 's/JPEG$/jpg/i'", "./x.jpeg"), "./x.jpg");
+        assert_eq!(renamed(r"s{\.jpeg$} {.jpg}", "./x.jpeg"), "./x.jpg");
+        assert_eq!(
+            renamed(r"rename 's/ (\d+) \. txt/-$1.md/x'", "./n7.txt"),
+            "./n-7.md"
+        );
+        assert_eq!(
+            renamed(r#"rename "s/(\w+)\.TXT/\L\$1\E.txt/""#, "./A/README.TXT"),
+            "./A/readme.txt"
+        );
+        assert_eq!(renamed(r"rename 's/(?<n>\d+)/[$+{n}]/'", "./v2"), "./v[2]");
+    }
+
+    #[test]
+    fn leading_anchors_apply_to_the_file_name() {
+        assert_eq!(
+            renamed("rename 's/^IMG_//'", "./IMG/IMG_1.jpg"),
+            "./IMG/1.jpg"
+        );
+        assert_eq!(
+            renamed("rename 's/^(\\d)-/0$1-/'", "./cd/1-intro.mp3"),
+            "./cd/01-intro.mp3"
+        );
+        assert_eq!(renamed("rename 's/^/x_/'", "./a/b.txt"), "./a/x_b.txt");
+        assert_eq!(renamed("rename 's/^a.*/[$&]/'", "./d/abc"), "./d/[abc]");
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn backreferences_pick_the_backtracking_engine() {
+        let import = import_commands(r"rename 's/(\w)\1/$1/'");
+        assert_eq!(import.rules[0].engine, Engine::Fancy);
+        assert_eq!(import.rules[0].from, r"^(.*/)?([^/]*?)((\w)\4)([^/]*)$");
+        assert_eq!(renamed(r"rename 's/^(\w)\1/$1/'", "./aab"), "./ab");
+        assert_eq!(renamed(r"rename 's/(\w)\1/$1/'", "./aa/xbb"), "./aa/xb");
+    }
+
+    #[cfg(not(feature = "fancy"))]
+    #[test]
+    fn backreferences_need_the_backtracking_engine() {
+        let import = import_commands(r"rename 's/(\w)\1/$1/'");
+        assert!(import.rules.is_empty());
+        assert!(
+            import.warnings[0].contains("needs the fancy-regex engine"),
+            "{:#?}",
+            import.warnings
+        );
+    }
+
+    #[test]
+    fn global_substitutions_keep_to_the_file_name() {
+        let import = import_commands("rename 's/_/ /g'");
+        let rule = &import.rules[0];
+        assert_eq!(rule.engine, Engine::Regex);
+        assert_eq!(rule.from, "_");
+        assert!(rule.replace_all && rule.name_only);
+        assert_eq!(renamed("rename 's/^_+//g'", "./_a/__b"), "./_a/b");
+    }
+
+    #[test]
+    fn drops_comments_of_extended_patterns() {
+        assert_eq!(
+            renamed(
+                r"rename 's/ (\d+) \. txt # the [number] and (?{ext})/-$1.md/x'",
+                "./7/n7.txt.bak"
+            ),
+            "./7/n-7.md.bak"
+        );
+        let (body, _) = translate_pattern("a # one\n b # two", 0, true).unwrap();
+        assert_eq!(body, "a  b ");
+    }
+
+    #[test]
+    fn rejects_slashes_in_patterns() {
+        for command in [
+            r"rename 's/a\/b/c/'",
+            "rename 's{a/b}{c}'",
+            r"rename 's{a\/b}{c}'",
+        ] {
+            let import = import_commands(command);
+            assert!(import.rules.is_empty(), "{command}");
+            assert!(import.warnings[0].contains("'/'"), "{command}");
+        }
+        assert_eq!(renamed("rename 's{[/_]}{-}'", "./a/b_c"), "./a/b-c");
+    }
+
+    #[test]
+    fn directories_are_never_rewritten() {
+        let unchanged = [
+            ("rename 's/^IMG_//'", "./IMG_dir/photo.jpg"),
+            ("rename 's/_/-/'", "./a_b/cd.txt"),
+            ("rename 's/^a.*//'", "./abc/xyz"),
+            (r"rename 's/[^x]y//'", "./ay/b"),
+            (r"rename 's/[^x]b//'", "./a/b"),
+            (r"rename 's/a.b//'", "./a/b"),
+            (r"rename 's/a\Wb//g'", "./a/b"),
+        ];
+        for (command, path) in unchanged {
+            assert_eq!(renamed(command, path), path, "{command}");
+        }
+        assert_eq!(
+            renamed("rename 's/_/ /g'", "./a_b/c_d.txt"),
+            "./a_b/c d.txt"
+        );
+        assert_eq!(renamed("rename 's/_/-/'", "./a_b/c_d_e"), "./a_b/c-d_e");
+        assert_eq!(renamed(r"rename 's/\W/_/g'", "./a b/c d"), "./a b/c_d");
+        assert_eq!(renamed(r"rename 's/[^x]+$/y/'", "./a/b"), "./a/y");
+        assert_eq!(
+            renamed(r"rename 's/[.-]/_/g'", "./a.b/c-d.e"),
+            "./a.b/c_d_e"
+        );
+    }
+
+    #[test]
+    fn translates_mmv_pairs() {
+        let import = import_commands("mmv '*.jpeg' '#1.jpg'");
+        assert_eq!(import.rules[0].kind, RuleKind::Glob);
+        assert_eq!(import.rules[0].to, "${1}.jpg");
+        assert_eq!(renamed("mmv '*.jpeg' '#1.jpg'", "./a.jpeg"), "a.jpg");
+        assert_eq!(
+            renamed("mmv ';*.JPG' '#1#l2.jpg'", "./a/b/IMG.JPG"),
+            "a/b/img.jpg"
+        );
+        assert_eq!(
+            renamed("mmv -m 'x{?}.txt' 'y#1.txt'", "./x{1}.txt"),
+            "y1.txt"
+        );
+        assert_eq!(renamed("mmv -r 'a/*.JPG' '#1.jpg'", "./a/b.JPG"), "a/b.jpg");
+        assert_eq!(
+            renamed("mmv -r '*/[ab]*.JPG' '#3#2.jpg'", "./x/a1.JPG"),
+            "x/1a.jpg"
+        );
+    }
+
+    #[test]
+    fn mmv_checks_rename_only_targets_and_warns_about_options() {
+        let import = import_commands(
+            "mmv -r '*.JPG' 'pics/#1.jpg'
+mmv -p '*.a' '#1.b'",
+        );
+        assert_eq!(import.warnings.len(), 2, "{:#?}", import.warnings);
+        assert!(import.warnings[0].starts_with("line 1: mmv -r"));
+        assert_eq!(import.warnings[1], "line 2: option -p is ignored");
+        assert_eq!(import.rules.len(), 1);
+    }
+
+    #[test]
+    fn warns_about_unsupported_constructs() {
+        let import = import_commands(
+            "# scripts\n\
+             rename 's/a/b/e'\n\
+             rename 's/(a)/\\U$1/'\n\
+             rename 's/\\Ga/b/'\n\
+             rename 's/a/$HOME/'\n\
+             rename 'y/a-z/A-Z/'\n\
+             mmv -c '*' '#1.bak'\n\
+             mmv '*' '#u1'\n\
+             mv a b\n\
+             rename 's/a/b\n\
+             rename 's/(a/b/'\n\
+             rename 's/x/y/' file.txt",
+        );
+        let lines: Vec<&str> = import
+            .warnings
+            .iter()
+            .map(|w| w.split(':').next().unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "line 2", "line 3", "line 4", "line 5", "line 6", "line 7", "line 8", "line 9",
+                "line 10", "line 11", "line 12"
+            ],
+            "{:#?}",
+            import.warnings
+        );
+        assert!(import.warnings[0].contains("e flag"));
+        assert!(import.warnings[9].contains("does not compile"));
+        assert!(import.warnings[10].contains("file arguments"));
+        // The rule that failed to compile is kept so it can be fixed.
+        assert_eq!(import.rules.len(), 2);
+    }
+
+    #[test]
+    fn splits_words_like_a_shell() {
+        assert_eq!(
+            shell_words(r#"rename -n 's/a b/c/' "x\"y" z\ w"#).unwrap(),
+            ["rename", "-n", "s/a b/c/", "x\"y", "z w"]
+        );
+        assert!(shell_words("rename 's/a/b/").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// A file system change that [`super::Renamer::undo`] can revert.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JournalEntry {
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Rewritten {
+        path: PathBuf,
+        /// Contents before the rewrite.
+        original: Vec<u8>,
+        /// Fingerprint of the written contents, to detect later edits.
+        written: u64,
+    },
+    /// A directory emptied by the batch was removed.
+    RemovedDir {
+        path: PathBuf,
+    },
+}
+
+/// Changes made by one batch, in the order they were applied.
+#[derive(Clone, Debug, Default)]
+pub struct UndoJournal {
+    entries: Vec<JournalEntry>,
+}
+
+impl UndoJournal {
+    pub fn record_move(&mut self, from: PathBuf, to: PathBuf) {
+        self.entries.push(JournalEntry::Moved { from, to });
+    }
+
+    pub fn record_rewrite(&mut self, path: PathBuf, original: Vec<u8>, written: &[u8]) {
+        self.entries.push(JournalEntry::Rewritten {
+            path,
+            original,
+            written: fingerprint(written),
+        });
+    }
+
+    pub fn record_removed_dir(&mut self, path: PathBuf) {
+        self.entries.push(JournalEntry::RemovedDir { path });
+    }
+
+    /// Original paths of the entries moved so far.
+    pub fn moved_sources(&self) -> impl Iterator<Item = &Path> {
+        self.entries.iter().filter_map(|entry| match entry {
+            JournalEntry::Moved { from, .. } => Some(from.as_path()),
+            _ => None,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Entries in the order they must be reverted: newest first.
+    pub fn into_undo_order(self) -> impl Iterator<Item = JournalEntry> {
+        self.entries.into_iter().rev()
+    }
+}
+
+/// Cheap content fingerprint; undo only needs to notice modifications.
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
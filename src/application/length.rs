@@ -0,0 +1,295 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::os_path::{path_bytes, path_from_bytes};
+use super::sanitize::NameIssue;
+
+/// What the planner does with destinations over the length limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LengthStrategy {
+    /// Leave the entry alone and report it.
+    #[default]
+    Fail,
+    /// Shorten the stem of the name, keeping its extension.
+    Truncate,
+    /// Shorten the stem and append a hash of the full name, so names that
+    /// share a long prefix stay distinct.
+    TruncateWithHash,
+}
+
+/// Strategies in the order the options bar offers them.
+pub const STRATEGIES: [LengthStrategy; 3] = [
+    LengthStrategy::Fail,
+    LengthStrategy::Truncate,
+    LengthStrategy::TruncateWithHash,
+];
+
+impl LengthStrategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            LengthStrategy::Fail => "Fail",
+            LengthStrategy::Truncate => "Truncate",
+            LengthStrategy::TruncateWithHash => "Truncate + hash",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            LengthStrategy::Fail => "Skip over-long destinations and report them",
+            LengthStrategy::Truncate => "Shorten the name before its extension",
+            LengthStrategy::TruncateWithHash => {
+                "Shorten the name and add a hash of the full name, e.g. 'long~1a2b3c4d.txt'"
+            }
+        }
+    }
+}
+
+/// Byte limits destinations are checked against before anything is moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LengthLimits {
+    /// Longest path component, in bytes.
+    pub max_name: usize,
+    /// Longest path, in bytes, as written relative to the working directory.
+    pub max_path: usize,
+    pub strategy: LengthStrategy,
+}
+
+/// Longest name the platform accepts: `NAME_MAX` on Unix, a component of
+/// NTFS on Windows.
+const DEFAULT_MAX_NAME: usize = 255;
+
+/// Longest path the platform accepts: `PATH_MAX` of Linux and macOS, or
+/// `MAX_PATH` of Windows.
+#[cfg(target_os = "macos")]
+const DEFAULT_MAX_PATH: usize = 1024;
+#[cfg(windows)]
+const DEFAULT_MAX_PATH: usize = 260;
+#[cfg(not(any(target_os = "macos", windows)))]
+const DEFAULT_MAX_PATH: usize = 4096;
+
+impl Default for LengthLimits {
+    /// The limits of the platform the app runs on.
+    fn default() -> Self {
+        Self {
+            max_name: DEFAULT_MAX_NAME,
+            max_path: DEFAULT_MAX_PATH,
+            strategy: LengthStrategy::Fail,
+        }
+    }
+}
+
+/// Shortens `name` to at most `max` bytes with `strategy`, cutting the stem
+/// on a character boundary and keeping the extension. Returns `None` when
+/// the name cannot be shortened that far.
+pub fn truncate_name(name: &[u8], max: usize, strategy: LengthStrategy) -> Option<Vec<u8>> {
+    if name.len() <= max {
+        return Some(name.to_vec());
+    }
+    let suffix = match strategy {
+        LengthStrategy::Fail => return None,
+        LengthStrategy::Truncate => String::new(),
+        LengthStrategy::TruncateWithHash => format!("~{:08x}", fnv1a(name)),
+    };
+    // Extensions longer than half the limit are more likely part of the
+    // name, e.g. `a.very long sentence`.
+    let dot = name
+        .iter()
+        .rposition(|b| *b == b'.')
+        .filter(|dot| *dot > 0 && name.len() - dot <= max / 2)
+        .unwrap_or(name.len());
+    let (stem, extension) = name.split_at(dot);
+    let room = max.checked_sub(extension.len() + suffix.len())?;
+    let cut = char_boundaries(stem)
+        .take_while(|boundary| *boundary <= room)
+        .last()
+        .filter(|cut| *cut > 0)?;
+    let mut out = stem[..cut].to_vec();
+    out.extend_from_slice(suffix.as_bytes());
+    out.extend_from_slice(extension);
+    Some(out)
+}
+
+/// Offsets in `bytes` that do not split a UTF-8 character, in order. Every
+/// invalid byte counts as a character of its own.
+fn char_boundaries(bytes: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    let mut offset = 0;
+    bytes
+        .utf8_chunks()
+        .flat_map(move |chunk| {
+            let start = offset;
+            offset += chunk.valid().len() + chunk.invalid().len();
+            let valid = chunk.valid().char_indices().map(move |(i, _)| start + i);
+            let invalid_start = start + chunk.valid().len();
+            valid.chain((0..chunk.invalid().len()).map(move |i| invalid_start + i))
+        })
+        .chain(std::iter::once(bytes.len()))
+}
+
+/// 32-bit FNV-1a, stable across builds so hashed names are reproducible.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Checks the components of `destination` not shared with `source`, and
+/// the whole path, against `limits`. Returns the shortened path, the issues
+/// found and whether every issue could be fixed.
+pub fn limit_length(
+    source: &Path,
+    destination: &Path,
+    limits: LengthLimits,
+) -> (PathBuf, Vec<NameIssue>, bool) {
+    let mut issues = Vec::new();
+    let mut fixed = true;
+    let mut path = PathBuf::new();
+    let mut shared = true;
+    let mut source_components = source.components();
+    for component in destination.components() {
+        shared = shared && source_components.next() == Some(component);
+        let name = match (component, path_bytes(Path::new(component.as_os_str()))) {
+            (Component::Normal(_), Some(name)) if !shared && name.len() > limits.max_name => name,
+            _ => {
+                path.push(component);
+                continue;
+            }
+        };
+        issues.push(NameIssue::NameTooLong {
+            bytes: name.len(),
+            limit: limits.max_name,
+        });
+        match truncate_name(name, limits.max_name, limits.strategy).and_then(path_from_bytes) {
+            Some(short) => path.push(short),
+            None => {
+                fixed = false;
+                path.push(component);
+            }
+        }
+    }
+
+    let length = path_bytes(&path).map_or(0, <[u8]>::len);
+    if length > limits.max_path {
+        issues.push(NameIssue::PathTooLong {
+            bytes: length,
+            limit: limits.max_path,
+        });
+        // Only the last component is shortened; the rest are directories
+        // other entries may share.
+        let last = path
+            .file_name()
+            .and_then(|name| path_bytes(Path::new(name)));
+        let short = last.and_then(|name| {
+            let max = name.len().checked_sub(length - limits.max_path)?;
+            truncate_name(name, max, limits.strategy).and_then(path_from_bytes)
+        });
+        match short {
+            Some(short) => path.set_file_name(short),
+            None => fixed = false,
+        }
+    }
+    (path, issues, fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_name: usize, max_path: usize, strategy: LengthStrategy) -> LengthLimits {
+        LengthLimits {
+            max_name,
+            max_path,
+            strategy,
+        }
+    }
+
+    #[test]
+    fn truncates_the_stem_and_keeps_the_extension() {
+        let truncate = |name: &str, max| {
+            truncate_name(name.as_bytes(), max, LengthStrategy::Truncate)
+                .map(|n| String::from_utf8(n).unwrap())
+        };
+        assert_eq!(truncate("abcdefgh.txt", 8).as_deref(), Some("abcd.txt"));
+        assert_eq!(truncate("short.txt", 20).as_deref(), Some("short.txt"));
+        // 'é' is two bytes and is never split.
+        assert_eq!(truncate("aéé.txt", 8).as_deref(), Some("aé.txt"));
+        assert_eq!(
+            truncate("a.sentence with dots", 10).as_deref(),
+            Some("a.sentence")
+        );
+        assert_eq!(
+            truncate_name(b"ab\xFFcd.txt", 8, LengthStrategy::Truncate),
+            Some(b"ab\xFFc.txt".to_vec())
+        );
+    }
+
+    #[test]
+    fn hash_suffix_keeps_truncated_names_distinct() {
+        let a = truncate_name(
+            b"holiday photos 0001.jpg",
+            18,
+            LengthStrategy::TruncateWithHash,
+        );
+        let b = truncate_name(
+            b"holiday photos 0002.jpg",
+            18,
+            LengthStrategy::TruncateWithHash,
+        );
+        let (a, b) = (
+            String::from_utf8(a.unwrap()).unwrap(),
+            String::from_utf8(b.unwrap()).unwrap(),
+        );
+        assert_eq!(a.len(), 18);
+        assert!(a.starts_with("holid~") && a.ends_with(".jpg"), "{a}");
+        assert_ne!(a, b);
+        assert_eq!(truncate_name(b"abcdef.txt", 8, LengthStrategy::Fail), None);
+        // The hash alone does not fit.
+        assert_eq!(
+            truncate_name(b"abcdef.txt", 8, LengthStrategy::TruncateWithHash),
+            None
+        );
+    }
+
+    #[test]
+    fn checks_new_components_and_the_whole_path() {
+        let source = Path::new("./very-long-directory/a.txt");
+        let destination = Path::new("./very-long-directory/abcdefghij.txt");
+
+        let (path, issues, fixed) = limit_length(
+            source,
+            destination,
+            limits(10, 100, LengthStrategy::Truncate),
+        );
+        assert_eq!(path, Path::new("./very-long-directory/abcdef.txt"));
+        assert_eq!(
+            issues,
+            vec![NameIssue::NameTooLong {
+                bytes: 14,
+                limit: 10
+            }]
+        );
+        assert!(fixed);
+
+        let (path, issues, fixed) = limit_length(
+            source,
+            destination,
+            limits(255, 30, LengthStrategy::Truncate),
+        );
+        assert_eq!(path, Path::new("./very-long-directory/abcd.txt"));
+        assert_eq!(
+            issues,
+            vec![NameIssue::PathTooLong {
+                bytes: 36,
+                limit: 30
+            }]
+        );
+        assert!(fixed);
+
+        let (path, _, fixed) =
+            limit_length(source, destination, limits(10, 100, LengthStrategy::Fail));
+        assert_eq!(path, destination);
+        assert!(!fixed);
+    }
+}
@@ -1,12 +1,64 @@
-use std::io;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::SystemTime;
 
-use crate::domain::Rule;
+use crate::domain::{Engine, Normalization, RegexLimits, Rule, RuleMode};
 use crate::telemetry::Logger;
-use regex::Regex;
 use walkdir::WalkDir;
 
+mod api;
+mod confine;
+mod content;
+mod diagnostics;
+mod engine;
+mod error;
+mod explain;
+mod glob;
+mod import;
+mod journal;
+mod length;
+mod normalize;
+mod os_path;
+mod playground;
+mod railroad;
+mod recipe;
+mod report;
+mod ruleset;
+mod sample;
+mod sanitize;
+mod search;
+mod template;
+mod transcode;
+
+pub use api::ApiCall;
+pub use confine::Confinement;
+pub use content::{ContentChange, ContentPreview};
+pub use diagnostics::{BENCHMARK_BUDGET, Diagnostics};
+pub use engine::{
+    ENGINES, PatternEngine, SYNTAX_FEATURES, compile_engine, engine_available, engine_name,
+};
+pub use error::{AppError, RuleError, compile_pattern, compile_with_limits};
+pub use explain::{ExplainNode, explain};
+pub use glob::rule_regex;
+pub use import::{Import, import_commands};
+pub use journal::{JournalEntry, UndoJournal};
+pub use length::STRATEGIES;
+pub use normalize::{NORMALIZATIONS, normalization_description, normalization_name};
+pub use os_path::display_path;
+pub use playground::{MATCH_LIMIT, PatternFlags, match_groups};
+pub use railroad::{BoxKind, Diagram, Shape, railroad};
+pub use recipe::{Recipe, load_recipes};
+pub use report::{ExecuteOptions, ExecutionReport, MoveOutcome, MoveStatus, Operation, SkipReason};
+pub use ruleset::{RuleSet, RuleSetFormat};
+pub use sample::RuleSamples;
+pub use sanitize::{PROFILES, PortabilityProfile, SanitizeOptions, describe_issues};
+pub use search::{FileHits, HIT_LIMIT, LineHit, SearchResults, SearchRow};
+pub use template::{Template, validate_template};
+pub use transcode::{NAME_ENCODINGS, TranscodePreview};
+
 #[derive(Clone)]
 pub struct FileEntry {
     pub path: PathBuf,
@@ -14,20 +66,45 @@ pub struct FileEntry {
 }
 
 pub trait FileSystem: Send + Sync {
-    fn find_matches(&self, pattern: &Regex) -> io::Result<Vec<FileEntry>>;
+    fn find_matches(&self, pattern: &dyn PatternEngine) -> Result<Vec<FileEntry>, AppError>;
     fn move_file(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn exists(&self, path: &Path) -> bool;
+    fn read_file(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// Replaces the contents of `path` so that readers never observe a
+    /// partially written file.
+    fn write_file(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    /// Whether `path` is a directory, not a link to one, without entries.
+    fn is_empty_dir(&self, path: &Path) -> bool;
+    /// Removes `path`, failing unless it is an empty directory.
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+    fn create_dir(&self, path: &Path) -> io::Result<()>;
+    /// Last modification time of `path`, not of the target of a link.
+    fn modified(&self, path: &Path) -> io::Result<SystemTime>;
+    /// Whether `path` itself is a symbolic link.
+    fn is_symlink(&self, path: &Path) -> bool;
 }
 
 pub struct StdFileSystem;
 
 impl FileSystem for StdFileSystem {
-    fn find_matches(&self, pattern: &Regex) -> io::Result<Vec<FileEntry>> {
+    fn find_matches(&self, pattern: &dyn PatternEngine) -> Result<Vec<FileEntry>, AppError> {
         let mut matches = Vec::new();
         for entry in WalkDir::new(".") {
             let entry = entry?;
-            let abs = entry.path().canonicalize()?;
-            let path_str = abs.to_string_lossy();
-            if pattern.is_match(&path_str) {
+            // Match the same bytes the renamer rewrites, so captures and
+            // destinations line up with what was matched. Rules written
+            // against absolute paths still select entries by their canonical
+            // path.
+            let matches_path = |path: &Path| match os_path::path_bytes(path) {
+                Some(bytes) => pattern.is_match_bytes(bytes),
+                None => Ok(false),
+            };
+            let matched = matches_path(entry.path())?
+                || match entry.path().canonicalize() {
+                    Ok(absolute) => matches_path(&absolute)?,
+                    Err(_) => false,
+                };
+            if matched {
                 matches.push(FileEntry {
                     path: entry.path().to_path_buf(),
                     is_dir: entry.file_type().is_dir(),
@@ -43,22 +120,218 @@ impl FileSystem for StdFileSystem {
         }
         std::fs::rename(from, to)
     }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.symlink_metadata().is_ok()
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write_file(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        // The rename below would replace a link with a plain file.
+        if path.is_symlink() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "refusing to replace a symbolic link",
+            ));
+        }
+        // Write a sibling temp file and rename it over the original, which is
+        // atomic on the same file system.
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp = path.with_file_name(temp_name);
+
+        let result = (|| {
+            let mut file = std::fs::File::create(&temp)?;
+            file.write_all(contents)?;
+            if let Ok(metadata) = std::fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+            }
+            file.sync_all()?;
+            std::fs::rename(&temp, path)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result
+    }
+
+    fn is_empty_dir(&self, path: &Path) -> bool {
+        path.symlink_metadata()
+            .is_ok_and(|metadata| metadata.is_dir())
+            && std::fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        path.symlink_metadata()?.modified()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        path.is_symlink()
+    }
 }
 
+/// A rule whose pattern compiled and whose template matches its groups.
+pub struct CompiledRule {
+    pub regex: Box<dyn PatternEngine>,
+    pub template: Template,
+    /// Selects the files a content rule reads.
+    pub path_filter: Box<dyn PatternEngine>,
+    /// Form paths are normalized to before matching and after expansion.
+    pub normalization: Normalization,
+    /// Whether destinations replace every match instead of the first.
+    pub replace_all: bool,
+    /// The only paths the rule applies to, see [`Rule::paths`].
+    pub paths: Option<HashSet<OsString>>,
+}
+
+impl CompiledRule {
+    /// Whether the rule applies to `path`, which its pattern matched.
+    fn selects(&self, path: &Path) -> bool {
+        self.paths
+            .as_ref()
+            .is_none_or(|paths| paths.contains(path.as_os_str()))
+    }
+
+    /// The entries `pattern` matches in `fs` that the rule applies to.
+    fn entries(
+        &self,
+        fs: &dyn FileSystem,
+        pattern: &dyn PatternEngine,
+    ) -> Result<Vec<FileEntry>, AppError> {
+        let mut entries = fs.find_matches(pattern)?;
+        entries.retain(|entry| self.selects(&entry.path));
+        Ok(entries)
+    }
+
+    /// The destination of `source`, built from its normalized bytes so
+    /// captured groups line up with what `regex` matched. `modified` is
+    /// only needed when the template [uses it](Template::uses_mtime).
+    pub fn destination(
+        &self,
+        source: &Path,
+        modified: Option<SystemTime>,
+    ) -> Result<Option<PathBuf>, AppError> {
+        let Some(bytes) = os_path::path_bytes(source) else {
+            return Ok(None);
+        };
+        let input = normalize::normalize_bytes(bytes, self.normalization);
+        let output = if self.replace_all {
+            self.template
+                .replace_all_bytes(self.regex.as_ref(), &input, modified)?
+        } else {
+            self.template
+                .replace_bytes(self.regex.as_ref(), &input, modified)?
+        };
+        Ok(os_path::path_from_bytes(output).map(|destination| {
+            normalize::normalize_destination(source, &destination, self.normalization)
+        }))
+    }
+}
+
+/// Compiles the rule's pattern and path filter and validates its template
+/// against the pattern.
+pub fn compile_rule(rule: &Rule) -> Result<CompiledRule, AppError> {
+    let pattern = rule_regex(&rule.from, rule.kind)?;
+    // Only rename rules match paths under a normalized form.
+    let normalization = match rule.mode {
+        RuleMode::Rename => rule.normalization,
+        RuleMode::Content | RuleMode::Transcode => Normalization::None,
+    };
+    let mut regex = compile_engine(&pattern, rule.engine, rule.limits)?;
+    if rule.mode == RuleMode::Rename && rule.name_only {
+        regex = engine::name_only(regex);
+    }
+    let regex = normalize::normalized(regex, normalization);
+    // Transcode rules derive new names from the old ones, not from `to`.
+    let to = match rule.mode {
+        RuleMode::Transcode => "",
+        RuleMode::Rename | RuleMode::Content => &rule.to,
+    };
+    let template = validate_template(to, regex.as_ref(), rule.mode)?;
+    let path_filter = compile_engine(&rule.path_filter, Engine::Regex, RegexLimits::default())?;
+    Ok(CompiledRule {
+        regex,
+        template,
+        path_filter,
+        normalization,
+        replace_all: rule.mode == RuleMode::Rename && rule.replace_all,
+        paths: (!rule.paths.is_empty()).then(|| {
+            rule.paths
+                .iter()
+                .map(|path| path.as_os_str().to_owned())
+                .collect()
+        }),
+    })
+}
+
+#[derive(Clone)]
 pub struct Renamer {
     logger: Arc<dyn Logger>,
     fs: Arc<dyn FileSystem>,
+    confinement: Confinement,
 }
 
 impl Renamer {
     pub fn new(logger: Arc<dyn Logger>, fs: Arc<dyn FileSystem>) -> Self {
-        Self { logger, fs }
+        Self {
+            logger,
+            fs,
+            confinement: Confinement::default(),
+        }
+    }
+
+    pub fn confinement(&self) -> &Confinement {
+        &self.confinement
+    }
+
+    /// Sets the root destinations must stay in and the paths no batch may
+    /// touch, for previews and executions alike.
+    pub fn set_confinement(&mut self, confinement: Confinement) {
+        self.confinement = confinement;
     }
 
-    pub fn count_matches(&self, rule: &mut Rule) -> io::Result<usize> {
-        let re =
-            Regex::new(&rule.from).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
-        let matches = self.fs.find_matches(&re)?;
+    pub fn count_matches(&self, rule: &mut Rule) -> Result<usize, AppError> {
+        if rule.mode == RuleMode::Content {
+            let changed = self.preview_content(rule)?.changes.len();
+            rule.file_match_count = Some(changed);
+            rule.dir_match_count = Some(0);
+            self.logger
+                .log(&format!("Found {changed} files containing '{}'", rule.from));
+            return Ok(changed);
+        }
+        if rule.mode == RuleMode::Transcode {
+            let changes = self
+                .preview_transcode(rule, SanitizeOptions::default())?
+                .changes;
+            let dir_count = changes.iter().filter(|c| c.is_dir).count();
+            rule.file_match_count = Some(changes.len() - dir_count);
+            rule.dir_match_count = Some(dir_count);
+            self.logger.log(&format!(
+                "Found {} names to transcode for '{}'",
+                changes.len(),
+                rule.from
+            ));
+            return Ok(changes.len());
+        }
+        let compiled = compile_rule(rule).map_err(|e| self.logged(e))?;
+        let matches = compiled
+            .entries(self.fs.as_ref(), compiled.regex.as_ref())
+            .map_err(|e| self.logged(e))?;
         let file_count = matches.iter().filter(|m| !m.is_dir).count();
         let dir_count = matches.iter().filter(|m| m.is_dir).count();
         rule.file_match_count = Some(file_count);
@@ -70,59 +343,818 @@ impl Renamer {
         Ok(file_count + dir_count)
     }
 
-    pub fn count_all_matches(&self, rules: &mut [Rule]) -> io::Result<()> {
-        for rule in rules {
-            self.count_matches(rule)?;
+    /// Returns up to `limit` matched paths of `rule` with their capture
+    /// groups and resulting destinations.
+    pub fn sample_matches(
+        &self,
+        rule: &Rule,
+        limit: usize,
+        sanitize: SanitizeOptions,
+    ) -> Result<RuleSamples, AppError> {
+        let compiled = compile_rule(rule).map_err(|e| self.logged(e))?;
+        let paths: Vec<PathBuf> = compiled
+            .entries(self.fs.as_ref(), compiled.regex.as_ref())
+            .map_err(|e| self.logged(e))?
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        RuleSamples::collect(
+            &compiled,
+            &paths,
+            limit,
+            sanitize,
+            &self.confinement.guard(),
+            &|path| self.modified(&compiled, path),
+        )
+        .map_err(|e| self.logged(e))
+    }
+
+    /// The modification time of `path`, read only when the destination of
+    /// `compiled` needs it. Unreadable times expand to nothing.
+    fn modified(&self, compiled: &CompiledRule, path: &Path) -> Option<SystemTime> {
+        if !compiled.template.uses_mtime() {
+            return None;
+        }
+        self.fs.modified(path).ok()
+    }
+
+    /// Measures the rule's pattern and benchmarks it against every path in
+    /// the tree.
+    pub fn diagnose(&self, rule: &Rule) -> Result<Diagnostics, AppError> {
+        let everything = compile_pattern("").map_err(|e| self.logged(e))?;
+        let paths: Vec<String> = self
+            .fs
+            .find_matches(&everything)
+            .map_err(|e| self.logged(e))?
+            .iter()
+            .map(|entry| entry.path.to_string_lossy().into_owned())
+            .collect();
+        let diagnostics = rule_regex(&rule.from, rule.kind)
+            .and_then(|pattern| diagnostics::diagnose(&pattern, rule.limits, &paths))
+            .map_err(|e| self.logged(e))?;
+        self.logger.log(&format!(
+            "Compiled '{}' in {:?}; {:.0} searches/s over {} paths",
+            rule.from,
+            diagnostics.compile_time,
+            diagnostics.benchmark.searches_per_second(),
+            paths.len()
+        ));
+        Ok(diagnostics)
+    }
+
+    /// Computes the names a transcode rule would change without moving.
+    pub fn preview_transcode(
+        &self,
+        rule: &Rule,
+        sanitize: SanitizeOptions,
+    ) -> Result<TranscodePreview, AppError> {
+        let compiled = compile_rule(rule).map_err(|e| self.logged(e))?;
+        self.plan_transcodes(rule, &compiled, sanitize)
+            .map_err(|e| self.logged(e))
+    }
+
+    fn plan_transcodes(
+        &self,
+        rule: &Rule,
+        compiled: &CompiledRule,
+        sanitize: SanitizeOptions,
+    ) -> Result<TranscodePreview, AppError> {
+        let source = rule
+            .transcoding
+            .source
+            .as_deref()
+            .map(transcode::name_encoding)
+            .transpose()?;
+        let target = transcode::name_encoding(&rule.transcoding.target)?;
+        let entries = compiled.entries(self.fs.as_ref(), compiled.regex.as_ref())?;
+        let mut preview =
+            TranscodePreview::plan(entries, source, target, rule.transcoding.reencode_utf8);
+        preview.sanitize(sanitize);
+        preview.confine(&self.confinement.guard());
+        Ok(preview)
+    }
+
+    /// Computes the rewrites a content rule would make without writing.
+    pub fn preview_content(&self, rule: &Rule) -> Result<ContentPreview, AppError> {
+        let compiled = compile_rule(rule).map_err(|e| self.logged(e))?;
+        let guard = self.confinement.guard();
+        let mut preview = ContentPreview::default();
+        for path in self.content_files(&compiled).map_err(|e| self.logged(e))? {
+            if let Some(reason) = self.rewrite_skip(&guard, &path) {
+                preview.skipped.push((path, reason));
+                continue;
+            }
+            let (text, encoding) = match self.fs.read_file(&path) {
+                Ok(bytes) => match content::decode(&bytes) {
+                    Ok(decoded) => decoded,
+                    Err(reason) => {
+                        preview.skipped.push((path, reason));
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    preview.unreadable.push((path, e.to_string()));
+                    continue;
+                }
+            };
+            let change = ContentChange::new(
+                path,
+                text,
+                encoding,
+                compiled.regex.as_ref(),
+                &compiled.template,
+            )
+            .map_err(|e| self.logged(e))?;
+            preview.changes.extend(change);
         }
+        Ok(preview)
+    }
+
+    /// Searches the contents of the files whose paths match `path_filter`
+    /// for `pattern`, collecting at most [`HIT_LIMIT`] matching lines.
+    pub fn search(&self, pattern: &str, path_filter: &str) -> Result<SearchResults, AppError> {
+        let regex = compile_pattern(pattern).map_err(|e| self.logged(e))?;
+        let filter = compile_pattern(path_filter).map_err(|e| self.logged(e))?;
+        let entries = self.fs.find_matches(&filter).map_err(|e| self.logged(e))?;
+        let mut results = SearchResults::default();
+        let mut remaining = HIT_LIMIT;
+        for entry in entries.into_iter().filter(|entry| !entry.is_dir) {
+            if remaining == 0 {
+                results.truncated = true;
+                break;
+            }
+            let text = match self.fs.read_file(&entry.path) {
+                Ok(bytes) => match content::decode(&bytes) {
+                    Ok((text, _)) => text,
+                    Err(reason) => {
+                        results.skipped.push((entry.path, reason));
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    results.unreadable.push((entry.path, e.to_string()));
+                    continue;
+                }
+            };
+            let (lines, match_count) = search::search_lines(&regex, &text, remaining);
+            if lines.is_empty() {
+                continue;
+            }
+            remaining -= lines.len();
+            results.files.push(FileHits {
+                path: entry.path,
+                lines,
+                match_count,
+            });
+        }
+        self.logger.log(&format!(
+            "Found {} matches in {} files for '{pattern}'",
+            results.match_count(),
+            results.files.len()
+        ));
+        Ok(results)
+    }
+
+    /// Why a content rule leaves `path` alone: it is outside the root or
+    /// protected, or a link whose target is not rewritten through it.
+    fn rewrite_skip(&self, guard: &confine::Guard, path: &Path) -> Option<SkipReason> {
+        guard
+            .check(path, path)
+            .or_else(|| self.fs.is_symlink(path).then_some(SkipReason::Symlink))
+    }
+
+    fn content_files(&self, compiled: &CompiledRule) -> Result<Vec<PathBuf>, AppError> {
+        Ok(compiled
+            .entries(self.fs.as_ref(), compiled.path_filter.as_ref())?
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| entry.path)
+            .collect())
+    }
+
+    /// Counts matches for every rule, returning the errors of the rules that
+    /// could not be counted.
+    pub fn count_all_matches(&self, rules: &mut [Rule]) -> Vec<RuleError> {
+        rules
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, rule)| {
+                self.count_matches(rule)
+                    .err()
+                    .map(|error| RuleError { rule: index, error })
+            })
+            .collect()
+    }
+
+    /// Applies every rule in order and records the outcome of each matched
+    /// entry. All rules (patterns and templates) are validated before
+    /// anything is moved; walk errors
+    /// and destination conflicts abort the run before the offending rule
+    /// touches the file system. Move failures are recorded and, unless
+    /// `continue_on_error` is set, stop the batch with the remaining entries
+    /// of the rule marked as aborted.
+    pub fn execute(
+        &self,
+        rules: &[Rule],
+        options: ExecuteOptions,
+    ) -> Result<ExecutionReport, RuleError> {
+        let compiled = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| compile_rule(rule).map_err(|error| self.rule_error(index, error)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut report = ExecutionReport::default();
+        for (index, (rule, compiled)) in rules.iter().zip(&compiled).enumerate() {
+            match rule.mode {
+                RuleMode::Rename => self.execute_renames(rule, compiled, options, &mut report),
+                RuleMode::Content => self.execute_rewrites(rule, compiled, options, &mut report),
+                RuleMode::Transcode => {
+                    self.execute_transcodes(rule, compiled, options, &mut report)
+                }
+            }
+            .map_err(|error| self.rule_error(index, error))?;
+            if report.aborted {
+                break;
+            }
+        }
+        if options.remove_empty_dirs {
+            self.remove_emptied_dirs(&mut report);
+        }
+        self.logger
+            .log(&format!("Execution finished: {}", report.summary()));
+        Ok(report)
+    }
+
+    fn execute_renames(
+        &self,
+        rule: &Rule,
+        compiled: &CompiledRule,
+        options: ExecuteOptions,
+        report: &mut ExecutionReport,
+    ) -> Result<(), AppError> {
+        self.logger
+            .log(&format!("Mapping '{}' -> '{}'", rule.from, rule.to));
+        // Destinations are built from the raw bytes of each path, so names
+        // that are not valid UTF-8 are moved under their exact bytes.
+        let mut moves = Vec::new();
+        for entry in compiled.entries(self.fs.as_ref(), compiled.regex.as_ref())? {
+            let modified = self.modified(compiled, &entry.path);
+            if let Some(destination) = compiled.destination(&entry.path, modified)? {
+                moves.push((entry.path, destination));
+            }
+        }
+        let moves = self.sanitize_moves(moves, options.sanitize, report);
+        let moves = self.confine_moves(moves, report);
+        check_conflicts(&moves)?;
+        self.apply_moves(moves, options, report);
         Ok(())
     }
 
-    pub fn execute(&self, rules: &[Rule], dry_run: bool) -> io::Result<()> {
-        for rule in rules {
-            self.logger
-                .log(&format!("Mapping '{}' -> '{}'", rule.from, rule.to));
-            let re = Regex::new(&rule.from)
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
-            for entry in self.fs.find_matches(&re)? {
-                let path_str = entry.path.to_string_lossy();
-                let dest_str = re.replace(&path_str, &rule.to).to_string();
-                if !dry_run {
-                    self.fs.move_file(&entry.path, Path::new(&dest_str))?;
+    /// Checks every destination against the portability profile and the
+    /// length limits. Offending destinations are rewritten or skipped, as
+    /// `options` say.
+    fn sanitize_moves(
+        &self,
+        moves: Vec<(PathBuf, PathBuf)>,
+        options: SanitizeOptions,
+        report: &mut ExecutionReport,
+    ) -> Vec<(PathBuf, PathBuf)> {
+        moves
+            .into_iter()
+            .filter_map(|(source, destination)| {
+                let sanitized = sanitize::sanitize_destination(&source, &destination, options);
+                if sanitized.issues.is_empty() {
+                    return Some((source, destination));
+                }
+                let issues = describe_issues(&sanitized.issues);
+                match sanitized.skip {
+                    None => {
+                        self.logger.log(&format!(
+                            "Sanitized '{}' -> '{}': {issues}",
+                            display_path(&destination),
+                            display_path(&sanitized.path)
+                        ));
+                        Some((source, sanitized.path))
+                    }
+                    Some(reason) => {
+                        self.logger.log(&format!(
+                            "Skipping '{}': {issues}",
+                            display_path(&destination)
+                        ));
+                        report.push(source, destination, MoveOutcome::Skipped(reason));
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Drops the moves that leave the root or touch a protected path,
+    /// reporting each as skipped.
+    fn confine_moves(
+        &self,
+        moves: Vec<(PathBuf, PathBuf)>,
+        report: &mut ExecutionReport,
+    ) -> Vec<(PathBuf, PathBuf)> {
+        let guard = self.confinement.guard();
+        moves
+            .into_iter()
+            .filter_map(|(source, destination)| {
+                let Some(reason) = guard.check(&source, &destination) else {
+                    return Some((source, destination));
+                };
+                self.logger.log(&format!(
+                    "Refusing '{}' -> '{}': {}",
+                    display_path(&source),
+                    display_path(&destination),
+                    reason.description()
+                ));
+                report.push(source, destination, MoveOutcome::Skipped(reason));
+                None
+            })
+            .collect()
+    }
+
+    /// Removes the directories left empty by the moves of the batch, deepest
+    /// first, so a parent emptied by removing its children goes too. Only
+    /// former parents of moved entries are considered, so directories that
+    /// were already empty stay.
+    fn remove_emptied_dirs(&self, report: &mut ExecutionReport) {
+        let guard = self.confinement.guard();
+        let mut candidates: Vec<PathBuf> = report
+            .journal
+            .moved_sources()
+            .flat_map(|source| source.ancestors().skip(1))
+            // Stops at the working directory.
+            .filter(|dir| dir.file_name().is_some())
+            .map(Path::to_path_buf)
+            .collect();
+        candidates.sort_by(|a, b| {
+            let depth = |path: &PathBuf| path.components().count();
+            depth(b).cmp(&depth(a)).then_with(|| a.cmp(b))
+        });
+        candidates.dedup();
+
+        for dir in candidates {
+            if guard.check(&dir, &dir).is_some() || !self.fs.is_empty_dir(&dir) {
+                continue;
+            }
+            let outcome = match self.fs.remove_dir(&dir) {
+                Ok(()) => {
+                    self.logger
+                        .log(&format!("Removed empty directory '{}'", display_path(&dir)));
+                    report.journal.record_removed_dir(dir.clone());
+                    MoveOutcome::Succeeded
+                }
+                Err(e) => {
+                    let outcome = MoveOutcome::failed(&e);
+                    self.log_error(&AppError::Directory {
+                        path: dir.clone(),
+                        source: e,
+                    });
+                    outcome
                 }
+            };
+            report.push_directory(Operation::RemoveDir, dir, outcome);
+        }
+    }
+
+    /// Renames every matched name from its detected or given encoding to the
+    /// target encoding. Names that cannot be converted exactly are reported
+    /// as skipped and left alone.
+    fn execute_transcodes(
+        &self,
+        rule: &Rule,
+        compiled: &CompiledRule,
+        options: ExecuteOptions,
+        report: &mut ExecutionReport,
+    ) -> Result<(), AppError> {
+        self.logger.log(&format!(
+            "Transcoding names matching '{}' from {} to {}",
+            rule.from,
+            rule.transcoding
+                .source
+                .as_deref()
+                .unwrap_or("detected encodings"),
+            rule.transcoding.target
+        ));
+        let plan = self.plan_transcodes(rule, compiled, options.sanitize)?;
+        let moves: Vec<(PathBuf, PathBuf)> = plan
+            .changes
+            .into_iter()
+            .map(|change| (change.path, change.new_path))
+            .collect();
+        check_conflicts(&moves)?;
+        for (path, reason) in plan.skipped {
+            report.push(path.clone(), path, MoveOutcome::Skipped(reason));
+        }
+        self.apply_moves(moves, options, report);
+        Ok(())
+    }
+
+    /// Performs planned moves in order, recording each in the report and the
+    /// undo journal.
+    fn apply_moves(
+        &self,
+        moves: Vec<(PathBuf, PathBuf)>,
+        options: ExecuteOptions,
+        report: &mut ExecutionReport,
+    ) {
+        for (source, destination) in moves {
+            let outcome = if report.aborted {
+                MoveOutcome::Skipped(SkipReason::Aborted)
+            } else if destination == source {
+                MoveOutcome::Skipped(SkipReason::Unchanged)
+            } else if options.dry_run {
+                MoveOutcome::Skipped(SkipReason::DryRun)
+            } else {
+                match self.fs.move_file(&source, &destination) {
+                    Ok(()) => {
+                        report
+                            .journal
+                            .record_move(source.clone(), destination.clone());
+                        MoveOutcome::Succeeded
+                    }
+                    Err(e) => {
+                        let outcome = MoveOutcome::failed(&e);
+                        self.log_error(&AppError::Move {
+                            from: source.clone(),
+                            to: destination.clone(),
+                            source: e,
+                        });
+                        report.aborted = !options.continue_on_error;
+                        outcome
+                    }
+                }
+            };
+            report.push(source, destination, outcome);
+        }
+    }
+
+    /// Rewrites the files selected by the rule's path filter. Files without a
+    /// match are not reported; after an aborting failure the remaining files
+    /// are not read at all.
+    fn execute_rewrites(
+        &self,
+        rule: &Rule,
+        compiled: &CompiledRule,
+        options: ExecuteOptions,
+        report: &mut ExecutionReport,
+    ) -> Result<(), AppError> {
+        self.logger.log(&format!(
+            "Replacing '{}' -> '{}' in files matching '{}'",
+            rule.from, rule.to, rule.path_filter
+        ));
+        let guard = self.confinement.guard();
+        for path in self.content_files(compiled)? {
+            if report.aborted {
+                break;
+            }
+            if let Some(reason) = self.rewrite_skip(&guard, &path) {
+                report.push_rewrite(path, 0, MoveOutcome::Skipped(reason));
+                continue;
             }
+            let bytes = match self.fs.read_file(&path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    report.push_rewrite(path.clone(), 0, MoveOutcome::failed(&e));
+                    self.log_error(&AppError::Rewrite { path, source: e });
+                    report.aborted = !options.continue_on_error;
+                    continue;
+                }
+            };
+            let (text, encoding) = match content::decode(&bytes) {
+                Ok(decoded) => decoded,
+                Err(reason) => {
+                    report.push_rewrite(path, 0, MoveOutcome::Skipped(reason));
+                    continue;
+                }
+            };
+            let change = ContentChange::new(
+                path.clone(),
+                text,
+                encoding,
+                compiled.regex.as_ref(),
+                &compiled.template,
+            )?;
+            let Some(change) = change else {
+                continue;
+            };
+            let outcome = if options.dry_run {
+                MoveOutcome::Skipped(SkipReason::DryRun)
+            } else {
+                let written = change
+                    .encoded()
+                    .and_then(|new| self.fs.write_file(&path, &new).map(|()| new));
+                match written {
+                    Ok(new) => {
+                        report.journal.record_rewrite(path.clone(), bytes, &new);
+                        MoveOutcome::Succeeded
+                    }
+                    Err(e) => {
+                        let outcome = MoveOutcome::failed(&e);
+                        self.log_error(&AppError::Rewrite {
+                            path: path.clone(),
+                            source: e,
+                        });
+                        report.aborted = !options.continue_on_error;
+                        outcome
+                    }
+                }
+            };
+            report.push_rewrite(path, change.replacements, outcome);
         }
         Ok(())
     }
+
+    /// Reverts the changes recorded in `journal`, newest first. Entries whose
+    /// files were changed again since the batch are reported as failures and
+    /// left untouched; the remaining entries are still reverted.
+    pub fn undo(&self, journal: UndoJournal) -> ExecutionReport {
+        self.logger
+            .log(&format!("Undoing {} operations", journal.len()));
+        let mut report = ExecutionReport::default();
+        for entry in journal.into_undo_order() {
+            match entry {
+                JournalEntry::Moved { from, to } => {
+                    let result = if self.fs.exists(&from) {
+                        Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            "the original path exists again",
+                        ))
+                    } else {
+                        self.fs.move_file(&to, &from)
+                    };
+                    let outcome = match result {
+                        Ok(()) => MoveOutcome::Succeeded,
+                        Err(e) => {
+                            let outcome = MoveOutcome::failed(&e);
+                            self.log_error(&AppError::Move {
+                                from: to.clone(),
+                                to: from.clone(),
+                                source: e,
+                            });
+                            outcome
+                        }
+                    };
+                    report.push(to, from, outcome);
+                }
+                JournalEntry::Rewritten {
+                    path,
+                    original,
+                    written,
+                } => {
+                    let result = self.fs.read_file(&path).and_then(|current| {
+                        if journal::fingerprint(&current) != written {
+                            return Err(io::Error::other("modified since the batch ran"));
+                        }
+                        self.fs.write_file(&path, &original)
+                    });
+                    let outcome = match result {
+                        Ok(()) => MoveOutcome::Succeeded,
+                        Err(e) => {
+                            let outcome = MoveOutcome::failed(&e);
+                            self.log_error(&AppError::Rewrite {
+                                path: path.clone(),
+                                source: e,
+                            });
+                            outcome
+                        }
+                    };
+                    report.push_restore(path, outcome);
+                }
+                JournalEntry::RemovedDir { path } => {
+                    let outcome = match self.fs.create_dir(&path) {
+                        Ok(()) => MoveOutcome::Succeeded,
+                        Err(e) => {
+                            let outcome = MoveOutcome::failed(&e);
+                            self.log_error(&AppError::Directory {
+                                path: path.clone(),
+                                source: e,
+                            });
+                            outcome
+                        }
+                    };
+                    report.push_directory(Operation::CreateDir, path, outcome);
+                }
+            }
+        }
+        self.logger
+            .log(&format!("Undo finished: {}", report.summary()));
+        report
+    }
+
+    fn log_error(&self, error: &AppError) {
+        self.logger.error(error.kind(), &error.fields(), error);
+    }
+
+    fn logged(&self, error: AppError) -> AppError {
+        self.log_error(&error);
+        error
+    }
+
+    fn rule_error(&self, rule: usize, error: AppError) -> RuleError {
+        RuleError {
+            rule,
+            error: self.logged(error),
+        }
+    }
+}
+
+/// Fails with [`AppError::Conflict`] when two entries that actually move
+/// share a destination.
+fn check_conflicts(moves: &[(PathBuf, PathBuf)]) -> Result<(), AppError> {
+    let mut by_destination: HashMap<&Path, Vec<PathBuf>> = HashMap::new();
+    for (source, destination) in moves.iter().filter(|(s, d)| s != d) {
+        by_destination
+            .entry(destination)
+            .or_default()
+            .push(source.clone());
+    }
+    match moves
+        .iter()
+        .find(|(_, d)| by_destination.get(d.as_path()).is_some_and(|s| s.len() > 1))
+    {
+        Some((_, destination)) => Err(AppError::Conflict {
+            destination: destination.clone(),
+            sources: by_destination
+                .remove(destination.as_path())
+                .unwrap_or_default(),
+        }),
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::RuleKind;
+    use std::collections::HashSet;
     use std::path::PathBuf;
     use std::sync::{Arc, Mutex};
 
-    struct MockFs {
-        entries: Vec<FileEntry>,
-        moved: Arc<Mutex<Vec<(PathBuf, PathBuf)>>>,
+    /// File system keeping files in memory. Every move is recorded, and
+    /// moves from or writes to the paths in `failing` fail with
+    /// `PermissionDenied`.
+    #[derive(Default)]
+    struct MemoryFs {
+        files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+        /// Paths whose moves and writes fail.
+        failing: Vec<PathBuf>,
+        /// Files that are symbolic links.
+        links: Vec<PathBuf>,
+        /// Directories that exist besides the parents of `files`.
+        dirs: Mutex<HashSet<PathBuf>>,
+        moves: Mutex<Vec<(PathBuf, PathBuf)>>,
     }
 
-    impl FileSystem for MockFs {
-        fn find_matches(&self, pattern: &Regex) -> io::Result<Vec<FileEntry>> {
-            Ok(self
-                .entries
-                .iter()
-                .filter(|e| pattern.is_match(&e.path.to_string_lossy()))
-                .cloned() // clone only the retained items
+    impl MemoryFs {
+        fn with_files(files: &[(&str, &[u8])]) -> Self {
+            Self::with_paths(
+                files
+                    .iter()
+                    .map(|(path, bytes)| (PathBuf::from(path), bytes.to_vec())),
+            )
+        }
+
+        /// Files with the given contents at paths that need not be valid UTF-8.
+        fn with_paths(paths: impl IntoIterator<Item = (PathBuf, Vec<u8>)>) -> Self {
+            Self {
+                files: Mutex::new(paths.into_iter().collect()),
+                ..Self::default()
+            }
+        }
+
+        fn empty_files(paths: &[&str]) -> Self {
+            Self::with_paths(paths.iter().map(|path| (PathBuf::from(path), Vec::new())))
+        }
+
+        fn failing(mut self, path: &str) -> Self {
+            self.failing.push(PathBuf::from(path));
+            self
+        }
+
+        fn contents(&self, path: &str) -> Option<Vec<u8>> {
+            self.files.lock().unwrap().get(Path::new(path)).cloned()
+        }
+
+        fn moves(&self) -> Vec<(PathBuf, PathBuf)> {
+            self.moves.lock().unwrap().clone()
+        }
+
+        fn check(&self, path: &Path) -> io::Result<()> {
+            if self.failing.iter().any(|p| p == path) {
+                return Err(io::ErrorKind::PermissionDenied.into());
+            }
+            Ok(())
+        }
+    }
+
+    impl FileSystem for MemoryFs {
+        fn find_matches(&self, pattern: &dyn PatternEngine) -> Result<Vec<FileEntry>, AppError> {
+            let mut paths = Vec::new();
+            for path in self.files.lock().unwrap().keys() {
+                if pattern.is_match_bytes(os_path::path_bytes(path).unwrap())? {
+                    paths.push(path.clone());
+                }
+            }
+            paths.sort();
+            Ok(paths
+                .into_iter()
+                .map(|path| FileEntry {
+                    path,
+                    is_dir: false,
+                })
                 .collect())
         }
 
         fn move_file(&self, from: &Path, to: &Path) -> io::Result<()> {
-            self.moved
+            self.check(from)?;
+            let mut files = self.files.lock().unwrap();
+            let bytes = files.remove(from).ok_or(io::ErrorKind::NotFound)?;
+            files.insert(to.to_path_buf(), bytes);
+            self.moves
                 .lock()
                 .unwrap()
                 .push((from.to_path_buf(), to.to_path_buf()));
             Ok(())
         }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.files.lock().unwrap().contains_key(path)
+        }
+
+        fn read_file(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+
+        fn write_file(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.check(path)?;
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), contents.to_vec());
+            Ok(())
+        }
+
+        fn is_empty_dir(&self, path: &Path) -> bool {
+            let below = |p: &PathBuf| p != path && p.starts_with(path);
+            !self.files.lock().unwrap().keys().any(below)
+                && !self.dirs.lock().unwrap().iter().any(below)
+        }
+
+        fn remove_dir(&self, path: &Path) -> io::Result<()> {
+            if !self.is_empty_dir(path) {
+                return Err(io::ErrorKind::DirectoryNotEmpty.into());
+            }
+            self.dirs.lock().unwrap().remove(path);
+            Ok(())
+        }
+
+        fn create_dir(&self, path: &Path) -> io::Result<()> {
+            self.dirs.lock().unwrap().insert(path.to_path_buf());
+            Ok(())
+        }
+
+        fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+            if !self.exists(path) {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+            // 2024-05-17 12:00:00 UTC.
+            Ok(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_715_947_200))
+        }
+
+        fn is_symlink(&self, path: &Path) -> bool {
+            self.links.iter().any(|p| p == path)
+        }
+    }
+
+    fn failing_renamer() -> (Arc<MemoryFs>, Renamer) {
+        let fs = Arc::new(MemoryFs::empty_files(&["a.txt", "b.txt", "c.txt"]).failing("b.txt"));
+        (Arc::clone(&fs), memory_renamer(&fs))
+    }
+
+    fn content_rule(from: &str, to: &str, filter: &str) -> Rule {
+        Rule {
+            from: from.into(),
+            to: to.into(),
+            mode: RuleMode::Content,
+            path_filter: filter.into(),
+            ..Default::default()
+        }
+    }
+
+    fn memory_renamer(fs: &Arc<MemoryFs>) -> Renamer {
+        let logger = Arc::new(TestLogger {
+            messages: Arc::new(Mutex::new(Vec::new())),
+        });
+        Renamer::new(logger, Arc::clone(fs) as Arc<dyn FileSystem>)
     }
 
     struct TestLogger {
@@ -141,28 +1173,22 @@ mod tests {
         let logger = Arc::new(TestLogger {
             messages: Arc::clone(&messages),
         });
-        let fs = Arc::new(MockFs {
-            entries: vec![],
-            moved: Arc::new(Mutex::new(Vec::new())),
-        });
-        let renamer = Renamer::new(logger, fs);
+        let renamer = Renamer::new(logger, Arc::new(MemoryFs::default()));
 
         let rules = vec![
             Rule {
                 from: "src".into(),
                 to: "dst".into(),
-                file_match_count: None,
-                dir_match_count: None,
+                ..Default::default()
             },
             Rule {
                 from: "foo".into(),
                 to: "bar".into(),
-                file_match_count: None,
-                dir_match_count: None,
+                ..Default::default()
             },
         ];
 
-        renamer.execute(&rules, false).unwrap();
+        renamer.execute(&rules, ExecuteOptions::default()).unwrap();
 
         let collected = messages.lock().unwrap().clone();
         assert_eq!(
@@ -170,36 +1196,19 @@ mod tests {
             vec![
                 "Mapping 'src' -> 'dst'".to_string(),
                 "Mapping 'foo' -> 'bar'".to_string(),
+                "Execution finished: 0 succeeded, 0 skipped, 0 failed".to_string(),
             ]
         );
     }
 
     #[test]
     fn count_matches_sets_rule_match_count() {
-        let messages = Arc::new(Mutex::new(Vec::new()));
-        let logger = Arc::new(TestLogger {
-            messages: Arc::clone(&messages),
-        });
-        let fs = Arc::new(MockFs {
-            entries: vec![
-                FileEntry {
-                    path: PathBuf::from("a.txt"),
-                    is_dir: false,
-                },
-                FileEntry {
-                    path: PathBuf::from("b.rs"),
-                    is_dir: false,
-                },
-            ],
-            moved: Arc::new(Mutex::new(Vec::new())),
-        });
-        let renamer = Renamer::new(logger, fs);
+        let fs = Arc::new(MemoryFs::empty_files(&["a.txt", "b.rs"]));
+        let renamer = memory_renamer(&fs);
 
         let mut rule = Rule {
             from: ".*\\.txt$".into(),
-            to: "".into(),
-            file_match_count: None,
-            dir_match_count: None,
+            ..Default::default()
         };
 
         renamer.count_matches(&mut rule).unwrap();
@@ -207,42 +1216,49 @@ mod tests {
         assert_eq!(rule.dir_match_count, Some(0));
     }
 
+    #[test]
+    fn count_matches_compiles_the_rule_like_execute() {
+        let fs = Arc::new(MemoryFs::empty_files(&["./a_b/c.txt", "./d/e_f.txt"]));
+        let renamer = memory_renamer(&fs);
+        let mut rule = Rule {
+            from: "_".into(),
+            to: "-".into(),
+            name_only: true,
+            ..Default::default()
+        };
+
+        assert_eq!(renamer.count_matches(&mut rule).unwrap(), 1);
+        let report = renamer.execute(&[rule], ExecuteOptions::default()).unwrap();
+        assert_eq!(report.records.len(), 1);
+
+        let mut invalid = Rule {
+            from: "(a)".into(),
+            to: "$2".into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            renamer.count_matches(&mut invalid),
+            Err(AppError::InvalidTemplate { .. })
+        ));
+    }
+
     #[test]
     fn count_all_updates_all_rules() {
-        let logger = Arc::new(TestLogger {
-            messages: Arc::new(Mutex::new(Vec::new())),
-        });
-        let fs = Arc::new(MockFs {
-            entries: vec![
-                FileEntry {
-                    path: PathBuf::from("a.txt"),
-                    is_dir: false,
-                },
-                FileEntry {
-                    path: PathBuf::from("b.txt"),
-                    is_dir: false,
-                },
-            ],
-            moved: Arc::new(Mutex::new(Vec::new())),
-        });
-        let renamer = Renamer::new(logger, fs);
+        let fs = Arc::new(MemoryFs::empty_files(&["a.txt", "b.txt"]));
+        let renamer = memory_renamer(&fs);
 
         let mut rules = vec![
             Rule {
                 from: ".*a\\.txt".into(),
-                to: "".into(),
-                file_match_count: None,
-                dir_match_count: None,
+                ..Default::default()
             },
             Rule {
                 from: ".*b\\.txt".into(),
-                to: "".into(),
-                file_match_count: None,
-                dir_match_count: None,
+                ..Default::default()
             },
         ];
 
-        renamer.count_all_matches(&mut rules).unwrap();
+        assert!(renamer.count_all_matches(&mut rules).is_empty());
 
         assert_eq!(rules[0].file_match_count, Some(1));
         assert_eq!(rules[1].file_match_count, Some(1));
@@ -250,38 +1266,19 @@ mod tests {
 
     #[test]
     fn execute_moves_matching_files() {
-        let messages = Arc::new(Mutex::new(Vec::new()));
-        let logger = Arc::new(TestLogger {
-            messages: Arc::clone(&messages),
-        });
-        let moved = Arc::new(Mutex::new(Vec::new()));
-        let fs = Arc::new(MockFs {
-            entries: vec![
-                FileEntry {
-                    path: PathBuf::from("foo/a.txt"),
-                    is_dir: false,
-                },
-                FileEntry {
-                    path: PathBuf::from("foo/b.txt"),
-                    is_dir: false,
-                },
-            ],
-            moved: Arc::clone(&moved),
-        });
-        let renamer = Renamer::new(logger, fs);
+        let fs = Arc::new(MemoryFs::empty_files(&["foo/a.txt", "foo/b.txt"]));
+        let renamer = memory_renamer(&fs);
 
         let rules = vec![Rule {
             from: "foo/(.*)\\.txt".into(),
             to: "bar/$1.md".into(),
-            file_match_count: None,
-            dir_match_count: None,
+            ..Default::default()
         }];
 
-        renamer.execute(&rules, false).unwrap();
+        renamer.execute(&rules, ExecuteOptions::default()).unwrap();
 
-        let moved_files = moved.lock().unwrap().clone();
         assert_eq!(
-            moved_files,
+            fs.moves(),
             vec![
                 (PathBuf::from("foo/a.txt"), PathBuf::from("bar/a.md")),
                 (PathBuf::from("foo/b.txt"), PathBuf::from("bar/b.md")),
@@ -290,29 +1287,658 @@ mod tests {
     }
 
     #[test]
-    fn execute_dry_run_skips_moves() {
-        let logger = Arc::new(TestLogger {
-            messages: Arc::new(Mutex::new(Vec::new())),
-        });
-        let moved = Arc::new(Mutex::new(Vec::new()));
-        let fs = Arc::new(MockFs {
-            entries: vec![FileEntry {
-                path: PathBuf::from("foo.txt"),
-                is_dir: false,
-            }],
-            moved: Arc::clone(&moved),
+    fn glob_rule_moves_with_numbered_wildcards() {
+        let fs = Arc::new(MemoryFs::empty_files(&[
+            "./IMG_1.JPG",
+            "./trip/IMG_2.JPG",
+            "./notes.txt",
+        ]));
+        let renamer = memory_renamer(&fs);
+
+        let rules = vec![Rule {
+            from: "**/*.JPG".into(),
+            to: "$1$2.jpg".into(),
+            kind: RuleKind::Glob,
+            ..Default::default()
+        }];
+
+        renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+
+        assert_eq!(
+            fs.moves(),
+            vec![
+                (PathBuf::from("./IMG_1.JPG"), PathBuf::from("IMG_1.jpg")),
+                (
+                    PathBuf::from("./trip/IMG_2.JPG"),
+                    PathBuf::from("trip/IMG_2.jpg")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn execute_sanitizes_or_skips_unportable_destinations() {
+        let fs = Arc::new(MemoryFs::empty_files(&["./a.txt"]));
+        let renamer = memory_renamer(&fs);
+        let rules = vec![Rule {
+            from: r"(\w+)\.txt$".into(),
+            to: "what? $1.txt".into(),
+            ..Default::default()
+        }];
+        let mut options = ExecuteOptions {
+            sanitize: SanitizeOptions {
+                profile: PortabilityProfile::Portable,
+                rewrite: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let report = renamer.execute(&rules, options).unwrap();
+        assert!(fs.moves().is_empty());
+        assert_eq!(
+            report.records[0].outcome,
+            MoveOutcome::Skipped(SkipReason::NotPortable)
+        );
+
+        options.sanitize.rewrite = true;
+        renamer.execute(&rules, options).unwrap();
+        assert_eq!(
+            fs.moves(),
+            vec![(PathBuf::from("./a.txt"), PathBuf::from("./what_ a.txt"))]
+        );
+    }
+
+    #[test]
+    fn execute_skips_moves_outside_the_root_or_into_protected_paths() {
+        let fs = Arc::new(MemoryFs::empty_files(&[
+            "./up.txt",
+            "./hook.txt",
+            "./ok.txt",
+        ]));
+        let mut renamer = memory_renamer(&fs);
+        let rules = vec![
+            Rule {
+                from: r"^\./up\.txt$".into(),
+                to: "../up.txt".into(),
+                ..Default::default()
+            },
+            Rule {
+                from: r"^\./hook\.txt$".into(),
+                to: "./.git/hooks/hook.txt".into(),
+                ..Default::default()
+            },
+            Rule {
+                from: r"^\./ok\.txt$".into(),
+                to: "./done/ok.txt".into(),
+                ..Default::default()
+            },
+        ];
+
+        let report = renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+        let outcomes: Vec<_> = report.records.iter().map(|r| &r.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                &MoveOutcome::Skipped(SkipReason::OutsideRoot),
+                &MoveOutcome::Skipped(SkipReason::Protected),
+                &MoveOutcome::Succeeded,
+            ]
+        );
+        assert_eq!(
+            fs.moves(),
+            vec![(PathBuf::from("./ok.txt"), PathBuf::from("./done/ok.txt"))]
+        );
+
+        renamer.set_confinement(Confinement {
+            enabled: false,
+            protected: Vec::new(),
+            ..Confinement::default()
         });
-        let renamer = Renamer::new(logger, fs);
+        renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+        assert!(fs.contents("../up.txt").is_some());
+        assert!(fs.contents("./.git/hooks/hook.txt").is_some());
+    }
+
+    #[test]
+    fn execute_matches_and_writes_normalized_names() {
+        let fs = Arc::new(MemoryFs::empty_files(&[
+            "./Cafe\u{301}/De\u{301}ja\u{300} vu.mp3",
+        ]));
+        let renamer = memory_renamer(&fs);
+        let rules = vec![Rule {
+            from: "Caf\u{E9}/([^/]+)\\.mp3$".into(),
+            to: "Caf\u{E9}/${slug(1)}.mp3".into(),
+            normalization: Normalization::Nfc,
+            ..Default::default()
+        }];
+
+        renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+
+        assert_eq!(
+            fs.moves(),
+            vec![(
+                PathBuf::from("./Cafe\u{301}/De\u{301}ja\u{300} vu.mp3"),
+                PathBuf::from("./Cafe\u{301}/deja-vu.mp3")
+            )]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn execute_moves_non_utf8_names_under_their_exact_bytes() {
+        let source = os_path::path_from_bytes(b"./caf\xE9/IMG_1.JPG".to_vec()).unwrap();
+        let fs = Arc::new(MemoryFs::with_paths([(source.clone(), Vec::new())]));
+        let renamer = memory_renamer(&fs);
+
+        let rules = vec![Rule {
+            from: r"IMG_(\d+)\.JPG$".into(),
+            to: "photo-$1.jpg".into(),
+            ..Default::default()
+        }];
+        renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+
+        let destination = os_path::path_from_bytes(b"./caf\xE9/photo-1.jpg".to_vec()).unwrap();
+        assert_eq!(fs.moves(), vec![(source, destination)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn transcode_rule_renames_names_and_reports_undecodable_ones() {
+        let fs = Arc::new(MemoryFs::with_paths(
+            [&b"./caf\xE9.txt"[..], b"./ok.txt", b"./\x82.txt"]
+                .iter()
+                .map(|p| (os_path::path_from_bytes(p.to_vec()).unwrap(), Vec::new())),
+        ));
+        let renamer = memory_renamer(&fs);
+
+        let rules = vec![Rule {
+            mode: RuleMode::Transcode,
+            to: "$9 is ignored".into(),
+            transcoding: crate::domain::Transcoding {
+                source: Some("Shift_JIS".into()),
+                target: "UTF-8".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }];
+        let report = renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+
+        assert!(fs.moves().is_empty());
+        assert_eq!(
+            report.records[0].outcome,
+            MoveOutcome::Skipped(SkipReason::UndecodableName)
+        );
+
+        let rules = vec![Rule {
+            mode: RuleMode::Transcode,
+            transcoding: crate::domain::Transcoding {
+                source: Some("latin1".into()),
+                target: "UTF-8".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }];
+        renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+
+        assert_eq!(
+            fs.moves(),
+            vec![
+                (
+                    os_path::path_from_bytes(b"./caf\xE9.txt".to_vec()).unwrap(),
+                    PathBuf::from("./café.txt")
+                ),
+                (
+                    os_path::path_from_bytes(b"./\x82.txt".to_vec()).unwrap(),
+                    PathBuf::from("./‚.txt")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn execute_dry_run_skips_moves() {
+        let fs = Arc::new(MemoryFs::empty_files(&["foo.txt"]));
+        let renamer = memory_renamer(&fs);
 
         let rules = vec![Rule {
             from: "foo.txt".into(),
             to: "bar.txt".into(),
-            file_match_count: None,
-            dir_match_count: None,
+            ..Default::default()
+        }];
+
+        let report = renamer
+            .execute(
+                &rules,
+                ExecuteOptions {
+                    dry_run: true,
+                    ..ExecuteOptions::default()
+                },
+            )
+            .unwrap();
+
+        assert!(fs.moves().is_empty());
+        assert_eq!(
+            report.records[0].outcome,
+            MoveOutcome::Skipped(SkipReason::DryRun)
+        );
+    }
+
+    #[test]
+    fn execute_aborts_after_first_failure_by_default() {
+        let (fs, renamer) = failing_renamer();
+        let rules = vec![Rule {
+            from: "(.)\\.txt".into(),
+            to: "$1.md".into(),
+            ..Default::default()
+        }];
+
+        let report = renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+
+        assert!(report.aborted);
+        assert_eq!(fs.moves().len(), 1);
+        let outcomes: Vec<_> = report.records.iter().map(|r| r.outcome.status()).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                MoveStatus::Succeeded,
+                MoveStatus::Failed,
+                MoveStatus::Skipped
+            ]
+        );
+        assert_eq!(
+            report.records[1].outcome,
+            MoveOutcome::Failed {
+                kind: io::ErrorKind::PermissionDenied,
+                message: io::Error::from(io::ErrorKind::PermissionDenied).to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn execute_continues_on_error_when_requested() {
+        let (fs, renamer) = failing_renamer();
+        let rules = vec![Rule {
+            from: "(.)\\.txt".into(),
+            to: "$1.md".into(),
+            ..Default::default()
         }];
 
-        renamer.execute(&rules, true).unwrap();
+        let report = renamer
+            .execute(
+                &rules,
+                ExecuteOptions {
+                    continue_on_error: true,
+                    ..ExecuteOptions::default()
+                },
+            )
+            .unwrap();
 
-        assert!(moved.lock().unwrap().is_empty());
+        assert!(!report.aborted);
+        assert_eq!(report.count(MoveStatus::Succeeded), 2);
+        assert_eq!(report.count(MoveStatus::Failed), 1);
+        assert_eq!(fs.moves().len(), 2);
+    }
+
+    #[test]
+    fn execute_rejects_invalid_rule_before_moving() {
+        let (fs, renamer) = failing_renamer();
+        let rules = vec![
+            Rule {
+                from: "a\\.txt".into(),
+                to: "z.txt".into(),
+                ..Default::default()
+            },
+            Rule {
+                from: "(unclosed".into(),
+                to: "x".into(),
+                ..Default::default()
+            },
+        ];
+
+        let err = renamer
+            .execute(&rules, ExecuteOptions::default())
+            .unwrap_err();
+
+        assert_eq!(err.rule, 1);
+        assert!(matches!(err.error, AppError::InvalidPattern { .. }));
+        assert!(fs.moves().is_empty());
+    }
+
+    #[test]
+    fn execute_reports_conflicting_destinations() {
+        let (fs, renamer) = failing_renamer();
+        let rules = vec![Rule {
+            from: ".\\.txt".into(),
+            to: "same.txt".into(),
+            ..Default::default()
+        }];
+
+        let err = renamer
+            .execute(&rules, ExecuteOptions::default())
+            .unwrap_err();
+
+        match err.error {
+            AppError::Conflict {
+                destination,
+                sources,
+            } => {
+                assert_eq!(destination, PathBuf::from("same.txt"));
+                assert_eq!(sources.len(), 3);
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+        assert!(fs.moves().is_empty());
+    }
+
+    #[test]
+    fn count_all_reports_errors_per_rule() {
+        let renamer = memory_renamer(&Arc::new(MemoryFs::default()));
+        let mut rules = vec![
+            Rule::default(),
+            Rule {
+                from: "[".into(),
+                ..Rule::default()
+            },
+        ];
+
+        let errors = renamer.count_all_matches(&mut rules);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule, 1);
+        assert_eq!(rules[0].file_match_count, Some(0));
+    }
+
+    #[test]
+    fn execute_rejects_unknown_template_group() {
+        let (fs, renamer) = failing_renamer();
+        let rules = vec![Rule {
+            from: "(.)\\.txt".into(),
+            to: "$2.md".into(),
+            ..Default::default()
+        }];
+
+        let err = renamer
+            .execute(&rules, ExecuteOptions::default())
+            .unwrap_err();
+
+        assert!(matches!(err.error, AppError::InvalidTemplate { .. }));
+        assert!(fs.moves().is_empty());
+    }
+
+    #[test]
+    fn content_rule_rewrites_filtered_files() {
+        let fs = Arc::new(MemoryFs::with_files(&[
+            ("src/a.rs", b"use old_name;\nold_name();\n"),
+            ("src/b.md", b"old_name"),
+            ("src/c.rs", b"untouched"),
+            ("src/d.rs", b"old_name\0binary"),
+        ]));
+        let renamer = memory_renamer(&fs);
+        let rule = content_rule("old_name", "new_name", r"\.rs$");
+
+        let preview = renamer.preview_content(&rule).unwrap();
+        assert_eq!(preview.changes.len(), 1);
+        assert_eq!(
+            preview.skipped,
+            vec![("src/d.rs".into(), SkipReason::Binary)]
+        );
+
+        let report = renamer.execute(&[rule], ExecuteOptions::default()).unwrap();
+        assert_eq!(
+            fs.contents("src/a.rs").unwrap(),
+            b"use new_name;\nnew_name();\n"
+        );
+        assert_eq!(fs.contents("src/b.md").unwrap(), b"old_name");
+        assert_eq!(
+            report.records[0].operation,
+            Operation::Rewrite { replacements: 2 }
+        );
+        assert_eq!(report.count(MoveStatus::Succeeded), 1);
+        assert_eq!(report.count(MoveStatus::Skipped), 1);
+        assert_eq!(report.journal.len(), 1);
+    }
+
+    #[test]
+    fn content_rule_respects_dry_run() {
+        let fs = Arc::new(MemoryFs::with_files(&[("a.txt", b"foo")]));
+        let renamer = memory_renamer(&fs);
+        let options = ExecuteOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = renamer
+            .execute(&[content_rule("foo", "bar", "")], options)
+            .unwrap();
+        assert_eq!(fs.contents("a.txt").unwrap(), b"foo");
+        assert_eq!(
+            report.records[0].outcome,
+            MoveOutcome::Skipped(SkipReason::DryRun)
+        );
+        assert!(report.journal.is_empty());
+    }
+
+    #[test]
+    fn failed_rewrite_aborts_remaining_files() {
+        let fs = Arc::new(
+            MemoryFs::with_files(&[("a.txt", b"foo"), ("b.txt", b"foo")]).failing("a.txt"),
+        );
+        let report = memory_renamer(&fs)
+            .execute(&[content_rule("foo", "bar", "")], ExecuteOptions::default())
+            .unwrap();
+        assert!(report.aborted);
+        assert_eq!(report.records.len(), 1);
+        assert_eq!(fs.contents("b.txt").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn content_rules_skip_symlinks() {
+        let mut fs = MemoryFs::with_files(&[("a.txt", b"foo"), ("link.txt", b"foo")]);
+        fs.links.push(PathBuf::from("link.txt"));
+        let fs = Arc::new(fs);
+        let renamer = memory_renamer(&fs);
+        let rule = content_rule("foo", "bar", "");
+
+        let preview = renamer.preview_content(&rule).unwrap();
+        assert_eq!(preview.changes.len(), 1);
+        assert_eq!(
+            preview.skipped,
+            vec![(PathBuf::from("link.txt"), SkipReason::Symlink)]
+        );
+
+        let report = renamer.execute(&[rule], ExecuteOptions::default()).unwrap();
+        assert_eq!(fs.contents("a.txt").unwrap(), b"bar");
+        assert_eq!(fs.contents("link.txt").unwrap(), b"foo");
+        assert!(
+            report
+                .records
+                .iter()
+                .any(|record| record.source == Path::new("link.txt")
+                    && record.outcome == MoveOutcome::Skipped(SkipReason::Symlink))
+        );
+    }
+
+    #[test]
+    fn undo_reverts_moves_and_rewrites_in_reverse_order() {
+        let fs = Arc::new(MemoryFs::with_files(&[
+            ("a.txt", b"foo"),
+            ("keep.txt", b"x"),
+        ]));
+        let renamer = memory_renamer(&fs);
+        let rules = [
+            content_rule("foo", "bar", r"a\.txt"),
+            Rule {
+                from: r"^a\.txt$".into(),
+                to: "b.txt".into(),
+                ..Default::default()
+            },
+        ];
+        let report = renamer.execute(&rules, ExecuteOptions::default()).unwrap();
+        assert_eq!(fs.contents("b.txt").unwrap(), b"bar");
+
+        let undo = renamer.undo(report.journal);
+        assert_eq!(undo.count(MoveStatus::Succeeded), 2);
+        assert_eq!(fs.contents("a.txt").unwrap(), b"foo");
+        assert!(fs.contents("b.txt").is_none());
+    }
+
+    #[test]
+    fn undo_leaves_files_edited_after_the_batch() {
+        let fs = Arc::new(MemoryFs::with_files(&[("a.txt", b"foo")]));
+        let renamer = memory_renamer(&fs);
+        let report = renamer
+            .execute(&[content_rule("foo", "bar", "")], ExecuteOptions::default())
+            .unwrap();
+        fs.write_file(Path::new("a.txt"), b"edited").unwrap();
+
+        let undo = renamer.undo(report.journal);
+        assert_eq!(undo.count(MoveStatus::Failed), 1);
+        assert_eq!(fs.contents("a.txt").unwrap(), b"edited");
+    }
+
+    #[test]
+    fn removes_only_directories_emptied_by_the_batch() {
+        let fs = Arc::new(MemoryFs::with_files(&[
+            ("./old/a/x.txt", b"x"),
+            ("./old/b/y.txt", b"y"),
+        ]));
+        fs.create_dir(Path::new("./old/empty")).unwrap();
+        let renamer = memory_renamer(&fs);
+        let rules = [Rule {
+            from: r"^\./old/\w/(\w+\.txt)$".into(),
+            to: "./new/$1".into(),
+            ..Default::default()
+        }];
+        let options = ExecuteOptions {
+            remove_empty_dirs: true,
+            ..Default::default()
+        };
+
+        let report = renamer.execute(&rules, options).unwrap();
+        let removed: Vec<_> = report
+            .records
+            .iter()
+            .filter(|r| r.operation == Operation::RemoveDir)
+            .map(|r| r.source.clone())
+            .collect();
+        // `./old` still holds the directory that was empty before.
+        assert_eq!(
+            removed,
+            vec![PathBuf::from("./old/a"), PathBuf::from("./old/b")]
+        );
+        assert_eq!(report.removed_dirs(), 2);
+        assert!(report.summary().ends_with("; 2 empty directories removed"));
+
+        let undo = renamer.undo(report.journal);
+        assert_eq!(undo.count(MoveStatus::Succeeded), 4);
+        assert_eq!(undo.records[0].operation, Operation::CreateDir);
+        assert_eq!(fs.contents("./old/a/x.txt").unwrap(), b"x");
+        assert!(fs.dirs.lock().unwrap().contains(Path::new("./old/b")));
+    }
+
+    #[test]
+    fn search_groups_hits_by_file() {
+        let fs = Arc::new(MemoryFs::with_files(&[
+            ("a.rs", b"fn todo() {}\n// TODO: later\n"),
+            ("b.md", b"TODO"),
+            ("c.rs", b"TODO\0"),
+            ("d.rs", b"done"),
+        ]));
+        let results = memory_renamer(&fs).search("(?i)todo", r"\.rs$").unwrap();
+        assert_eq!(results.files.len(), 1);
+        assert_eq!(results.files[0].path, PathBuf::from("a.rs"));
+        let numbers: Vec<usize> = results.files[0]
+            .lines
+            .iter()
+            .map(|l| l.line_number)
+            .collect();
+        assert_eq!(numbers, vec![1, 2]);
+        assert_eq!(results.skipped, vec![("c.rs".into(), SkipReason::Binary)]);
+        assert!(!results.truncated);
+    }
+
+    #[test]
+    fn rules_from_a_search_only_touch_its_files() {
+        let fs = Arc::new(MemoryFs::with_files(&[("a.txt", b"x"), ("b.txt", b"x")]));
+        let renamer = memory_renamer(&fs);
+        let mut rule = Rule {
+            from: r"^(.*)\.txt$".into(),
+            to: "$1.md".into(),
+            paths: vec![PathBuf::from("b.txt")],
+            ..Default::default()
+        };
+
+        assert_eq!(renamer.count_matches(&mut rule).unwrap(), 1);
+        renamer.execute(&[rule], ExecuteOptions::default()).unwrap();
+        assert!(fs.contents("a.txt").is_some());
+        assert!(fs.contents("b.md").is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rule_paths_compare_exact_bytes() {
+        let path = |bytes: &[u8]| os_path::path_from_bytes(bytes.to_vec()).unwrap();
+        let fs = Arc::new(MemoryFs::with_paths([
+            (path(b"./a\xE9.txt"), Vec::new()),
+            (path(b"./a\xE8.txt"), Vec::new()),
+        ]));
+        let renamer = memory_renamer(&fs);
+        let mut rule = Rule {
+            from: r"\.txt$".into(),
+            to: ".md".into(),
+            paths: vec![path(b"./a\xE9.txt")],
+            ..Default::default()
+        };
+
+        assert_eq!(renamer.count_matches(&mut rule).unwrap(), 1);
+        renamer.execute(&[rule], ExecuteOptions::default()).unwrap();
+        assert_eq!(
+            fs.moves(),
+            vec![(path(b"./a\xE9.txt"), path(b"./a\xE9.md"))]
+        );
+    }
+
+    #[test]
+    fn std_write_file_replaces_contents() {
+        let dir = std::env::temp_dir().join(format!("regex-gui-write-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "old").unwrap();
+
+        StdFileSystem.write_file(&path, b"new").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        // Only the target remains; the temp file was renamed over it.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn std_write_file_leaves_symlinks_alone() {
+        let dir = std::env::temp_dir().join(format!("regex-gui-link-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.txt");
+        let link = dir.join("link.txt");
+        std::fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        assert!(StdFileSystem.is_symlink(&link));
+        assert!(!StdFileSystem.is_symlink(&target));
+        let error = StdFileSystem.write_file(&link, b"new").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(link.is_symlink());
+        assert_eq!(std::fs::read(&target).unwrap(), b"old");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn std_find_matches_accepts_absolute_patterns() {
+        let absolute = Path::new("Cargo.toml").canonicalize().unwrap();
+        let pattern = format!("^{}$", regex::escape(&absolute.to_string_lossy()));
+        let engine = compile_engine(&pattern, Engine::Regex, RegexLimits::default()).unwrap();
+        let matches = StdFileSystem.find_matches(engine.as_ref()).unwrap();
+        let paths: Vec<_> = matches.iter().map(|m| m.path.as_path()).collect();
+        assert_eq!(paths, vec![Path::new("./Cargo.toml")]);
     }
 }
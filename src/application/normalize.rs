@@ -0,0 +1,220 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use unicode_normalization::UnicodeNormalization;
+
+use super::engine::GroupRanges;
+use super::os_path::{path_bytes, path_from_bytes};
+use super::{AppError, PatternEngine};
+use crate::domain::Normalization;
+
+/// Normalization forms in the order the rule editor offers them.
+pub const NORMALIZATIONS: [Normalization; 4] = [
+    Normalization::None,
+    Normalization::Nfc,
+    Normalization::Nfd,
+    Normalization::Nfkc,
+];
+
+pub fn normalization_name(form: Normalization) -> &'static str {
+    match form {
+        Normalization::None => "—",
+        Normalization::Nfc => "NFC",
+        Normalization::Nfd => "NFD",
+        Normalization::Nfkc => "NFKC",
+    }
+}
+
+pub fn normalization_description(form: Normalization) -> &'static str {
+    match form {
+        Normalization::None => "Match and rename paths as stored",
+        Normalization::Nfc => "Composed: 'é' is one character, as on Windows and Linux",
+        Normalization::Nfd => "Decomposed: 'é' is 'e' and an accent, as on macOS",
+        Normalization::Nfkc => "Composed, with look-alikes such as 'ﬁ' or '１' folded",
+    }
+}
+
+/// Normalizes `text`, borrowing it when it already is in `form`.
+pub fn normalize(text: &str, form: Normalization) -> Cow<'_, str> {
+    let normalized: String = match form {
+        Normalization::None => return Cow::Borrowed(text),
+        Normalization::Nfc => text.nfc().collect(),
+        Normalization::Nfd => text.nfd().collect(),
+        Normalization::Nfkc => text.nfkc().collect(),
+    };
+    if normalized == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+/// Normalizes the valid UTF-8 in `bytes`, keeping invalid bytes as they are.
+pub fn normalize_bytes(bytes: &[u8], form: Normalization) -> Cow<'_, [u8]> {
+    if form == Normalization::None {
+        return Cow::Borrowed(bytes);
+    }
+    let mut out = Vec::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        out.extend_from_slice(normalize(chunk.valid(), form).as_bytes());
+        out.extend_from_slice(chunk.invalid());
+    }
+    if out == bytes {
+        Cow::Borrowed(bytes)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+/// Wraps `engine` so it matches the normalized form of every haystack.
+pub fn normalized(engine: Box<dyn PatternEngine>, form: Normalization) -> Box<dyn PatternEngine> {
+    match form {
+        Normalization::None => engine,
+        _ => Box::new(NormalizedEngine {
+            inner: engine,
+            form,
+        }),
+    }
+}
+
+/// Matches the normalized form of each haystack, so a name stored in NFD
+/// matches a pattern typed in NFC. Group ranges refer to the normalized
+/// haystack; callers slicing the haystack normalize it first.
+#[derive(Debug)]
+struct NormalizedEngine {
+    inner: Box<dyn PatternEngine>,
+    form: Normalization,
+}
+
+impl PatternEngine for NormalizedEngine {
+    fn is_match(&self, haystack: &str) -> Result<bool, AppError> {
+        self.inner.is_match(&normalize(haystack, self.form))
+    }
+
+    fn captures(&self, haystack: &str) -> Result<Option<GroupRanges>, AppError> {
+        self.inner.captures(&normalize(haystack, self.form))
+    }
+
+    fn captures_all(&self, haystack: &str) -> Result<Vec<GroupRanges>, AppError> {
+        self.inner.captures_all(&normalize(haystack, self.form))
+    }
+
+    fn captures_len(&self) -> usize {
+        self.inner.captures_len()
+    }
+
+    fn capture_names(&self) -> Vec<Option<String>> {
+        self.inner.capture_names()
+    }
+
+    fn is_match_bytes(&self, haystack: &[u8]) -> Result<bool, AppError> {
+        self.inner
+            .is_match_bytes(&normalize_bytes(haystack, self.form))
+    }
+
+    fn captures_bytes(&self, haystack: &[u8]) -> Result<Option<GroupRanges>, AppError> {
+        self.inner
+            .captures_bytes(&normalize_bytes(haystack, self.form))
+    }
+
+    fn captures_all_bytes(&self, haystack: &[u8]) -> Result<Vec<GroupRanges>, AppError> {
+        self.inner
+            .captures_all_bytes(&normalize_bytes(haystack, self.form))
+    }
+
+    fn find_all(&self, haystack: &str) -> Result<Vec<Range<usize>>, AppError> {
+        self.inner.find_all(&normalize(haystack, self.form))
+    }
+}
+
+/// Normalizes `destination`, except for the leading components naming the
+/// same parent directories as `source`: those keep the spelling they have
+/// on disk, so files are not moved into a look-alike directory.
+pub fn normalize_destination(source: &Path, destination: &Path, form: Normalization) -> PathBuf {
+    if form == Normalization::None {
+        return destination.to_path_buf();
+    }
+    let mut parents = source.parent().into_iter().flat_map(Path::components);
+    let mut shared = true;
+    let mut path = PathBuf::new();
+    for component in destination.components() {
+        let parent = parents.next();
+        let Component::Normal(name) = component else {
+            path.push(component);
+            continue;
+        };
+        let Some(bytes) = path_bytes(Path::new(name)) else {
+            path.push(name);
+            continue;
+        };
+        let normalized = normalize_bytes(bytes, form);
+        let original = match parent {
+            Some(Component::Normal(parent)) if shared => path_bytes(Path::new(parent))
+                .filter(|parent| normalize_bytes(parent, form) == normalized)
+                .map(|_| parent),
+            _ => None,
+        };
+        shared = original.is_some();
+        match original {
+            Some(parent) => path.push(parent),
+            None => match path_from_bytes(normalized.into_owned()) {
+                Some(name) => path.push(name),
+                None => path.push(name),
+            },
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::compile_pattern;
+
+    const NFC: &str = "Caf\u{E9}";
+    const NFD: &str = "Cafe\u{301}";
+
+    #[test]
+    fn normalizes_text_and_keeps_invalid_bytes() {
+        assert_eq!(normalize(NFD, Normalization::Nfc), NFC);
+        assert_eq!(normalize(NFC, Normalization::Nfd), NFD);
+        assert_eq!(
+            normalize("\u{FB01}le\u{FF11}", Normalization::Nfkc),
+            "file1"
+        );
+        assert!(matches!(
+            normalize(NFC, Normalization::Nfc),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            normalize_bytes(b"Cafe\xCC\x81\xFF", Normalization::Nfc).as_ref(),
+            b"Caf\xC3\xA9\xFF"
+        );
+    }
+
+    #[test]
+    fn normalized_engine_matches_either_form() {
+        let re = normalized(
+            Box::new(compile_pattern(&format!("{NFC}/(.+)")).unwrap()),
+            Normalization::Nfc,
+        );
+        let path = format!("./{NFD}/a.txt");
+        assert!(re.is_match_bytes(path.as_bytes()).unwrap());
+        assert_eq!(
+            re.captures(&path).unwrap(),
+            Some(vec![Some(2..13), Some(8..13)]),
+            "ranges refer to the normalized haystack"
+        );
+    }
+
+    #[test]
+    fn keeps_the_spelling_of_existing_parents() {
+        let source = PathBuf::from(format!("./{NFD}/{NFD}.txt"));
+        let destination = PathBuf::from(format!("./{NFC}/{NFC}/new {NFD}.txt"));
+        assert_eq!(
+            normalize_destination(&source, &destination, Normalization::Nfc),
+            PathBuf::from(format!("./{NFD}/{NFC}/new {NFC}.txt"))
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The bytes of `path` as the OS stores them. Unix paths are arbitrary
+/// bytes; elsewhere only paths that are valid Unicode have bytes, so other
+/// paths are never matched rather than matched under a lossy name.
+pub fn path_bytes(path: &Path) -> Option<&[u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(path.as_os_str().as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        path.to_str().map(str::as_bytes)
+    }
+}
+
+/// The path with exactly these bytes, or `None` where the platform cannot
+/// represent them.
+pub fn path_from_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8(bytes).ok().map(PathBuf::from)
+    }
+}
+
+/// Shows `path` with every byte that is not valid UTF-8 escaped as `\xNN`,
+/// so distinct names never look alike.
+pub fn display_path(path: &Path) -> String {
+    match path_bytes(path) {
+        Some(bytes) => display_bytes(bytes).0,
+        None => path.display().to_string(),
+    }
+}
+
+/// Escapes the invalid UTF-8 in `bytes` like [`display_path`]. The second
+/// value maps every byte offset of `bytes` (and its end) to the matching
+/// offset in the escaped text.
+pub fn display_bytes(bytes: &[u8]) -> (String, Vec<usize>) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut offsets = Vec::with_capacity(bytes.len() + 1);
+    for chunk in bytes.utf8_chunks() {
+        let valid = chunk.valid();
+        offsets.extend((0..valid.len()).map(|i| text.len() + i));
+        text.push_str(valid);
+        for byte in chunk.invalid() {
+            offsets.push(text.len());
+            let _ = write!(text, "\\x{byte:02X}");
+        }
+    }
+    offsets.push(text.len());
+    (text, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_invalid_bytes_and_maps_offsets() {
+        let (text, offsets) = display_bytes(b"a\xFFb\xC3\xA9");
+        assert_eq!(text, r"a\xFFbé");
+        assert_eq!(offsets, vec![0, 1, 5, 6, 7, 8]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn round_trips_non_utf8_paths() {
+        let path = path_from_bytes(b"./caf\xE9.txt".to_vec()).unwrap();
+        assert_eq!(path_bytes(&path), Some(&b"./caf\xE9.txt"[..]));
+        assert_eq!(display_path(&path), r"./caf\xE9.txt");
+    }
+}
@@ -0,0 +1,135 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+
+use super::AppError;
+use super::error::pattern_error;
+
+/// Maximum number of matches collected for display.
+pub const MATCH_LIMIT: usize = 1000;
+
+/// Flags applied to a playground pattern, mirroring the inline `(?imsxUuR)`
+/// flags and the corresponding [`RegexBuilder`] options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PatternFlags {
+    pub case_insensitive: bool,
+    pub multi_line: bool,
+    pub dot_matches_new_line: bool,
+    pub ignore_whitespace: bool,
+    pub swap_greed: bool,
+    pub unicode: bool,
+    pub crlf: bool,
+}
+
+impl Default for PatternFlags {
+    fn default() -> Self {
+        Self {
+            case_insensitive: false,
+            multi_line: false,
+            dot_matches_new_line: false,
+            ignore_whitespace: false,
+            swap_greed: false,
+            unicode: true,
+            crlf: false,
+        }
+    }
+}
+
+impl PatternFlags {
+    /// Compiles `pattern` with these flags.
+    pub fn compile(&self, pattern: &str) -> Result<Regex, AppError> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .ignore_whitespace(self.ignore_whitespace)
+            .swap_greed(self.swap_greed)
+            .unicode(self.unicode)
+            .crlf(self.crlf)
+            .build()
+            .map_err(|error| {
+                let parser = regex_syntax::ParserBuilder::new()
+                    .case_insensitive(self.case_insensitive)
+                    .multi_line(self.multi_line)
+                    .dot_matches_new_line(self.dot_matches_new_line)
+                    .ignore_whitespace(self.ignore_whitespace)
+                    .swap_greed(self.swap_greed)
+                    .unicode(self.unicode)
+                    .crlf(self.crlf)
+                    .build();
+                pattern_error(pattern, &error, parser)
+            })
+    }
+
+    /// Compiles `patterns` into a [`RegexSet`] with these flags.
+    pub fn compile_set(&self, patterns: &[&str]) -> Result<RegexSet, AppError> {
+        RegexSetBuilder::new(patterns)
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .ignore_whitespace(self.ignore_whitespace)
+            .swap_greed(self.swap_greed)
+            .unicode(self.unicode)
+            .crlf(self.crlf)
+            .build()
+            .map_err(|error| AppError::InvalidPattern {
+                pattern: patterns.join("\n"),
+                message: error.to_string(),
+                span: None,
+            })
+    }
+}
+
+/// Byte ranges of every capture group of the first [`MATCH_LIMIT`] matches of
+/// `re` in `text`. Index 0 of each entry is the whole match.
+pub fn match_groups(re: &Regex, text: &str) -> Vec<Vec<Option<Range<usize>>>> {
+    re.captures_iter(text)
+        .take(MATCH_LIMIT)
+        .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_change_matching() {
+        let text = "Foo\nfoo";
+        let plain = PatternFlags::default().compile("^foo$").unwrap();
+        assert!(match_groups(&plain, text).is_empty());
+
+        let flags = PatternFlags {
+            case_insensitive: true,
+            multi_line: true,
+            ..PatternFlags::default()
+        };
+        let re = flags.compile("^foo$").unwrap();
+        assert_eq!(
+            match_groups(&re, text),
+            vec![vec![Some(0..3)], vec![Some(4..7)]]
+        );
+    }
+
+    #[test]
+    fn collects_group_offsets() {
+        let re = PatternFlags::default().compile(r"(\w)(\d)?").unwrap();
+        assert_eq!(
+            match_groups(&re, "é a1"),
+            vec![
+                vec![Some(0..2), Some(0..2), None],
+                vec![Some(3..5), Some(3..4), Some(4..5)],
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_are_located_with_the_same_flags() {
+        let flags = PatternFlags {
+            ignore_whitespace: true,
+            ..PatternFlags::default()
+        };
+        let err = flags.compile("a b (").unwrap_err();
+        assert_eq!(err.span(), Some(4..5));
+    }
+}
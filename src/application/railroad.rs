@@ -0,0 +1,649 @@
+use std::fmt::Write as _;
+
+use regex_syntax::ast::{
+    self, Ast, ClassPerlKind, GroupKind, RepetitionKind, RepetitionOp, RepetitionRange,
+};
+
+use super::AppError;
+use super::explain::parse_ast;
+
+/// A point in diagram units; the painter and the SVG export scale these.
+pub type Point = [f32; 2];
+
+/// Horizontal advance of one character in a box label.
+const CHAR_WIDTH: f32 = 7.8;
+/// Horizontal advance of one character in a caption.
+const CAPTION_CHAR_WIDTH: f32 = 6.6;
+const BOX_HEIGHT: f32 = 24.0;
+const BOX_PADDING: f32 = 8.0;
+const GAP: f32 = 12.0;
+const CORNER: f32 = 10.0;
+const VERTICAL_GAP: f32 = 8.0;
+const CAPTION_HEIGHT: f32 = 14.0;
+const GROUP_PADDING: f32 = 8.0;
+const MARGIN: f32 = 16.0;
+const LEAD: f32 = 16.0;
+
+/// Visual kind of a box in the diagram.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoxKind {
+    /// Literal text, drawn with rounded corners.
+    Literal,
+    /// A character class such as `\d` or `[a-z]`.
+    Class,
+    /// A zero-width assertion or a flag change.
+    Assertion,
+}
+
+/// A drawing primitive of a laid-out [`Diagram`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Box {
+        min: Point,
+        max: Point,
+        label: String,
+        kind: BoxKind,
+    },
+    /// Dashed outline around a group, with its caption in the top-left corner.
+    Frame {
+        min: Point,
+        max: Point,
+        label: String,
+    },
+    /// Connected line segments.
+    Path(Vec<Point>),
+    Caption {
+        center: Point,
+        text: String,
+    },
+}
+
+/// A railroad diagram of a pattern, laid out in abstract units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagram {
+    pub width: f32,
+    pub height: f32,
+    pub shapes: Vec<Shape>,
+}
+
+/// Diagram tree built from the pattern's AST.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Box {
+        label: String,
+        kind: BoxKind,
+    },
+    Sequence(Vec<Node>),
+    /// The first branch runs along the main line, the others below it.
+    Choice(Vec<Node>),
+    Repeat {
+        body: Box<Node>,
+        /// A bypass above the body, for a minimum of zero.
+        skip: bool,
+        /// A return path below the body, for a maximum above one.
+        back: bool,
+        label: Option<String>,
+    },
+    Group {
+        label: String,
+        body: Box<Node>,
+    },
+}
+
+/// Extent of a laid-out node around its main line.
+#[derive(Clone, Copy)]
+struct Size {
+    width: f32,
+    up: f32,
+    down: f32,
+}
+
+/// Parses `pattern` and lays it out as a railroad diagram.
+pub fn railroad(pattern: &str) -> Result<Diagram, AppError> {
+    let ast = parse_ast(pattern)?;
+    Ok(Diagram::layout(&node(&ast, pattern)))
+}
+
+fn node(ast: &Ast, pattern: &str) -> Node {
+    let source = |span: &ast::Span| pattern[span.start.offset..span.end.offset].to_string();
+    match ast {
+        Ast::Empty(_) => Node::Sequence(Vec::new()),
+        Ast::Flags(set) => Node::Box {
+            label: source(&set.span),
+            kind: BoxKind::Assertion,
+        },
+        Ast::Literal(lit) => Node::Box {
+            label: quoted(&lit.c.to_string()),
+            kind: BoxKind::Literal,
+        },
+        Ast::Dot(_) => Node::Box {
+            label: "any character".to_string(),
+            kind: BoxKind::Class,
+        },
+        Ast::Assertion(assertion) => Node::Box {
+            label: match assertion.kind {
+                ast::AssertionKind::StartLine | ast::AssertionKind::StartText => "start".into(),
+                ast::AssertionKind::EndLine | ast::AssertionKind::EndText => "end".into(),
+                ast::AssertionKind::WordBoundary => "word boundary".into(),
+                _ => source(&assertion.span),
+            },
+            kind: BoxKind::Assertion,
+        },
+        Ast::ClassPerl(class) => {
+            let name = match class.kind {
+                ClassPerlKind::Digit => "digit",
+                ClassPerlKind::Space => "whitespace",
+                ClassPerlKind::Word => "word character",
+            };
+            Node::Box {
+                label: if class.negated {
+                    format!("not {name}")
+                } else {
+                    name.to_string()
+                },
+                kind: BoxKind::Class,
+            }
+        }
+        Ast::ClassUnicode(class) => Node::Box {
+            label: source(&class.span),
+            kind: BoxKind::Class,
+        },
+        Ast::ClassBracketed(class) => Node::Box {
+            label: source(&class.span),
+            kind: BoxKind::Class,
+        },
+        Ast::Repetition(rep) => repeat(node(&rep.ast, pattern), &rep.op, rep.greedy),
+        Ast::Group(group) => {
+            let body = node(&group.ast, pattern);
+            let label = match &group.kind {
+                GroupKind::CaptureIndex(index) => format!("group {index}"),
+                GroupKind::CaptureName { name, .. } => {
+                    format!("group {} <{}>", name.index, name.name)
+                }
+                GroupKind::NonCapturing(flags) if flags.items.is_empty() => return body,
+                GroupKind::NonCapturing(_) => {
+                    let span = &group.span;
+                    let inner = group.ast.span().start.offset;
+                    pattern[span.start.offset..inner].to_string()
+                }
+            };
+            Node::Group {
+                label,
+                body: Box::new(body),
+            }
+        }
+        Ast::Alternation(alt) => {
+            Node::Choice(alt.asts.iter().map(|ast| node(ast, pattern)).collect())
+        }
+        Ast::Concat(concat) => {
+            // Runs of plain literals read better as a single box.
+            let mut items: Vec<Node> = Vec::new();
+            let mut run = String::new();
+            for ast in &concat.asts {
+                match ast {
+                    Ast::Literal(lit) => run.push(lit.c),
+                    other => {
+                        if !run.is_empty() {
+                            items.push(literal_box(&std::mem::take(&mut run)));
+                        }
+                        items.push(node(other, pattern));
+                    }
+                }
+            }
+            if !run.is_empty() {
+                items.push(literal_box(&run));
+            }
+            Node::Sequence(items)
+        }
+    }
+}
+
+fn literal_box(text: &str) -> Node {
+    Node::Box {
+        label: quoted(text),
+        kind: BoxKind::Literal,
+    }
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.escape_debug())
+}
+
+fn repeat(body: Node, op: &RepetitionOp, greedy: bool) -> Node {
+    let (min, max) = match &op.kind {
+        RepetitionKind::ZeroOrOne => (0, Some(1)),
+        RepetitionKind::ZeroOrMore => (0, None),
+        RepetitionKind::OneOrMore => (1, None),
+        RepetitionKind::Range(RepetitionRange::Exactly(n)) => (*n, Some(*n)),
+        RepetitionKind::Range(RepetitionRange::AtLeast(n)) => (*n, None),
+        RepetitionKind::Range(RepetitionRange::Bounded(m, n)) => (*m, Some(*n)),
+    };
+    // `*`, `+` and `?` are fully described by the shape of the diagram.
+    let mut label = match (min, max) {
+        (0 | 1, None) | (0, Some(1)) => None,
+        (m, Some(n)) if m == n => Some(format!("{n} times")),
+        (m, None) => Some(format!("at least {m} times")),
+        (m, Some(n)) => Some(format!("{m} to {n} times")),
+    };
+    if !greedy {
+        label = Some(label.map_or("lazy".to_string(), |l| format!("{l}, lazy")));
+    }
+    Node::Repeat {
+        body: Box::new(body),
+        skip: min == 0,
+        back: max != Some(1) && max != Some(0),
+        label,
+    }
+}
+
+impl Node {
+    /// Computes the extent of this node and of every node below it.
+    fn lay_out(&self) -> Laid<'_> {
+        let children: Vec<Laid> = match self {
+            Node::Box { .. } => Vec::new(),
+            Node::Sequence(items) | Node::Choice(items) => {
+                items.iter().map(Node::lay_out).collect()
+            }
+            Node::Repeat { body, .. } | Node::Group { body, .. } => vec![body.lay_out()],
+        };
+        let sizes: Vec<Size> = children.iter().map(|child| child.size).collect();
+        let size = match self {
+            Node::Box { label, .. } => Size {
+                width: box_width(label),
+                up: BOX_HEIGHT / 2.0,
+                down: BOX_HEIGHT / 2.0,
+            },
+            Node::Sequence(_) => Size {
+                width: sizes.iter().map(|s| s.width).sum::<f32>()
+                    + GAP * sizes.len().saturating_sub(1) as f32,
+                up: sizes.iter().map(|s| s.up).fold(0.0, f32::max),
+                down: sizes.iter().map(|s| s.down).fold(0.0, f32::max),
+            },
+            Node::Choice(_) => {
+                let offsets = branch_offsets(&sizes);
+                Size {
+                    width: sizes.iter().map(|s| s.width).fold(0.0, f32::max) + 4.0 * CORNER,
+                    up: sizes.first().map_or(0.0, |s| s.up),
+                    down: offsets.last().copied().unwrap_or(0.0)
+                        + sizes.last().map_or(0.0, |s| s.down),
+                }
+            }
+            Node::Repeat {
+                skip, back, label, ..
+            } => {
+                let inner = &sizes[0];
+                let down = if *back {
+                    back_offset(inner)
+                } else {
+                    inner.down
+                };
+                Size {
+                    width: repeat_width(inner, label.as_deref()),
+                    up: if *skip { skip_offset(inner) } else { inner.up },
+                    down: down + label.as_ref().map_or(0.0, |_| CAPTION_HEIGHT),
+                }
+            }
+            Node::Group { label, .. } => {
+                let inner = &sizes[0];
+                Size {
+                    width: (inner.width + 2.0 * GROUP_PADDING).max(caption_width(label)),
+                    up: inner.up + GROUP_PADDING + CAPTION_HEIGHT,
+                    down: inner.down + GROUP_PADDING,
+                }
+            }
+        };
+        Laid {
+            node: self,
+            size,
+            children,
+        }
+    }
+}
+
+/// A node with its extent and those of its children, computed once per
+/// diagram rather than again for every shape drawn.
+struct Laid<'a> {
+    node: &'a Node,
+    size: Size,
+    children: Vec<Laid<'a>>,
+}
+
+impl Laid<'_> {
+    /// Emits the shapes of this node with its main line entering at `(x, y)`.
+    fn draw(&self, x: f32, y: f32, shapes: &mut Vec<Shape>) {
+        let size = &self.size;
+        match self.node {
+            Node::Box { label, kind } => {
+                shapes.push(Shape::Box {
+                    min: [x, y - BOX_HEIGHT / 2.0],
+                    max: [x + size.width, y + BOX_HEIGHT / 2.0],
+                    label: label.clone(),
+                    kind: *kind,
+                });
+            }
+            Node::Sequence(_) => {
+                let mut cursor = x;
+                for (i, item) in self.children.iter().enumerate() {
+                    if i > 0 {
+                        shapes.push(Shape::Path(vec![[cursor, y], [cursor + GAP, y]]));
+                        cursor += GAP;
+                    }
+                    item.draw(cursor, y, shapes);
+                    cursor += item.size.width;
+                }
+            }
+            Node::Choice(_) => {
+                let width = size.width;
+                let sizes: Vec<Size> = self.children.iter().map(|child| child.size).collect();
+                let offsets = branch_offsets(&sizes);
+                let (left, right) = (x + 2.0 * CORNER, x + width - 2.0 * CORNER);
+                for (branch, dy) in self.children.iter().zip(offsets) {
+                    let by = y + dy;
+                    let branch_width = branch.size.width;
+                    if dy == 0.0 {
+                        shapes.push(Shape::Path(vec![[x, y], [left, y]]));
+                        shapes.push(Shape::Path(vec![[left + branch_width, y], [x + width, y]]));
+                    } else {
+                        shapes.push(Shape::Path(vec![
+                            [x, y],
+                            [x + CORNER, y + CORNER],
+                            [x + CORNER, by - CORNER],
+                            [left, by],
+                        ]));
+                        shapes.push(Shape::Path(vec![
+                            [left + branch_width, by],
+                            [right, by],
+                            [right + CORNER, by - CORNER],
+                            [right + CORNER, y + CORNER],
+                            [x + width, y],
+                        ]));
+                    }
+                    branch.draw(left, by, shapes);
+                }
+            }
+            Node::Repeat {
+                skip, back, label, ..
+            } => {
+                let body = &self.children[0];
+                let inner = &body.size;
+                let width = size.width;
+                let (left, right) = (x + 2.0 * CORNER, x + width - 2.0 * CORNER);
+                shapes.push(Shape::Path(vec![[x, y], [left, y]]));
+                body.draw(left, y, shapes);
+                shapes.push(Shape::Path(vec![[left + inner.width, y], [x + width, y]]));
+                if *skip {
+                    let sy = y - skip_offset(inner);
+                    shapes.push(Shape::Path(vec![
+                        [x, y],
+                        [x + CORNER, y - CORNER],
+                        [x + CORNER, sy + CORNER],
+                        [left, sy],
+                        [right, sy],
+                        [right + CORNER, sy + CORNER],
+                        [right + CORNER, y - CORNER],
+                        [x + width, y],
+                    ]));
+                }
+                let mut bottom = y + inner.down;
+                if *back {
+                    bottom = y + back_offset(inner);
+                    shapes.push(Shape::Path(vec![
+                        [right, y],
+                        [right + CORNER, y + CORNER],
+                        [right + CORNER, bottom - CORNER],
+                        [right, bottom],
+                        [left, bottom],
+                        [x + CORNER, bottom - CORNER],
+                        [x + CORNER, y + CORNER],
+                        [left, y],
+                    ]));
+                }
+                if let Some(label) = label {
+                    shapes.push(Shape::Caption {
+                        center: [x + width / 2.0, bottom + CAPTION_HEIGHT / 2.0 + 2.0],
+                        text: label.clone(),
+                    });
+                }
+            }
+            Node::Group { label, .. } => {
+                let body = &self.children[0];
+                let inner = &body.size;
+                shapes.push(Shape::Frame {
+                    min: [x, y - size.up],
+                    max: [x + size.width, y + size.down],
+                    label: label.clone(),
+                });
+                let start = x + (size.width - inner.width) / 2.0;
+                shapes.push(Shape::Path(vec![[x, y], [start, y]]));
+                body.draw(start, y, shapes);
+                shapes.push(Shape::Path(vec![
+                    [start + inner.width, y],
+                    [x + size.width, y],
+                ]));
+            }
+        }
+    }
+}
+
+fn box_width(label: &str) -> f32 {
+    label.chars().count() as f32 * CHAR_WIDTH + 2.0 * BOX_PADDING
+}
+
+fn caption_width(text: &str) -> f32 {
+    text.chars().count() as f32 * CAPTION_CHAR_WIDTH + 2.0 * GROUP_PADDING
+}
+
+fn repeat_width(inner: &Size, label: Option<&str>) -> f32 {
+    inner.width.max(label.map_or(0.0, caption_width)) + 4.0 * CORNER
+}
+
+/// Distance from the main line up to the bypass of an optional node.
+fn skip_offset(inner: &Size) -> f32 {
+    (inner.up + VERTICAL_GAP).max(2.0 * CORNER)
+}
+
+/// Distance from the main line down to the return path of a loop.
+fn back_offset(inner: &Size) -> f32 {
+    (inner.down + VERTICAL_GAP).max(2.0 * CORNER)
+}
+
+/// Vertical offset of each branch's main line relative to the first one.
+fn branch_offsets(sizes: &[Size]) -> Vec<f32> {
+    let mut offsets = Vec::with_capacity(sizes.len());
+    let mut y = 0.0;
+    for (i, size) in sizes.iter().enumerate() {
+        if i > 0 {
+            y += (sizes[i - 1].down + VERTICAL_GAP + size.up).max(2.0 * CORNER);
+        }
+        offsets.push(y);
+    }
+    offsets
+}
+
+impl Diagram {
+    fn layout(root: &Node) -> Self {
+        let root = root.lay_out();
+        let size = root.size;
+        let y = MARGIN + size.up;
+        let (start, end) = (MARGIN + LEAD, MARGIN + LEAD + size.width);
+        let mut shapes = vec![
+            // Entry and exit markers: a single and a double bar.
+            Shape::Path(vec![[MARGIN, y - CORNER], [MARGIN, y + CORNER]]),
+            Shape::Path(vec![[MARGIN, y], [start, y]]),
+            Shape::Path(vec![[end, y], [end + LEAD, y]]),
+            Shape::Path(vec![
+                [end + LEAD - 4.0, y - CORNER],
+                [end + LEAD - 4.0, y + CORNER],
+            ]),
+            Shape::Path(vec![[end + LEAD, y - CORNER], [end + LEAD, y + CORNER]]),
+        ];
+        root.draw(start, y, &mut shapes);
+        Self {
+            width: end + LEAD + MARGIN,
+            height: y + size.down + MARGIN,
+            shapes,
+        }
+    }
+
+    /// Renders the diagram as a standalone SVG document.
+    pub fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        );
+        svg.push_str(
+            "<style>\
+             svg { background: #1e1e2e; }\
+             path, rect { fill: none; stroke: #cdd6f4; stroke-width: 1.5; }\
+             rect.literal { fill: #313244; stroke: #a6e3a1; }\
+             rect.class { fill: #313244; stroke: #89b4fa; }\
+             rect.assertion { fill: #313244; stroke: #f9e2af; }\
+             rect.frame { stroke: #7f849c; stroke-dasharray: 4 3; }\
+             text { fill: #cdd6f4; font-family: monospace; font-size: 13px; }\
+             text.caption { fill: #a6adc8; font-size: 11px; }\
+             </style>\n",
+        );
+        for shape in &self.shapes {
+            match shape {
+                Shape::Box {
+                    min,
+                    max,
+                    label,
+                    kind,
+                } => {
+                    let (class, radius) = match kind {
+                        BoxKind::Literal => ("literal", BOX_HEIGHT / 2.0),
+                        BoxKind::Class => ("class", 0.0),
+                        BoxKind::Assertion => ("assertion", 4.0),
+                    };
+                    let _ = writeln!(
+                        svg,
+                        r#"<rect class="{class}" x="{}" y="{}" width="{}" height="{}" rx="{radius}"/>"#,
+                        min[0],
+                        min[1],
+                        max[0] - min[0],
+                        max[1] - min[1]
+                    );
+                    let _ = writeln!(
+                        svg,
+                        r#"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                        (min[0] + max[0]) / 2.0,
+                        (min[1] + max[1]) / 2.0,
+                        escape_xml(label)
+                    );
+                }
+                Shape::Frame { min, max, label } => {
+                    let _ = writeln!(
+                        svg,
+                        r#"<rect class="frame" x="{}" y="{}" width="{}" height="{}" rx="4"/>"#,
+                        min[0],
+                        min[1],
+                        max[0] - min[0],
+                        max[1] - min[1]
+                    );
+                    let _ = writeln!(
+                        svg,
+                        r#"<text class="caption" x="{}" y="{}" dominant-baseline="central">{}</text>"#,
+                        min[0] + GROUP_PADDING,
+                        min[1] + CAPTION_HEIGHT / 2.0 + 2.0,
+                        escape_xml(label)
+                    );
+                }
+                Shape::Path(points) => {
+                    let mut d = String::new();
+                    for (i, [px, py]) in points.iter().enumerate() {
+                        let _ = write!(d, "{}{px} {py}", if i == 0 { "M" } else { " L" });
+                    }
+                    let _ = writeln!(svg, r#"<path d="{d}"/>"#);
+                }
+                Shape::Caption { center, text } => {
+                    let _ = writeln!(
+                        svg,
+                        r#"<text class="caption" x="{}" y="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                        center[0],
+                        center[1],
+                        escape_xml(text)
+                    );
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boxes(diagram: &Diagram) -> Vec<(&str, BoxKind)> {
+        diagram
+            .shapes
+            .iter()
+            .filter_map(|shape| match shape {
+                Shape::Box { label, kind, .. } => Some((label.as_str(), *kind)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merges_literal_runs_into_one_box() {
+        let diagram = railroad(r"IMG_\d+\.jpg").unwrap();
+        assert_eq!(
+            boxes(&diagram),
+            vec![
+                ("\"IMG_\"", BoxKind::Literal),
+                ("digit", BoxKind::Class),
+                ("\".jpg\"", BoxKind::Literal),
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_counted_repetitions_and_groups() {
+        let diagram = railroad(r"(?P<year>\d{4})-(a|b)??").unwrap();
+        let text: Vec<&str> = diagram
+            .shapes
+            .iter()
+            .filter_map(|shape| match shape {
+                Shape::Frame { label, .. } => Some(label.as_str()),
+                Shape::Caption { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, vec!["group 1 <year>", "4 times", "group 2", "lazy"]);
+    }
+
+    #[test]
+    fn alternatives_stack_below_the_main_line() {
+        let single = railroad("a").unwrap();
+        let choice = railroad("a|b|c").unwrap();
+        assert!(choice.height > single.height + 2.0 * BOX_HEIGHT);
+        assert!(choice.width > single.width);
+    }
+
+    #[test]
+    fn svg_export_escapes_labels() {
+        let svg = railroad(r"a<b&").unwrap().to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("&quot;a&lt;b&amp;&quot;"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn invalid_pattern_is_reported() {
+        let err = railroad("a(b").unwrap_err();
+        assert_eq!(err.kind(), "invalid_pattern");
+    }
+}
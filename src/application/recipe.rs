@@ -0,0 +1,246 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::ruleset::{FORMAT_VERSION, RuleSetFormat, parse_versioned};
+use super::template::Function;
+use super::{AppError, compile_rule};
+use crate::domain::{Rule, RuleMode};
+
+/// Recipes shipped with the app, as a TOML `[[recipes]]` array.
+const BUILT_IN: &str = include_str!("recipes.toml");
+
+/// Modification time the examples of a recipe are computed with:
+/// 2024-05-17 12:00:00 UTC.
+fn example_modified() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_715_947_200)
+}
+
+/// A path before and after the rules of a recipe ran.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Example {
+    pub before: String,
+    pub after: String,
+}
+
+/// A named set of rules with a description and examples, inserted into the
+/// rule table from the recipe browser.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Recipe {
+    pub version: u64,
+    pub name: String,
+    pub description: String,
+    pub examples: Vec<Example>,
+    pub rules: Vec<Rule>,
+}
+
+impl Default for Recipe {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            name: String::new(),
+            description: String::new(),
+            examples: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Library {
+    recipes: Vec<Recipe>,
+}
+
+impl Recipe {
+    /// The recipes shipped with the app.
+    pub fn built_in() -> Vec<Recipe> {
+        toml::from_str::<Library>(BUILT_IN)
+            .expect("built-in recipes are valid TOML")
+            .recipes
+    }
+
+    /// A recipe of `rules` whose examples are what the rules make of
+    /// `before`.
+    pub fn new(name: String, description: String, rules: Vec<Rule>, before: &[String]) -> Self {
+        let mut recipe = Self {
+            name,
+            description,
+            rules,
+            ..Self::default()
+        };
+        recipe.examples = before
+            .iter()
+            .map(|before| Example {
+                before: before.clone(),
+                after: recipe
+                    .apply(Path::new(before), Some(example_modified()))
+                    .to_string_lossy()
+                    .into_owned(),
+            })
+            .collect();
+        recipe
+    }
+
+    /// Where `path` ends up after every rename rule of the recipe ran in
+    /// order. Content and transcode rules, and rules that do not compile or
+    /// fail to match, leave it alone.
+    pub fn apply(&self, path: &Path, modified: Option<SystemTime>) -> PathBuf {
+        let mut path = path.to_path_buf();
+        for rule in self.rules.iter().filter(|r| r.mode == RuleMode::Rename) {
+            let Ok(compiled) = compile_rule(rule) else {
+                continue;
+            };
+            // Paths the rule does not match come back unchanged.
+            if let Ok(Some(destination)) = compiled.destination(&path, modified) {
+                path = destination;
+            }
+        }
+        path
+    }
+
+    /// Name of the file the recipe is saved to, e.g.
+    /// `strip-camera-prefixes.toml`.
+    pub fn file_name(&self) -> String {
+        let slug =
+            String::from_utf8(Function::Slug.apply(self.name.as_bytes())).expect("slugs are ASCII");
+        if slug.is_empty() {
+            "recipe.toml".to_string()
+        } else {
+            format!("{slug}.toml")
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let error = |message: String| AppError::RuleFile {
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        parse_versioned(&text, RuleSetFormat::of(path)).map_err(error)
+    }
+
+    /// Writes the recipe to `dir` as TOML, creating the directory, and
+    /// returns the path written. A recipe already saved under the same file
+    /// name, perhaps by a teammate, is never replaced.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, AppError> {
+        let path = dir.join(self.file_name());
+        let error = |message: String| AppError::RuleFile {
+            path: path.clone(),
+            message,
+        };
+        let text = toml::to_string_pretty(self).map_err(|e| error(e.to_string()))?;
+        std::fs::create_dir_all(dir).map_err(|e| error(e.to_string()))?;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => {
+                    error("a recipe with this name already exists; pick another name".to_string())
+                }
+                _ => error(e.to_string()),
+            })?;
+        file.write_all(text.as_bytes())
+            .map_err(|e| error(e.to_string()))?;
+        Ok(path)
+    }
+}
+
+/// Loads every `.toml` and `.json` recipe in `dir`, sorted by name, with
+/// the errors of the files that could not be read.
+pub fn load_recipes(dir: &Path) -> (Vec<Recipe>, Vec<AppError>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            let error = AppError::RuleFile {
+                path: dir.to_path_buf(),
+                message: e.to_string(),
+            };
+            return (Vec::new(), vec![error]);
+        }
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("toml") || extension.eq_ignore_ascii_case("json")
+            })
+        })
+        .collect();
+    paths.sort();
+    let (mut recipes, mut errors) = (Vec::new(), Vec::new());
+    for path in paths {
+        match Recipe::load(&path) {
+            Ok(recipe) => recipes.push(recipe),
+            Err(error) => errors.push(error),
+        }
+    }
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+    (recipes, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_examples_match_their_rules() {
+        let recipes = Recipe::built_in();
+        assert_eq!(recipes.len(), 5);
+        for recipe in &recipes {
+            assert!(!recipe.description.is_empty(), "{}", recipe.name);
+            assert!(!recipe.examples.is_empty(), "{}", recipe.name);
+            for rule in &recipe.rules {
+                assert!(compile_rule(rule).is_ok(), "{}: {}", recipe.name, rule.from);
+            }
+            for example in &recipe.examples {
+                let after = recipe.apply(Path::new(&example.before), Some(example_modified()));
+                assert_eq!(after, Path::new(&example.after), "{}", recipe.name);
+            }
+        }
+    }
+
+    #[test]
+    fn team_recipes_round_trip_through_a_directory() {
+        let dir = std::env::temp_dir().join(format!("recipes-{}", std::process::id()));
+        let rules = vec![Rule {
+            from: r"^(.*/)draft-([^/]+)$".into(),
+            to: "$1$2".into(),
+            ..Rule::default()
+        }];
+        let recipe = Recipe::new(
+            "Drop draft prefix!".into(),
+            "Publishes drafts.".into(),
+            rules,
+            &["./posts/draft-hello.md".into()],
+        );
+        assert_eq!(recipe.examples[0].after, "./posts/hello.md");
+
+        let path = recipe.save(&dir).unwrap();
+        assert_eq!(path, dir.join("drop-draft-prefix.toml"));
+        let mut teammate = recipe.clone();
+        teammate.description = "Someone else's.".into();
+        let error = teammate.save(&dir).unwrap_err();
+        assert!(error.to_string().contains("already exists"), "{error}");
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let (recipes, errors) = load_recipes(&dir);
+        assert_eq!(recipes, vec![recipe]);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].to_string().contains("broken.json"),
+            "{}",
+            errors[0]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (recipes, errors) = load_recipes(&dir);
+        assert!(recipes.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+}
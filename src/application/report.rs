@@ -0,0 +1,264 @@
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{SanitizeOptions, UndoJournal};
+
+/// Options controlling how [`super::Renamer::execute`] applies a batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecuteOptions {
+    /// Simulate the batch without touching the file system.
+    pub dry_run: bool,
+    /// Keep going after a failed move instead of aborting the batch.
+    pub continue_on_error: bool,
+    /// Portability and length checks applied to destinations.
+    pub sanitize: SanitizeOptions,
+    /// Remove the directories the batch emptied once every rule has run.
+    pub remove_empty_dirs: bool,
+}
+
+/// Coarse classification of a [`MoveOutcome`], used for counting and filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MoveStatus {
+    Succeeded,
+    Skipped,
+    Failed,
+}
+
+/// Why a move was not attempted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Dry-run mode was enabled.
+    DryRun,
+    /// The destination is identical to the source.
+    Unchanged,
+    /// An earlier move failed and `continue_on_error` was disabled.
+    Aborted,
+    /// A content rule selected a file that looks binary.
+    Binary,
+    /// A content rule could not decode the file as text.
+    Undecodable,
+    /// A transcode rule could not decode the name in the source encoding.
+    UndecodableName,
+    /// The target encoding of a transcode rule cannot represent the name.
+    Unmappable,
+    /// The destination breaks the portability profile and rewriting is off.
+    NotPortable,
+    /// The destination exceeds the length limits and cannot be truncated.
+    TooLong,
+    /// The source or destination resolves outside the root the batch is
+    /// confined to.
+    OutsideRoot,
+    /// The source or destination is a protected path.
+    Protected,
+    /// A content rule selected a symbolic link.
+    Symlink,
+}
+
+impl SkipReason {
+    pub fn description(self) -> &'static str {
+        match self {
+            SkipReason::DryRun => "dry run",
+            SkipReason::Unchanged => "destination unchanged",
+            SkipReason::Aborted => "aborted after earlier failure",
+            SkipReason::Binary => "binary file",
+            SkipReason::Undecodable => "not valid text in any detected encoding",
+            SkipReason::UndecodableName => "name is not valid in the source encoding",
+            SkipReason::Unmappable => "target encoding cannot represent the name",
+            SkipReason::NotPortable => "destination name is not portable",
+            SkipReason::TooLong => "destination is too long",
+            SkipReason::OutsideRoot => "path is outside the root",
+            SkipReason::Protected => "path is protected",
+            SkipReason::Symlink => "symbolic link",
+        }
+    }
+}
+
+/// What happened to a single matched entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveOutcome {
+    Succeeded,
+    Skipped(SkipReason),
+    Failed {
+        kind: io::ErrorKind,
+        message: String,
+    },
+}
+
+impl MoveOutcome {
+    pub fn failed(error: &io::Error) -> Self {
+        MoveOutcome::Failed {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn status(&self) -> MoveStatus {
+        match self {
+            MoveOutcome::Succeeded => MoveStatus::Succeeded,
+            MoveOutcome::Skipped(_) => MoveStatus::Skipped,
+            MoveOutcome::Failed { .. } => MoveStatus::Failed,
+        }
+    }
+}
+
+/// File operation a [`MoveRecord`] describes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// Rename `source` to `destination`.
+    Move,
+    /// Rewrite the contents of `source` (equal to `destination`) in place.
+    Rewrite { replacements: usize },
+    /// Restore the contents of `source` saved by an earlier rewrite.
+    Restore,
+    /// Remove `source`, a directory the batch emptied.
+    RemoveDir,
+    /// Recreate `source`, a directory removed by an earlier batch.
+    CreateDir,
+}
+
+#[derive(Clone, Debug)]
+pub struct MoveRecord {
+    pub operation: Operation,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub outcome: MoveOutcome,
+}
+
+/// Per-entry summary of an execution run.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionReport {
+    pub records: Vec<MoveRecord>,
+    /// `true` when the batch stopped early because of a failure.
+    pub aborted: bool,
+    /// Operations that actually changed the file system, for undo.
+    pub journal: UndoJournal,
+}
+
+impl ExecutionReport {
+    pub fn push(&mut self, source: PathBuf, destination: PathBuf, outcome: MoveOutcome) {
+        self.records.push(MoveRecord {
+            operation: Operation::Move,
+            source,
+            destination,
+            outcome,
+        });
+    }
+
+    pub fn push_rewrite(&mut self, path: PathBuf, replacements: usize, outcome: MoveOutcome) {
+        self.records.push(MoveRecord {
+            operation: Operation::Rewrite { replacements },
+            source: path.clone(),
+            destination: path,
+            outcome,
+        });
+    }
+
+    pub fn push_restore(&mut self, path: PathBuf, outcome: MoveOutcome) {
+        self.records.push(MoveRecord {
+            operation: Operation::Restore,
+            source: path.clone(),
+            destination: path,
+            outcome,
+        });
+    }
+
+    pub fn push_directory(&mut self, operation: Operation, path: PathBuf, outcome: MoveOutcome) {
+        self.records.push(MoveRecord {
+            operation,
+            source: path.clone(),
+            destination: path,
+            outcome,
+        });
+    }
+
+    pub fn count(&self, status: MoveStatus) -> usize {
+        self.records
+            .iter()
+            .filter(|r| r.outcome.status() == status)
+            .count()
+    }
+
+    /// Distinct error kinds among the failed records, in order of first occurrence.
+    pub fn failure_kinds(&self) -> Vec<io::ErrorKind> {
+        let mut kinds = Vec::new();
+        for record in &self.records {
+            if let MoveOutcome::Failed { kind, .. } = record.outcome {
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+            }
+        }
+        kinds
+    }
+
+    /// Number of emptied directories the batch removed.
+    pub fn removed_dirs(&self) -> usize {
+        self.records
+            .iter()
+            .filter(|r| r.operation == Operation::RemoveDir)
+            .filter(|r| r.outcome == MoveOutcome::Succeeded)
+            .count()
+    }
+
+    pub fn summary(&self) -> String {
+        let summary = format!(
+            "{} succeeded, {} skipped, {} failed",
+            self.count(MoveStatus::Succeeded),
+            self.count(MoveStatus::Skipped),
+            self.count(MoveStatus::Failed)
+        );
+        match self.removed_dirs() {
+            0 => summary,
+            1 => format!("{summary}; 1 empty directory removed"),
+            n => format!("{summary}; {n} empty directories removed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_records_by_status() {
+        let mut report = ExecutionReport::default();
+        report.push("a".into(), "b".into(), MoveOutcome::Succeeded);
+        report.push(
+            "c".into(),
+            "d".into(),
+            MoveOutcome::Skipped(SkipReason::DryRun),
+        );
+        report.push(
+            "e".into(),
+            "f".into(),
+            MoveOutcome::failed(&io::Error::from(io::ErrorKind::PermissionDenied)),
+        );
+        assert_eq!(report.count(MoveStatus::Succeeded), 1);
+        assert_eq!(report.count(MoveStatus::Skipped), 1);
+        assert_eq!(report.count(MoveStatus::Failed), 1);
+        assert_eq!(report.summary(), "1 succeeded, 1 skipped, 1 failed");
+    }
+
+    #[test]
+    fn failure_kinds_are_deduplicated() {
+        let mut report = ExecutionReport::default();
+        for kind in [
+            io::ErrorKind::PermissionDenied,
+            io::ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied,
+        ] {
+            report.push(
+                "a".into(),
+                "b".into(),
+                MoveOutcome::failed(&io::Error::from(kind)),
+            );
+        }
+        assert_eq!(
+            report.failure_kinds(),
+            vec![io::ErrorKind::PermissionDenied, io::ErrorKind::NotFound]
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{AppError, Confinement, ExecuteOptions};
+use crate::domain::Rule;
+
+/// Version written to new rule set files. Bump it when a field changes
+/// meaning or is renamed, and teach [`migrate`] to upgrade older files;
+/// added fields only need a default.
+pub const FORMAT_VERSION: u64 = 1;
+
+/// Syntax of a rule set file, picked by its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleSetFormat {
+    Toml,
+    Json,
+}
+
+impl RuleSetFormat {
+    /// `.json` files are JSON; everything else is TOML.
+    pub fn of(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => RuleSetFormat::Json,
+            _ => RuleSetFormat::Toml,
+        }
+    }
+}
+
+/// Rules together with the options they were run with, as saved to disk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    pub version: u64,
+    pub options: ExecuteOptions,
+    pub confinement: Confinement,
+    pub rules: Vec<Rule>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            options: ExecuteOptions::default(),
+            confinement: Confinement::default(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl RuleSet {
+    pub fn to_text(&self, format: RuleSetFormat) -> Result<String, String> {
+        match format {
+            RuleSetFormat::Toml => toml::to_string_pretty(self).map_err(|e| e.to_string()),
+            RuleSetFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Parses a rule set written by this or an earlier version.
+    pub fn from_text(text: &str, format: RuleSetFormat) -> Result<Self, String> {
+        parse_versioned(text, format)
+    }
+
+    /// The set as opened from a file that may come from someone else, with
+    /// the safety settings of `current` it would loosen kept: dry run and
+    /// confinement stay on, removing emptied directories stays off, the
+    /// stricter portability profile and length limits win, the root stays
+    /// where it is and every protected path stays protected.
+    pub fn within_safety_of(mut self, current: &RuleSet) -> Self {
+        self.options.dry_run |= current.options.dry_run;
+        self.options.remove_empty_dirs &= current.options.remove_empty_dirs;
+        let sanitize = &mut self.options.sanitize;
+        sanitize.profile = sanitize.profile.max(current.options.sanitize.profile);
+        let length = &current.options.sanitize.length;
+        sanitize.length.max_name = sanitize.length.max_name.min(length.max_name);
+        sanitize.length.max_path = sanitize.length.max_path.min(length.max_path);
+        if current.confinement.enabled {
+            self.confinement.enabled = true;
+            self.confinement.root = current.confinement.root.clone();
+        }
+        for path in &current.confinement.protected {
+            if !self.confinement.protected.contains(path) {
+                self.confinement.protected.push(path.clone());
+            }
+        }
+        self
+    }
+
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let error = |message: String| AppError::RuleFile {
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        Self::from_text(&text, RuleSetFormat::of(path)).map_err(error)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        let error = |message: String| AppError::RuleFile {
+            path: PathBuf::from(path),
+            message,
+        };
+        let text = self.to_text(RuleSetFormat::of(path)).map_err(error)?;
+        std::fs::write(path, text).map_err(|e| error(e.to_string()))
+    }
+}
+
+/// Parses a versioned file, such as a rule set or a recipe, written by this
+/// or an earlier version.
+pub(super) fn parse_versioned<T: DeserializeOwned>(
+    text: &str,
+    format: RuleSetFormat,
+) -> Result<T, String> {
+    let value = match format {
+        RuleSetFormat::Toml => toml::from_str(text).map_err(|e| e.to_string())?,
+        RuleSetFormat::Json => serde_json::from_str(text).map_err(|e| e.to_string())?,
+    };
+    serde_json::from_value(migrate(value)?).map_err(|e| e.to_string())
+}
+
+/// Upgrades a parsed file to [`FORMAT_VERSION`], one version at a time. A
+/// file without a version is read as the current one.
+fn migrate(mut value: Value) -> Result<Value, String> {
+    let Value::Object(fields) = &mut value else {
+        return Err("expected a table of settings and rules".to_string());
+    };
+    let version = match fields.get("version") {
+        None => FORMAT_VERSION,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("the version must be a whole number, not {version}"))?,
+    };
+    if version > FORMAT_VERSION {
+        return Err(format!(
+            "written by a newer version (format {version}, this build reads up to {FORMAT_VERSION})"
+        ));
+    }
+    // Version 1 is the first format, so there is nothing to upgrade yet.
+    fields.insert("version".to_string(), FORMAT_VERSION.into());
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::PortabilityProfile;
+    use crate::domain::{Engine, RuleMode};
+
+    fn rule_set() -> RuleSet {
+        let mut set = RuleSet {
+            rules: vec![
+                Rule {
+                    from: r"(\d+)\.JPG$".into(),
+                    to: "$1.jpg".into(),
+                    engine: Engine::Fancy,
+                    ..Rule::default()
+                },
+                Rule {
+                    from: "colour".into(),
+                    to: "color".into(),
+                    mode: RuleMode::Content,
+                    path_filter: r"\.md$".into(),
+                    ..Rule::default()
+                },
+            ],
+            ..RuleSet::default()
+        };
+        set.options.dry_run = true;
+        set.options.sanitize.profile = PortabilityProfile::StrictAscii;
+        set.confinement.protected.push("node_modules".into());
+        set
+    }
+
+    #[test]
+    fn round_trips_in_both_formats() {
+        let set = rule_set();
+        for format in [RuleSetFormat::Toml, RuleSetFormat::Json] {
+            let text = set.to_text(format).unwrap();
+            assert!(text.contains("strict-ascii"), "{text}");
+            assert_eq!(RuleSet::from_text(&text, format).unwrap(), set);
+        }
+        assert_eq!(
+            RuleSetFormat::of(Path::new("rules.JSON")),
+            RuleSetFormat::Json
+        );
+        assert_eq!(
+            RuleSetFormat::of(Path::new("rules.toml")),
+            RuleSetFormat::Toml
+        );
+    }
+
+    #[test]
+    fn opened_files_never_loosen_the_safety_settings() {
+        let current = rule_set();
+        let mut shared = RuleSet::default();
+        shared.options.dry_run = false;
+        shared.options.continue_on_error = true;
+        shared.options.remove_empty_dirs = true;
+        shared.options.sanitize.length.max_name = 1000;
+        shared.options.sanitize.length.max_path = 100;
+        shared.confinement = Confinement {
+            enabled: false,
+            root: PathBuf::from("/"),
+            protected: vec![PathBuf::from("dist")],
+        };
+
+        let opened = shared.clone().within_safety_of(&current);
+        assert!(opened.options.dry_run);
+        assert!(opened.options.continue_on_error);
+        assert!(!opened.options.remove_empty_dirs);
+        assert_eq!(
+            opened.options.sanitize.profile,
+            PortabilityProfile::StrictAscii
+        );
+        assert_eq!(opened.options.sanitize.length.max_name, 255);
+        assert_eq!(opened.options.sanitize.length.max_path, 100);
+        assert!(opened.confinement.enabled);
+        assert_eq!(opened.confinement.root, Path::new("."));
+        assert_eq!(
+            opened.confinement.protected,
+            ["dist", ".git", "node_modules"].map(PathBuf::from)
+        );
+
+        // A file may still tighten them.
+        let mut loose = rule_set();
+        loose.options.dry_run = false;
+        loose.confinement.enabled = false;
+        loose.options.remove_empty_dirs = true;
+        loose.options.sanitize.profile = PortabilityProfile::Off;
+        let opened = current.clone().within_safety_of(&loose);
+        assert!(opened.options.dry_run);
+        assert!(opened.confinement.enabled);
+        assert!(!opened.options.remove_empty_dirs);
+        assert_eq!(
+            opened.options.sanitize.profile,
+            PortabilityProfile::StrictAscii
+        );
+    }
+
+    #[test]
+    fn fills_in_missing_fields_and_rejects_newer_files() {
+        let set =
+            RuleSet::from_text("[[rules]]\nfrom = 'a'\nto = 'b'\n", RuleSetFormat::Toml).unwrap();
+        assert_eq!(set.version, FORMAT_VERSION);
+        assert_eq!(set.options, ExecuteOptions::default());
+        assert_eq!(set.confinement, Confinement::default());
+        assert_eq!(set.rules[0].to, "b");
+
+        let error =
+            RuleSet::from_text(r#"{"version": 2, "rules": []}"#, RuleSetFormat::Json).unwrap_err();
+        assert!(error.contains("newer version"), "{error}");
+        assert!(RuleSet::from_text("[]", RuleSetFormat::Json).is_err());
+    }
+
+    #[test]
+    fn rejects_versions_that_are_not_numbers() {
+        let error = RuleSet::from_text(r#"{"version": "1", "rules": []}"#, RuleSetFormat::Json)
+            .unwrap_err();
+        assert_eq!(error, r#"the version must be a whole number, not "1""#);
+        let error = RuleSet::from_text("version = 1.5\n", RuleSetFormat::Toml).unwrap_err();
+        assert_eq!(error, "the version must be a whole number, not 1.5");
+    }
+}
@@ -0,0 +1,215 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::confine::Guard;
+use super::normalize::normalize_bytes;
+use super::os_path::{display_bytes, path_bytes};
+use super::sanitize::{NameIssue, sanitize_destination};
+use super::{AppError, CompiledRule, SanitizeOptions, SkipReason, display_path};
+
+/// A matched path with the byte ranges its capture groups cover. Bytes that
+/// are not valid UTF-8 are shown escaped in both paths, and the path is
+/// shown in the normalization form the rule matched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchSample {
+    pub path: String,
+    /// One entry per group, as ranges of `path`; index 0 is the whole
+    /// match, `None` means the group did not participate.
+    pub groups: Vec<Option<Range<usize>>>,
+    pub destination: String,
+    /// Portability and length problems of the destination, rewritten in
+    /// `destination` unless `skip` is set.
+    pub issues: Vec<NameIssue>,
+    /// Why the entry would be left alone: its destination is not portable,
+    /// too long, outside the root or protected.
+    pub skip: Option<SkipReason>,
+}
+
+/// A bounded sample of the entries a rule matches.
+#[derive(Clone, Debug, Default)]
+pub struct RuleSamples {
+    /// Name of each group (index 0 is always unnamed).
+    pub group_names: Vec<Option<String>>,
+    pub samples: Vec<MatchSample>,
+    /// Number of entries the rule matched in total.
+    pub total: usize,
+}
+
+impl RuleSamples {
+    /// Builds samples for the first `limit` of `paths`.
+    pub fn collect(
+        compiled: &CompiledRule,
+        paths: &[PathBuf],
+        limit: usize,
+        sanitize: SanitizeOptions,
+        guard: &Guard,
+        modified: &dyn Fn(&Path) -> Option<SystemTime>,
+    ) -> Result<Self, AppError> {
+        let mut samples = Vec::new();
+        for source in paths {
+            if samples.len() == limit {
+                break;
+            }
+            let Some(bytes) = path_bytes(source) else {
+                continue;
+            };
+            let bytes = normalize_bytes(bytes, compiled.normalization);
+            let Some(groups) = compiled.regex.captures_bytes(&bytes)? else {
+                continue;
+            };
+            let (path, offsets) = display_bytes(&bytes);
+            let Some(destination) = compiled.destination(source, modified(source))? else {
+                continue;
+            };
+            let sanitized = sanitize_destination(source, &destination, sanitize);
+            let skip = sanitized
+                .skip
+                .or_else(|| guard.check(source, &sanitized.path));
+            samples.push(MatchSample {
+                path,
+                groups: groups
+                    .into_iter()
+                    .map(|group| group.map(|r| offsets[r.start]..offsets[r.end]))
+                    .collect(),
+                destination: display_path(&sanitized.path),
+                issues: sanitized.issues,
+                skip,
+            });
+        }
+        Ok(Self {
+            group_names: compiled.regex.capture_names(),
+            samples,
+            total: paths.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::sanitize::NameIssue;
+    use crate::application::{Confinement, PortabilityProfile, compile_rule};
+    use crate::domain::Rule;
+
+    fn guard() -> Guard {
+        Confinement::default().guard()
+    }
+
+    #[test]
+    fn collects_group_ranges_and_destinations() {
+        let compiled = compile_rule(&Rule {
+            from: r"(?P<stem>\w+)\.(txt)$".into(),
+            to: "${stem}.md".into(),
+            ..Rule::default()
+        })
+        .unwrap();
+        let paths = vec![PathBuf::from("./a/notes.txt"), PathBuf::from("./b/x.txt")];
+
+        let samples = RuleSamples::collect(
+            &compiled,
+            &paths,
+            1,
+            SanitizeOptions::default(),
+            &guard(),
+            &|_| None,
+        )
+        .unwrap();
+
+        assert_eq!(samples.total, 2);
+        assert_eq!(
+            samples.group_names,
+            vec![None, Some("stem".to_string()), None]
+        );
+        assert_eq!(
+            samples.samples,
+            vec![MatchSample {
+                path: "./a/notes.txt".into(),
+                groups: vec![Some(4..13), Some(4..9), Some(10..13)],
+                destination: "./a/notes.md".into(),
+                issues: vec![],
+                skip: None,
+            }]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escapes_invalid_utf8_and_shifts_group_ranges() {
+        let compiled = compile_rule(&Rule {
+            from: r"(\d+)\.txt$".into(),
+            to: "n$1.txt".into(),
+            ..Rule::default()
+        })
+        .unwrap();
+        let path = super::super::os_path::path_from_bytes(b"./\xFF/1.txt".to_vec()).unwrap();
+
+        let samples = RuleSamples::collect(
+            &compiled,
+            &[path],
+            1,
+            SanitizeOptions::default(),
+            &guard(),
+            &|_| None,
+        )
+        .unwrap();
+
+        let sample = &samples.samples[0];
+        assert_eq!(sample.path, r"./\xFF/1.txt");
+        assert_eq!(sample.groups, vec![Some(7..12), Some(7..8)]);
+        assert_eq!(sample.destination, r"./\xFF/n1.txt");
+    }
+
+    #[test]
+    fn reports_sanitized_and_skipped_destinations() {
+        let compiled = compile_rule(&Rule {
+            from: r"(\d+)\.txt$".into(),
+            to: "$1?.txt".into(),
+            ..Rule::default()
+        })
+        .unwrap();
+        let paths = [PathBuf::from("./1.txt")];
+        let mut sanitize = SanitizeOptions {
+            profile: PortabilityProfile::Portable,
+            rewrite: true,
+            ..SanitizeOptions::default()
+        };
+
+        let rewritten = &RuleSamples::collect(&compiled, &paths, 1, sanitize, &guard(), &|_| None)
+            .unwrap()
+            .samples[0];
+        assert_eq!(rewritten.destination, "./1_.txt");
+        assert_eq!(rewritten.issues, vec![NameIssue::Forbidden('?')]);
+        assert_eq!(rewritten.skip, None);
+
+        sanitize.rewrite = false;
+        let skipped = &RuleSamples::collect(&compiled, &paths, 1, sanitize, &guard(), &|_| None)
+            .unwrap()
+            .samples[0];
+        assert_eq!(skipped.destination, "./1?.txt");
+        assert_eq!(skipped.skip, Some(SkipReason::NotPortable));
+    }
+
+    #[test]
+    fn reports_destinations_outside_the_root() {
+        let compiled = compile_rule(&Rule {
+            from: r"^\./(\w+)\.txt$".into(),
+            to: "../$1.txt".into(),
+            ..Rule::default()
+        })
+        .unwrap();
+
+        let samples = RuleSamples::collect(
+            &compiled,
+            &[PathBuf::from("./a.txt")],
+            1,
+            SanitizeOptions::default(),
+            &guard(),
+            &|_| None,
+        )
+        .unwrap();
+
+        assert_eq!(samples.samples[0].destination, "../a.txt");
+        assert_eq!(samples.samples[0].skip, Some(SkipReason::OutsideRoot));
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::SkipReason;
+use super::length::{LengthLimits, limit_length};
+use super::os_path::{path_bytes, path_from_bytes};
+
+/// Rules destination names are checked against before anything is moved,
+/// ordered from the loosest to the strictest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PortabilityProfile {
+    /// Names are used as the template produces them.
+    #[default]
+    Off,
+    /// No control characters, which POSIX allows but most tools mangle.
+    Posix,
+    /// Names that also work on Windows, macOS and common sync services:
+    /// valid UTF-8, none of `<>:"\|?*`, no trailing dot or space and no
+    /// reserved device name such as `CON` or `NUL.txt`.
+    Portable,
+    /// Portable names made only of `A-Z a-z 0-9 . _ -`, not starting with
+    /// `-`: the POSIX portable filename character set.
+    StrictAscii,
+}
+
+/// Profiles in the order the options bar offers them.
+pub const PROFILES: [PortabilityProfile; 4] = [
+    PortabilityProfile::Off,
+    PortabilityProfile::Posix,
+    PortabilityProfile::Portable,
+    PortabilityProfile::StrictAscii,
+];
+
+impl PortabilityProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            PortabilityProfile::Off => "Off",
+            PortabilityProfile::Posix => "POSIX",
+            PortabilityProfile::Portable => "Portable",
+            PortabilityProfile::StrictAscii => "Strict ASCII",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            PortabilityProfile::Off => "Do not check destination names",
+            PortabilityProfile::Posix => "No control characters",
+            PortabilityProfile::Portable => {
+                "Safe on Windows and macOS: no <>:\"\\|?*, trailing dots or reserved names like CON"
+            }
+            PortabilityProfile::StrictAscii => "Only letters, digits, '.', '_' and '-'",
+        }
+    }
+}
+
+/// How the planner treats destinations that break the profile or the
+/// length limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SanitizeOptions {
+    pub profile: PortabilityProfile,
+    /// Rewrite names that break the profile; otherwise their entries are
+    /// skipped.
+    pub rewrite: bool,
+    pub length: LengthLimits,
+}
+
+/// A portability problem in a destination name, and how it is rewritten.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NameIssue {
+    /// A character the profile forbids; replaced with `_`.
+    Forbidden(char),
+    /// Bytes that are not valid UTF-8; each replaced with `_`.
+    InvalidUtf8,
+    /// Trailing dots or spaces, which Windows strips; removed.
+    TrailingDotOrSpace,
+    /// A reserved device name such as `CON`; suffixed with `_`.
+    Reserved(String),
+    /// A name starting with `-`, read as an option by most tools.
+    LeadingDash,
+    /// A path component longer than the file system allows; truncated.
+    NameTooLong { bytes: usize, limit: usize },
+    /// A whole path longer than the file system allows; its last component
+    /// is truncated.
+    PathTooLong { bytes: usize, limit: usize },
+}
+
+impl fmt::Display for NameIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameIssue::Forbidden(c) if c.is_control() => {
+                write!(f, "control character U+{:04X}", u32::from(*c))
+            }
+            NameIssue::Forbidden(c) => write!(f, "forbidden character '{c}'"),
+            NameIssue::InvalidUtf8 => write!(f, "bytes that are not valid UTF-8"),
+            NameIssue::TrailingDotOrSpace => write!(f, "trailing dot or space"),
+            NameIssue::Reserved(name) => write!(f, "reserved name '{name}'"),
+            NameIssue::LeadingDash => write!(f, "leading '-'"),
+            NameIssue::NameTooLong { bytes, limit } => {
+                write!(f, "name of {bytes} bytes exceeds {limit}")
+            }
+            NameIssue::PathTooLong { bytes, limit } => {
+                write!(f, "path of {bytes} bytes exceeds {limit}")
+            }
+        }
+    }
+}
+
+const WINDOWS_FORBIDDEN: &str = "<>:\"\\|?*";
+const RESERVED: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+fn forbidden(profile: PortabilityProfile, c: char) -> bool {
+    match profile {
+        PortabilityProfile::Off => false,
+        PortabilityProfile::Posix => c.is_control(),
+        PortabilityProfile::Portable => c.is_control() || WINDOWS_FORBIDDEN.contains(c),
+        PortabilityProfile::StrictAscii => !(c.is_ascii_alphanumeric() || "._-".contains(c)),
+    }
+}
+
+fn is_reserved(stem: &str) -> bool {
+    let upper = stem.to_ascii_uppercase();
+    RESERVED.contains(&upper.as_str())
+        || ["COM", "LPT"].iter().any(|prefix| {
+            upper
+                .strip_prefix(prefix)
+                .is_some_and(|n| n.len() == 1 && matches!(n.as_bytes()[0], b'1'..=b'9'))
+        })
+}
+
+/// Rewrites one path component to satisfy `profile`, returning the new
+/// name and every issue found (each kind once).
+pub fn sanitize_name(name: &[u8], profile: PortabilityProfile) -> (Vec<u8>, Vec<NameIssue>) {
+    let mut issues = Vec::new();
+    let mut note = |issue: NameIssue| {
+        if !issues.contains(&issue) {
+            issues.push(issue);
+        }
+    };
+    if profile == PortabilityProfile::Off {
+        return (name.to_vec(), issues);
+    }
+    let mut out = Vec::with_capacity(name.len());
+    for chunk in name.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if forbidden(profile, c) {
+                note(NameIssue::Forbidden(c));
+                out.push(b'_');
+            } else {
+                out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+        }
+        if !chunk.invalid().is_empty() {
+            if profile == PortabilityProfile::Posix {
+                out.extend_from_slice(chunk.invalid());
+            } else {
+                note(NameIssue::InvalidUtf8);
+                out.extend(chunk.invalid().iter().map(|_| b'_'));
+            }
+        }
+    }
+    if profile == PortabilityProfile::Posix {
+        return (out, issues);
+    }
+
+    // Everything left is valid UTF-8: invalid bytes became underscores.
+    let mut text = String::from_utf8(out).unwrap_or_default();
+    let trimmed = text.trim_end_matches(['.', ' ']).len();
+    if trimmed < text.len() {
+        note(NameIssue::TrailingDotOrSpace);
+        text.truncate(trimmed);
+        if text.is_empty() {
+            text.push('_');
+        }
+    }
+    let stem_len = text.find('.').unwrap_or(text.len());
+    if is_reserved(&text[..stem_len]) {
+        note(NameIssue::Reserved(text[..stem_len].to_string()));
+        text.insert(stem_len, '_');
+    }
+    if profile == PortabilityProfile::StrictAscii && text.starts_with('-') {
+        note(NameIssue::LeadingDash);
+        text.replace_range(..1, "_");
+    }
+    (text.into_bytes(), issues)
+}
+
+/// A destination checked against [`SanitizeOptions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sanitized {
+    /// The destination with every issue rewritten, or the original one when
+    /// the entry is skipped.
+    pub path: PathBuf,
+    pub issues: Vec<NameIssue>,
+    /// Why the entry is left alone, if it is.
+    pub skip: Option<SkipReason>,
+}
+
+/// Checks `destination` against the portability profile and then the
+/// length limits, rewriting it as far as `options` allow.
+pub fn sanitize_destination(
+    source: &Path,
+    destination: &Path,
+    options: SanitizeOptions,
+) -> Sanitized {
+    let (portable, mut issues) = sanitize_components(source, destination, options.profile);
+    if !issues.is_empty() && !options.rewrite {
+        return Sanitized {
+            path: destination.to_path_buf(),
+            issues,
+            skip: Some(SkipReason::NotPortable),
+        };
+    }
+    let (path, long, fixed) = limit_length(source, &portable, options.length);
+    issues.extend(long);
+    if !fixed {
+        return Sanitized {
+            path: destination.to_path_buf(),
+            issues,
+            skip: Some(SkipReason::TooLong),
+        };
+    }
+    Sanitized {
+        path,
+        issues,
+        skip: None,
+    }
+}
+
+/// Sanitizes the components of `destination` after the leading components
+/// it shares with `source`, so existing parent directories are left alone.
+fn sanitize_components(
+    source: &Path,
+    destination: &Path,
+    profile: PortabilityProfile,
+) -> (PathBuf, Vec<NameIssue>) {
+    let mut issues = Vec::new();
+    let mut path = PathBuf::new();
+    let mut shared = true;
+    let mut source_components = source.components();
+    for component in destination.components() {
+        shared = shared && source_components.next() == Some(component);
+        let Component::Normal(name) = component else {
+            path.push(component);
+            continue;
+        };
+        let sanitized = match path_bytes(Path::new(name)) {
+            Some(bytes) if !shared => {
+                let (new_name, found) = sanitize_name(bytes, profile);
+                for issue in found {
+                    if !issues.contains(&issue) {
+                        issues.push(issue);
+                    }
+                }
+                path_from_bytes(new_name)
+            }
+            _ => None,
+        };
+        match sanitized {
+            Some(new_name) => path.push(new_name),
+            None => path.push(name),
+        }
+    }
+    (path, issues)
+}
+
+/// Joins issues into one line for logs and previews.
+pub fn describe_issues(issues: &[NameIssue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::length::LengthStrategy;
+
+    fn sanitize(name: &str, profile: PortabilityProfile) -> (String, Vec<NameIssue>) {
+        let (name, issues) = sanitize_name(name.as_bytes(), profile);
+        (String::from_utf8(name).unwrap(), issues)
+    }
+
+    #[test]
+    fn posix_only_rejects_control_characters() {
+        assert_eq!(
+            sanitize("a:b\tc.", PortabilityProfile::Posix),
+            ("a:b_c.".into(), vec![NameIssue::Forbidden('\t')])
+        );
+        assert_eq!(
+            sanitize_name(b"caf\xE9", PortabilityProfile::Posix),
+            (b"caf\xE9".to_vec(), vec![])
+        );
+    }
+
+    #[test]
+    fn portable_rewrites_windows_hazards() {
+        assert_eq!(
+            sanitize("what? a:b.. ", PortabilityProfile::Portable),
+            (
+                "what_ a_b".into(),
+                vec![
+                    NameIssue::Forbidden('?'),
+                    NameIssue::Forbidden(':'),
+                    NameIssue::TrailingDotOrSpace
+                ]
+            )
+        );
+        assert_eq!(
+            sanitize("con.tar.gz", PortabilityProfile::Portable),
+            (
+                "con_.tar.gz".into(),
+                vec![NameIssue::Reserved("con".into())]
+            )
+        );
+        assert_eq!(sanitize("COM10", PortabilityProfile::Portable).1, vec![]);
+        assert_eq!(
+            sanitize_name(b"caf\xE9", PortabilityProfile::Portable),
+            (b"caf_".to_vec(), vec![NameIssue::InvalidUtf8])
+        );
+        assert_eq!(sanitize("...", PortabilityProfile::Portable).0, "_");
+    }
+
+    #[test]
+    fn strict_ascii_keeps_only_the_portable_character_set() {
+        assert_eq!(
+            sanitize("-Café 1.txt", PortabilityProfile::StrictAscii),
+            (
+                "_Caf__1.txt".into(),
+                vec![
+                    NameIssue::Forbidden('é'),
+                    NameIssue::Forbidden(' '),
+                    NameIssue::LeadingDash
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn leaves_shared_parent_directories_alone() {
+        let (path, issues) = sanitize_components(
+            Path::new("./in:box/a.txt"),
+            Path::new("./in:box/new:name/a?.txt"),
+            PortabilityProfile::Portable,
+        );
+        assert_eq!(path, PathBuf::from("./in:box/new_name/a_.txt"));
+        assert_eq!(
+            describe_issues(&issues),
+            "forbidden character ':', forbidden character '?'"
+        );
+    }
+
+    #[test]
+    fn truncates_after_rewriting_and_reports_what_cannot_be_fixed() {
+        let mut options = SanitizeOptions {
+            profile: PortabilityProfile::Portable,
+            rewrite: true,
+            length: LengthLimits {
+                max_name: 8,
+                ..LengthLimits::default()
+            },
+        };
+        options.length.strategy = LengthStrategy::Truncate;
+        let source = Path::new("./a.txt");
+        let destination = Path::new("./what?now.txt");
+
+        let sanitized = sanitize_destination(source, destination, options);
+        assert_eq!(sanitized.path, PathBuf::from("./what.txt"));
+        assert_eq!(
+            describe_issues(&sanitized.issues),
+            "forbidden character '?', name of 12 bytes exceeds 8"
+        );
+        assert_eq!(sanitized.skip, None);
+
+        options.length.strategy = LengthStrategy::Fail;
+        let sanitized = sanitize_destination(source, destination, options);
+        assert_eq!(sanitized.path, destination);
+        assert_eq!(sanitized.skip, Some(SkipReason::TooLong));
+
+        options.rewrite = false;
+        let sanitized = sanitize_destination(source, destination, options);
+        assert_eq!(sanitized.skip, Some(SkipReason::NotPortable));
+    }
+}
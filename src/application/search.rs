@@ -0,0 +1,187 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use regex::Regex;
+
+use super::SkipReason;
+
+/// Maximum number of matching lines collected by one search.
+pub const HIT_LIMIT: usize = 100_000;
+
+/// Lines are cut to this many bytes for display; matches past the cut are
+/// still counted.
+const MAX_LINE_LEN: usize = 500;
+
+/// One line of a file containing at least one match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineHit {
+    /// 1-based line number.
+    pub line_number: usize,
+    /// The line without its terminator, cut to [`MAX_LINE_LEN`] bytes.
+    pub text: String,
+    /// Byte ranges of the matches within `text`.
+    pub matches: Vec<Range<usize>>,
+}
+
+/// The matching lines of one file.
+#[derive(Clone, Debug)]
+pub struct FileHits {
+    pub path: PathBuf,
+    pub lines: Vec<LineHit>,
+    /// Total number of matches, including those past a cut line.
+    pub match_count: usize,
+}
+
+/// Flattened view of [`SearchResults`], one entry per displayed row, so that
+/// the results can be scrolled without laying out every hit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchRow {
+    /// Header of `files[i]`.
+    File(usize),
+    /// `files[i].lines[j]`.
+    Line(usize, usize),
+}
+
+/// Matches of a content search, grouped by file in walk order.
+#[derive(Clone, Debug, Default)]
+pub struct SearchResults {
+    pub files: Vec<FileHits>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+    /// Files that could not be read, with the error message.
+    pub unreadable: Vec<(PathBuf, String)>,
+    /// Whether the search stopped after [`HIT_LIMIT`] lines.
+    pub truncated: bool,
+}
+
+impl SearchResults {
+    pub fn match_count(&self) -> usize {
+        self.files.iter().map(|f| f.match_count).sum()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.files.iter().map(|f| f.lines.len()).sum()
+    }
+
+    pub fn rows(&self) -> Vec<SearchRow> {
+        let mut rows = Vec::with_capacity(self.files.len() + self.line_count());
+        for (i, file) in self.files.iter().enumerate() {
+            rows.push(SearchRow::File(i));
+            rows.extend((0..file.lines.len()).map(|j| SearchRow::Line(i, j)));
+        }
+        rows
+    }
+
+    /// Paths of the files with hits, for a rule limited to them.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|f| f.path.clone()).collect()
+    }
+}
+
+/// Finds the matches of `regex` in `text` line by line, as `grep` does, and
+/// returns the matching lines (at most `limit`) with the total match count.
+pub fn search_lines(regex: &Regex, text: &str, limit: usize) -> (Vec<LineHit>, usize) {
+    let mut lines = Vec::new();
+    let mut match_count = 0;
+    for (index, line) in text.lines().enumerate() {
+        if lines.len() == limit {
+            break;
+        }
+        let matches: Vec<Range<usize>> = regex.find_iter(line).map(|m| m.range()).collect();
+        if matches.is_empty() {
+            continue;
+        }
+        match_count += matches.len();
+        let mut cut = line.len().min(MAX_LINE_LEN);
+        while !line.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        lines.push(LineHit {
+            line_number: index + 1,
+            text: line[..cut].to_string(),
+            matches: matches
+                .into_iter()
+                .filter(|m| m.start < cut || m.start == line.len())
+                .map(|m| m.start.min(cut)..m.end.min(cut))
+                .collect(),
+        });
+    }
+    (lines, match_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_matches_per_line() {
+        let regex = Regex::new(r"\bfoo\w*").unwrap();
+        let (lines, count) = search_lines(&regex, "foo bar foo\r\nbaz\nfoobar foo\n", 10);
+        assert_eq!(count, 4);
+        assert_eq!(
+            lines,
+            vec![
+                LineHit {
+                    line_number: 1,
+                    text: "foo bar foo".into(),
+                    matches: vec![0..3, 8..11],
+                },
+                LineHit {
+                    line_number: 3,
+                    text: "foobar foo".into(),
+                    matches: vec![0..6, 7..10],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cuts_long_lines_and_stops_at_limit() {
+        let regex = Regex::new("x").unwrap();
+        let long = format!("{}é x", "a".repeat(MAX_LINE_LEN - 1));
+        let text = format!("x\n{long}\nx\n");
+        let (lines, count) = search_lines(&regex, &text, 2);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(count, 2);
+        assert_eq!(lines[1].text.len(), MAX_LINE_LEN - 1);
+        assert!(lines[1].matches.is_empty());
+    }
+
+    #[test]
+    fn rows_and_paths_cover_every_file() {
+        let hit = |n| LineHit {
+            line_number: n,
+            text: String::new(),
+            matches: vec![],
+        };
+        let results = SearchResults {
+            files: vec![
+                FileHits {
+                    path: "./a.txt".into(),
+                    lines: vec![hit(1), hit(4)],
+                    match_count: 2,
+                },
+                FileHits {
+                    path: "./b (1).txt".into(),
+                    lines: vec![hit(2)],
+                    match_count: 3,
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(results.match_count(), 5);
+        assert_eq!(
+            results.rows(),
+            vec![
+                SearchRow::File(0),
+                SearchRow::Line(0, 0),
+                SearchRow::Line(0, 1),
+                SearchRow::File(1),
+                SearchRow::Line(1, 0),
+            ]
+        );
+        assert_eq!(
+            results.paths(),
+            vec![PathBuf::from("./a.txt"), PathBuf::from("./b (1).txt")]
+        );
+    }
+}
@@ -0,0 +1,618 @@
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use any_ascii::any_ascii;
+
+use super::{AppError, PatternEngine};
+use crate::domain::RuleMode;
+
+/// Reference to a capture group inside a template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupRef {
+    Index(usize),
+    Name(String),
+}
+
+/// Transform applied to a group through `${function(group)}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    /// Transliterates to ASCII: `Déjà vu` becomes `Deja vu`.
+    Ascii,
+    /// Transliterates, lowercases and joins the words with `-`:
+    /// `Café Déjà vu` becomes `cafe-deja-vu`.
+    Slug,
+    /// Lowercases letters, keeping everything else: `JPG` becomes `jpg`.
+    Lower,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ascii" => Some(Function::Ascii),
+            "slug" => Some(Function::Slug),
+            "lower" => Some(Function::Lower),
+            _ => None,
+        }
+    }
+
+    /// Applies the function to the valid UTF-8 in `bytes`. Invalid bytes
+    /// are kept, like everywhere else in a destination.
+    pub(super) fn apply(self, bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut separate = false;
+        for chunk in bytes.utf8_chunks() {
+            match self {
+                Function::Lower => {
+                    out.extend_from_slice(chunk.valid().to_lowercase().as_bytes());
+                    out.extend_from_slice(chunk.invalid());
+                }
+                Function::Ascii => {
+                    out.extend_from_slice(any_ascii(chunk.valid()).as_bytes());
+                    out.extend_from_slice(chunk.invalid());
+                }
+                Function::Slug => {
+                    let ascii = any_ascii(chunk.valid());
+                    let word_bytes = ascii
+                        .bytes()
+                        .map(|b| b.is_ascii_alphanumeric().then(|| b.to_ascii_lowercase()))
+                        .chain(chunk.invalid().iter().map(|b| Some(*b)));
+                    for byte in word_bytes {
+                        match byte {
+                            Some(byte) => {
+                                if separate && !out.is_empty() {
+                                    out.push(b'-');
+                                }
+                                separate = false;
+                                out.push(byte);
+                            }
+                            None => separate = true,
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Group {
+        group: GroupRef,
+        /// Byte range of the whole reference (`$1`, `${name}`) in the source.
+        span: Range<usize>,
+        braced: bool,
+        function: Option<Function>,
+    },
+    /// `${mtime:FORMAT}`: the modification time of the matched path, in UTC.
+    Modified {
+        format: String,
+        span: Range<usize>,
+    },
+}
+
+/// A parsed replacement template using the `regex` crate's syntax: `$1`,
+/// `$name`, `${1}`, `${name}` and `$$` for a literal dollar sign. Braced
+/// references may also apply a [`Function`] to the group, as in
+/// `${slug(1)}` or `${ascii(name)}`, and `${mtime:%Y-%m-%d}` inserts the
+/// modification time of the renamed path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses `template`, rejecting unclosed or empty `${}` references and
+    /// unknown functions.
+    pub fn parse(template: &str) -> Result<Self, AppError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(pos) = rest.find('$') {
+            literal.push_str(&rest[..pos]);
+            let start = template.len() - rest.len() + pos;
+            let after = &rest[pos + 1..];
+
+            let (group, braced, len, function) = if let Some(escaped) = after.strip_prefix('$') {
+                literal.push('$');
+                rest = escaped;
+                continue;
+            } else if let Some(inner) = after.strip_prefix('{') {
+                let Some(close) = inner.find('}') else {
+                    return Err(invalid(template, "unclosed '${'", start..template.len()));
+                };
+                if close == 0 {
+                    return Err(invalid(template, "empty group reference", start..start + 3));
+                }
+                let span = start..start + close + 3;
+                if let Some(format) = inner[..close].strip_prefix("mtime:") {
+                    if let Some(message) = check_time_format(format) {
+                        return Err(invalid(template, &message, span));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Modified {
+                        format: format.to_string(),
+                        span,
+                    });
+                    rest = &inner[close + 1..];
+                    continue;
+                }
+                let (group, function) = match inner[..close]
+                    .strip_suffix(')')
+                    .and_then(|call| call.split_once('('))
+                {
+                    Some((name, group)) => {
+                        let Some(function) = Function::parse(name) else {
+                            let message = format!(
+                                "unknown function '{name}'; use 'slug', 'ascii' or 'lower'"
+                            );
+                            return Err(invalid(template, &message, span));
+                        };
+                        if group.is_empty() {
+                            return Err(invalid(template, "empty group reference", span));
+                        }
+                        (group, Some(function))
+                    }
+                    None => (&inner[..close], None),
+                };
+                (group, true, close + 3, function)
+            } else {
+                let name_len = after
+                    .bytes()
+                    .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_')
+                    .count();
+                if name_len == 0 {
+                    // A lone `$` is kept literally, as the `regex` crate does.
+                    literal.push('$');
+                    rest = after;
+                    continue;
+                }
+                (&after[..name_len], false, name_len + 1, None)
+            };
+
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Group {
+                group: match group.parse::<usize>() {
+                    Ok(index) => GroupRef::Index(index),
+                    Err(_) => GroupRef::Name(group.to_string()),
+                },
+                span: start..start + len,
+                braced,
+                function,
+            });
+            rest = &rest[pos + len..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self {
+            source: template.to_string(),
+            parts,
+        })
+    }
+
+    /// Checks every group reference against the groups `re` defines.
+    pub fn check_groups(&self, re: &dyn PatternEngine) -> Result<(), AppError> {
+        let group_count = re.captures_len();
+        let names = re.capture_names();
+        for part in &self.parts {
+            let Part::Group {
+                group,
+                span,
+                braced,
+                ..
+            } = part
+            else {
+                continue;
+            };
+            let message = match group {
+                GroupRef::Index(index) if *index >= group_count => format!(
+                    "group {index} does not exist; the pattern has {} group(s)",
+                    group_count - 1
+                ),
+                GroupRef::Name(name) if !*braced && ambiguous_split(name).is_some() => {
+                    let (digits, suffix) = ambiguous_split(name).unwrap_or_default();
+                    format!(
+                        "'${name}' refers to a group named '{name}'; write '${{{digits}}}{suffix}' to use group {digits}"
+                    )
+                }
+                GroupRef::Name(name) if !names.iter().any(|n| n.as_ref() == Some(name)) => {
+                    format!("the pattern has no group named '{name}'")
+                }
+                _ => continue,
+            };
+            return Err(invalid(&self.source, &message, span.clone()));
+        }
+        Ok(())
+    }
+
+    /// Whether the template refers to the modification time of the path.
+    pub fn uses_mtime(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Modified { .. }))
+    }
+
+    /// Expands the template for one match of a pattern whose groups are
+    /// named `names`. Unmatched groups, and `${mtime:…}` as text has no
+    /// modification time, expand to "".
+    pub fn expand(
+        &self,
+        haystack: &str,
+        groups: &[Option<Range<usize>>],
+        names: &[Option<String>],
+    ) -> String {
+        String::from_utf8(self.expand_bytes(haystack.as_bytes(), groups, names, None))
+            .expect("groups of a str match lie on character boundaries and functions emit UTF-8")
+    }
+
+    /// Expands the template for one match in raw bytes, copying the bytes of
+    /// every group verbatim.
+    fn expand_bytes(
+        &self,
+        haystack: &[u8],
+        groups: &[Option<Range<usize>>],
+        names: &[Option<String>],
+        modified: Option<SystemTime>,
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.extend_from_slice(text.as_bytes()),
+                Part::Modified { format, .. } => {
+                    if let Some(time) = modified {
+                        out.extend_from_slice(format_time(format, time).as_bytes());
+                    }
+                }
+                Part::Group {
+                    group, function, ..
+                } => {
+                    let index = match group {
+                        GroupRef::Index(index) => Some(*index),
+                        GroupRef::Name(name) => {
+                            names.iter().position(|n| n.as_deref() == Some(name))
+                        }
+                    };
+                    let range = index.and_then(|i| groups.get(i).cloned().flatten());
+                    let bytes = range.map_or(&[][..], |r| &haystack[r]);
+                    match function {
+                        Some(function) => out.extend(function.apply(bytes)),
+                        None => out.extend_from_slice(bytes),
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Replaces the first match of `re` in the raw bytes `haystack`, like
+    /// `regex::bytes::Regex::replace`. Bytes outside the match are kept as is.
+    /// `modified` is the modification time `${mtime:…}` expands to.
+    pub fn replace_bytes(
+        &self,
+        re: &dyn PatternEngine,
+        haystack: &[u8],
+        modified: Option<SystemTime>,
+    ) -> Result<Vec<u8>, AppError> {
+        Ok(match re.captures_bytes(haystack)? {
+            Some(groups) => {
+                let whole = groups[0].clone().expect("group 0 always matches");
+                let mut out = haystack[..whole.start].to_vec();
+                out.extend(self.expand_bytes(haystack, &groups, &re.capture_names(), modified));
+                out.extend_from_slice(&haystack[whole.end..]);
+                out
+            }
+            None => haystack.to_vec(),
+        })
+    }
+
+    /// Replaces every non-overlapping match of `re` in the raw bytes
+    /// `haystack`, like `regex::bytes::Regex::replace_all`.
+    pub fn replace_all_bytes(
+        &self,
+        re: &dyn PatternEngine,
+        haystack: &[u8],
+        modified: Option<SystemTime>,
+    ) -> Result<Vec<u8>, AppError> {
+        let names = re.capture_names();
+        let mut out = Vec::with_capacity(haystack.len());
+        let mut last = 0;
+        for groups in re.captures_all_bytes(haystack)? {
+            let whole = groups[0].clone().expect("group 0 always matches");
+            out.extend_from_slice(&haystack[last..whole.start]);
+            out.extend(self.expand_bytes(haystack, &groups, &names, modified));
+            last = whole.end;
+        }
+        out.extend_from_slice(&haystack[last..]);
+        Ok(out)
+    }
+
+    /// Replaces every non-overlapping match of `re` in `haystack`, like
+    /// `Regex::replace_all`.
+    pub fn replace_all(&self, re: &dyn PatternEngine, haystack: &str) -> Result<String, AppError> {
+        let names = re.capture_names();
+        let mut out = String::with_capacity(haystack.len());
+        let mut last = 0;
+        for groups in re.captures_all(haystack)? {
+            let whole = groups[0].clone().expect("group 0 always matches");
+            out.push_str(&haystack[last..whole.start]);
+            out.push_str(&self.expand(haystack, &groups, &names));
+            last = whole.end;
+        }
+        out.push_str(&haystack[last..]);
+        Ok(out)
+    }
+}
+
+/// Parses `template` and checks its group references against `re`.
+/// `${mtime:…}` is only accepted in rename rules, as text has no
+/// modification time.
+pub fn validate_template(
+    template: &str,
+    re: &dyn PatternEngine,
+    mode: RuleMode,
+) -> Result<Template, AppError> {
+    let parsed = Template::parse(template)?;
+    parsed.check_groups(re)?;
+    if mode != RuleMode::Rename {
+        let modified = parsed.parts.iter().find_map(|part| match part {
+            Part::Modified { span, .. } => Some(span.clone()),
+            _ => None,
+        });
+        if let Some(span) = modified {
+            let message = "'${mtime:…}' is only available in rename rules";
+            return Err(invalid(template, message, span));
+        }
+    }
+    Ok(parsed)
+}
+
+/// Why `format` is not a valid `${mtime:…}` format, if it is not.
+fn check_time_format(format: &str) -> Option<String> {
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        match chars.next() {
+            Some('Y' | 'm' | 'd' | 'H' | 'M' | 'S' | '%') => {}
+            Some(field) => {
+                return Some(format!(
+                    "unknown date field '%{field}'; use %Y, %m, %d, %H, %M, %S or %%"
+                ));
+            }
+            None => return Some("date format ends with a lone '%'".to_string()),
+        }
+    }
+    None
+}
+
+/// Formats `time` in UTC with the `strftime` fields [`check_time_format`]
+/// accepts.
+fn format_time(format: &str, time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let of_day = seconds.rem_euclid(86_400);
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{year:04}")),
+            Some('m') => out.push_str(&format!("{month:02}")),
+            Some('d') => out.push_str(&format!("{day:02}")),
+            Some('H') => out.push_str(&format!("{:02}", of_day / 3600)),
+            Some('M') => out.push_str(&format!("{:02}", of_day / 60 % 60)),
+            Some('S') => out.push_str(&format!("{:02}", of_day % 60)),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Year, month and day of the proleptic Gregorian calendar `days` after
+/// 1970-01-01, after Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Splits names such as `1a` into (`1`, `a`): an index immediately followed by
+/// text, which the `regex` crate reads as a single (missing) group name.
+fn ambiguous_split(name: &str) -> Option<(&str, &str)> {
+    let digits = name.bytes().take_while(u8::is_ascii_digit).count();
+    (digits > 0 && digits < name.len()).then(|| name.split_at(digits))
+}
+
+fn invalid(template: &str, message: &str, span: Range<usize>) -> AppError {
+    AppError::InvalidTemplate {
+        template: template.to_string(),
+        message: message.to_string(),
+        span: Some(span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::compile_engine;
+    use crate::domain::{Engine, RegexLimits};
+    use regex::Regex;
+
+    fn check(template: &str, pattern: &str) -> Result<Template, AppError> {
+        validate_template(template, &Regex::new(pattern).unwrap(), RuleMode::Rename)
+    }
+
+    #[test]
+    fn accepts_plain_and_braced_references() {
+        assert!(check("bar/$1-${name}.md", r"(?P<name>\w+)(\d)").is_ok());
+        assert!(check("cost: $$5", "x").is_ok());
+    }
+
+    #[test]
+    fn rejects_unclosed_brace() {
+        let err = Template::parse("a/${1.txt").unwrap_err();
+        assert_eq!(err.kind(), "invalid_template");
+        assert_eq!(err.span(), Some(2..9));
+    }
+
+    #[test]
+    fn rejects_empty_reference() {
+        let err = Template::parse("x${}y").unwrap_err();
+        assert_eq!(err.span(), Some(1..4));
+    }
+
+    #[test]
+    fn flags_unknown_group_index() {
+        let err = check("out/$2", r"(\d+)").unwrap_err();
+        assert_eq!(err.span(), Some(4..6));
+        assert_eq!(
+            err.to_string(),
+            "invalid template: group 2 does not exist; the pattern has 1 group(s)"
+        );
+    }
+
+    #[test]
+    fn flags_unknown_group_name() {
+        let err = check("${year}-x", r"(\d+)").unwrap_err();
+        assert_eq!(err.span(), Some(0..7));
+    }
+
+    #[test]
+    fn flags_ambiguous_reference_and_suggests_braces() {
+        let err = check("$1a.txt", r"(\d+)").unwrap_err();
+        assert_eq!(err.span(), Some(0..3));
+        assert!(err.to_string().contains("write '${1}a'"));
+    }
+
+    #[test]
+    fn functions_transliterate_groups() {
+        let re = Regex::new(r"(?P<stem>[^/]+)\.mp3$").unwrap();
+        let haystack = "./Café Déjà vu.mp3";
+        let expand = |template: &str| {
+            let parsed = validate_template(template, &re, RuleMode::Rename).unwrap();
+            parsed.replace_all(&re, haystack).unwrap()
+        };
+        assert_eq!(expand("${slug(1)}.mp3"), "./cafe-deja-vu.mp3");
+        assert_eq!(expand("${ascii(stem)}.mp3"), "./Cafe Deja vu.mp3");
+        assert_eq!(Function::Slug.apply("--Ærø 2!".as_bytes()), b"aero-2");
+        assert_eq!(Function::Slug.apply(b"a \xFF b"), b"a-\xFF-b");
+    }
+
+    #[test]
+    fn rejects_unknown_functions_and_checks_their_groups() {
+        let err = Template::parse("x${upper(1)}").unwrap_err();
+        assert_eq!(err.span(), Some(1..12));
+        assert!(err.to_string().contains("unknown function 'upper'"));
+        assert_eq!(Template::parse("${slug()}").unwrap_err().span(), Some(0..9));
+        let err = check("${slug(2)}", r"(\d+)").unwrap_err();
+        assert_eq!(err.span(), Some(0..10));
+    }
+
+    #[test]
+    fn replace_bytes_matches_regex_crate_semantics() {
+        let pattern = r"foo/(?P<stem>.*)\.txt";
+        let engine = compile_engine(pattern, Engine::Regex, RegexLimits::default()).unwrap();
+        let re = regex::bytes::Regex::new(pattern).unwrap();
+        for template in ["bar/$1.md", "${stem}_x", "$$1 $stem", "a$", "$ b"] {
+            let parsed = Template::parse(template).unwrap();
+            for haystack in [&b"./foo/a.txt"[..], b"./\xFF/foo/a.txt", b"nothing"] {
+                assert_eq!(
+                    parsed
+                        .replace_bytes(engine.as_ref(), haystack, None)
+                        .unwrap(),
+                    re.replace(haystack, template.as_bytes()).into_owned(),
+                    "template {template:?} on {haystack:?}"
+                );
+            }
+        }
+        let all = compile_engine("[_ ]", Engine::Regex, RegexLimits::default()).unwrap();
+        assert_eq!(
+            Template::parse("-")
+                .unwrap()
+                .replace_all_bytes(all.as_ref(), b"./a_b \xFF_c", None)
+                .unwrap(),
+            b"./a-b-\xFF-c"
+        );
+    }
+
+    #[test]
+    fn lower_keeps_invalid_bytes() {
+        assert_eq!(
+            Function::Lower.apply("JPG Été".as_bytes()),
+            "jpg été".as_bytes()
+        );
+        assert_eq!(Function::Lower.apply(b"A\xFFB"), b"a\xFFb");
+    }
+
+    #[test]
+    fn mtime_expands_to_the_formatted_modification_time() {
+        let engine = compile_engine(r"([^/]+)$", Engine::Regex, RegexLimits::default()).unwrap();
+        let parsed = Template::parse("${mtime:%Y-%m-%d}_$1").unwrap();
+        assert!(parsed.uses_mtime());
+        // 2024-02-29 13:05:09 UTC.
+        let modified = UNIX_EPOCH + std::time::Duration::from_secs(1_709_211_909);
+        assert_eq!(
+            parsed
+                .replace_bytes(engine.as_ref(), b"./a.txt", Some(modified))
+                .unwrap(),
+            b"./2024-02-29_a.txt"
+        );
+        assert_eq!(
+            parsed
+                .replace_bytes(engine.as_ref(), b"./a.txt", None)
+                .unwrap(),
+            b"./_a.txt"
+        );
+        assert_eq!(format_time("%H:%M:%S %%", modified), "13:05:09 %");
+        assert_eq!(format_time("%Y-%m-%d", UNIX_EPOCH), "1970-01-01");
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+
+        let re = Regex::new("a").unwrap();
+        let err = validate_template("b ${mtime:%Y}", &re, RuleMode::Content).unwrap_err();
+        assert_eq!(err.span(), Some(2..13));
+        assert!(err.to_string().contains("only available in rename rules"));
+
+        let err = Template::parse("x${mtime:%Q}").unwrap_err();
+        assert_eq!(err.span(), Some(1..12));
+        assert!(err.to_string().contains("unknown date field '%Q'"));
+        assert!(Template::parse("${mtime:%}").is_err());
+    }
+
+    #[test]
+    fn replace_all_matches_regex_crate_semantics() {
+        let re = Regex::new(r"(?P<n>\d*)").unwrap();
+        for template in ["<$n>", "[${1}]", ""] {
+            let parsed = Template::parse(template).unwrap();
+            for haystack in ["a1b22c", "", "héllo 42"] {
+                assert_eq!(
+                    parsed.replace_all(&re, haystack).unwrap(),
+                    re.replace_all(haystack, template),
+                    "template {template:?} on {haystack:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+
+use encoding_rs::{Encoding, UTF_8};
+
+use super::confine::Guard;
+use super::os_path::{path_bytes, path_from_bytes};
+use super::sanitize::{NameIssue, sanitize_destination};
+use super::{AppError, FileEntry, SanitizeOptions, SkipReason};
+
+/// Encodings offered for file names, by WHATWG label. Latin-1 names are
+/// decoded as windows-1252, its superset, as browsers do.
+pub const NAME_ENCODINGS: [&str; 12] = [
+    "UTF-8",
+    "windows-1252",
+    "ISO-8859-2",
+    "ISO-8859-15",
+    "windows-1251",
+    "KOI8-R",
+    "Shift_JIS",
+    "EUC-JP",
+    "gb18030",
+    "GBK",
+    "Big5",
+    "EUC-KR",
+];
+
+/// Looks up the encoding for a WHATWG label such as `latin1` or `sjis`.
+pub fn name_encoding(label: &str) -> Result<&'static Encoding, AppError> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| AppError::UnknownEncoding {
+        label: label.to_string(),
+    })
+}
+
+/// Re-encodes one file name from `source` (detected when `None`) to
+/// `target`. Returns the new name and the encoding it was decoded with, or
+/// `None` when the name does not change. Nothing is replaced: names that do
+/// not decode or encode exactly are rejected.
+///
+/// Converting to UTF-8 leaves names that already are valid UTF-8 alone,
+/// so running a rule twice does not mangle `café` into `cafÃ©`, unless
+/// `reencode_utf8` is set.
+pub fn transcode_name(
+    name: &[u8],
+    source: Option<&'static Encoding>,
+    target: &'static Encoding,
+    reencode_utf8: bool,
+) -> Result<Option<(Vec<u8>, &'static Encoding)>, SkipReason> {
+    if target == UTF_8 && !reencode_utf8 && std::str::from_utf8(name).is_ok() {
+        return Ok(None);
+    }
+    let source = source.unwrap_or_else(|| match std::str::from_utf8(name) {
+        Ok(_) => UTF_8,
+        Err(_) => {
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(name, true);
+            detector.guess(None, false)
+        }
+    });
+    let text = source
+        .decode_without_bom_handling_and_without_replacement(name)
+        .ok_or(SkipReason::UndecodableName)?;
+    let (encoded, _, unmappable) = target.encode(&text);
+    if unmappable {
+        return Err(SkipReason::Unmappable);
+    }
+    Ok((*encoded != *name).then(|| (encoded.into_owned(), source)))
+}
+
+/// A name a transcode rule changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameChange {
+    pub path: PathBuf,
+    pub new_path: PathBuf,
+    pub is_dir: bool,
+    /// Encoding the old name was decoded with.
+    pub source: &'static Encoding,
+    /// Portability problems rewritten in the new name.
+    pub issues: Vec<NameIssue>,
+}
+
+/// Dry-run result of a transcode rule: the names it would change and the
+/// names it cannot convert.
+#[derive(Clone, Debug, Default)]
+pub struct TranscodePreview {
+    pub changes: Vec<NameChange>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+impl TranscodePreview {
+    /// Transcodes the last component of every entry. Entries are ordered
+    /// deepest first, so renaming a directory never moves an entry that is
+    /// still to be renamed.
+    pub fn plan(
+        mut entries: Vec<FileEntry>,
+        source: Option<&'static Encoding>,
+        target: &'static Encoding,
+        reencode_utf8: bool,
+    ) -> Self {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.path.components().count()));
+        let mut preview = Self::default();
+        for entry in entries {
+            let Some(name) = entry
+                .path
+                .file_name()
+                .and_then(|n| path_bytes(Path::new(n)))
+            else {
+                continue;
+            };
+            match transcode_name(name, source, target, reencode_utf8) {
+                Ok(Some((new_name, source))) => {
+                    let Some(new_name) = path_from_bytes(new_name) else {
+                        preview.skipped.push((entry.path, SkipReason::Unmappable));
+                        continue;
+                    };
+                    preview.changes.push(NameChange {
+                        new_path: entry.path.with_file_name(new_name),
+                        path: entry.path,
+                        is_dir: entry.is_dir,
+                        source,
+                        issues: Vec::new(),
+                    });
+                }
+                Ok(None) => {}
+                Err(reason) => preview.skipped.push((entry.path, reason)),
+            }
+        }
+        preview
+    }
+
+    /// Checks the new names against the portability profile and the length
+    /// limits, rewriting offending names or moving them to the skipped list.
+    pub fn sanitize(&mut self, options: SanitizeOptions) {
+        let mut changes = Vec::with_capacity(self.changes.len());
+        for mut change in std::mem::take(&mut self.changes) {
+            let sanitized = sanitize_destination(&change.path, &change.new_path, options);
+            match sanitized.skip {
+                None => {
+                    change.new_path = sanitized.path;
+                    change.issues = sanitized.issues;
+                    changes.push(change);
+                }
+                Some(reason) => self.skipped.push((change.path, reason)),
+            }
+        }
+        self.changes = changes;
+    }
+
+    /// Moves the changes leaving the root or touching a protected path to the
+    /// skipped list.
+    pub fn confine(&mut self, guard: &Guard) {
+        let mut changes = Vec::with_capacity(self.changes.len());
+        for change in std::mem::take(&mut self.changes) {
+            match guard.check(&change.path, &change.new_path) {
+                None => changes.push(change),
+                Some(reason) => self.skipped.push((change.path, reason)),
+            }
+        }
+        self.changes = changes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    #[test]
+    fn converts_latin1_and_shift_jis_names_to_utf8() {
+        assert_eq!(
+            transcode_name(b"caf\xE9.txt", Some(WINDOWS_1252), UTF_8, false),
+            Ok(Some(("café.txt".as_bytes().to_vec(), WINDOWS_1252)))
+        );
+        assert_eq!(
+            transcode_name(b"\x93\xfa\x96\x7b.txt", Some(SHIFT_JIS), UTF_8, false),
+            Ok(Some(("日本.txt".as_bytes().to_vec(), SHIFT_JIS)))
+        );
+    }
+
+    #[test]
+    fn leaves_utf8_names_alone_when_detecting() {
+        assert_eq!(
+            transcode_name("café".as_bytes(), None, UTF_8, false),
+            Ok(None)
+        );
+        let (name, _) = transcode_name(b"r\xE9sum\xE9", None, UTF_8, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(name, "résumé".as_bytes());
+    }
+
+    #[test]
+    fn leaves_utf8_names_alone_unless_asked_to_reencode_them() {
+        let latin1 = Some(WINDOWS_1252);
+        assert_eq!(
+            transcode_name("café".as_bytes(), latin1, UTF_8, false),
+            Ok(None)
+        );
+        let (once, _) = transcode_name(b"caf\xE9", latin1, UTF_8, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(transcode_name(&once, latin1, UTF_8, false), Ok(None));
+        assert_eq!(
+            transcode_name("café".as_bytes(), latin1, UTF_8, true),
+            Ok(Some(("cafÃ©".as_bytes().to_vec(), WINDOWS_1252)))
+        );
+    }
+
+    #[test]
+    fn flags_undecodable_and_unmappable_names() {
+        assert_eq!(
+            transcode_name(b"\x82", Some(SHIFT_JIS), UTF_8, false),
+            Err(SkipReason::UndecodableName)
+        );
+        assert_eq!(
+            transcode_name("日本".as_bytes(), Some(UTF_8), WINDOWS_1252, false),
+            Err(SkipReason::Unmappable)
+        );
+    }
+
+    #[test]
+    fn looks_up_encodings_by_label() {
+        assert_eq!(name_encoding("latin1").unwrap(), WINDOWS_1252);
+        assert_eq!(
+            name_encoding("unknown").unwrap_err().kind(),
+            "unknown_encoding"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plans_deepest_entries_first_and_renames_only_the_last_component() {
+        let entry = |path: &[u8], is_dir| FileEntry {
+            path: path_from_bytes(path.to_vec()).unwrap(),
+            is_dir,
+        };
+        let preview = TranscodePreview::plan(
+            vec![
+                entry(b"./caf\xE9", true),
+                entry(b"./caf\xE9/r\xE9.txt", false),
+            ],
+            Some(WINDOWS_1252),
+            UTF_8,
+            false,
+        );
+        let renames: Vec<_> = preview
+            .changes
+            .iter()
+            .map(|c| {
+                (
+                    path_bytes(&c.path).unwrap(),
+                    path_bytes(&c.new_path).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            renames,
+            vec![
+                (&b"./caf\xE9/r\xE9.txt"[..], &b"./caf\xE9/r\xC3\xA9.txt"[..]),
+                (&b"./caf\xE9"[..], "./café".as_bytes()),
+            ]
+        );
+    }
+}
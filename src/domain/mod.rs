@@ -1,11 +1,197 @@
-#[derive(Default)]
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// What a rule rewrites.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleMode {
+    /// Rename matched paths to the expanded template.
+    #[default]
+    Rename,
+    /// Replace matches inside the contents of the files selected by
+    /// [`Rule::path_filter`].
+    Content,
+    /// Convert the names of matched paths between encodings, see
+    /// [`Rule::transcoding`].
+    Transcode,
+}
+
+/// Filename encodings of a transcode rule, as WHATWG labels such as
+/// `windows-1252` or `Shift_JIS`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transcoding {
+    /// Encoding of the current names; `None` detects it for every name.
+    pub source: Option<String>,
+    /// Encoding of the new names.
+    pub target: String,
+    /// Also converts names that are already valid UTF-8 when converting a
+    /// given encoding to UTF-8, like convmv's `--nosmart`.
+    pub reencode_utf8: bool,
+}
+
+impl Default for Transcoding {
+    fn default() -> Self {
+        Self {
+            source: None,
+            target: "UTF-8".to_string(),
+            reencode_utf8: false,
+        }
+    }
+}
+
+/// Syntax of a rule's pattern.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleKind {
+    /// A regular expression, searched anywhere in the path.
+    #[default]
+    Regex,
+    /// A shell glob matched against the whole path, whose wildcards become
+    /// numbered groups.
+    Glob,
+}
+
+/// Regex engine a rule's pattern is compiled with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Engine {
+    /// The `regex` crate: linear time, no look-around or backreferences.
+    #[default]
+    Regex,
+    /// `fancy-regex`: adds look-around and backreferences by backtracking.
+    Fancy,
+    /// `regex-lite`: small and linear time, without Unicode classes.
+    Lite,
+}
+
+/// Unicode normalization form applied to the paths of a rename rule.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Normalization {
+    /// Paths are matched and renamed as stored.
+    #[default]
+    None,
+    /// Composed form (`é` as one code point), used by Windows and Linux tools.
+    Nfc,
+    /// Decomposed form (`e` + combining accent), as written by macOS.
+    Nfd,
+    /// Composed form that also folds compatibility characters such as `ﬁ`
+    /// or full-width digits.
+    Nfkc,
+}
+
+/// Compile limits applied to a rule's pattern through `regex::RegexBuilder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegexLimits {
+    /// Approximate heap size, in bytes, of the compiled program.
+    pub size_limit: usize,
+    /// Cache capacity, in bytes, of the lazy DFA.
+    pub dfa_size_limit: usize,
+}
+
+impl Default for RegexLimits {
+    /// The defaults of the `regex` crate.
+    fn default() -> Self {
+        Self {
+            size_limit: 10 * (1 << 20),
+            dfa_size_limit: 2 * (1 << 20),
+        }
+    }
+}
+
+/// A single rule. Fields missing from a saved rule set take their defaults,
+/// so files written before a field existed still load.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Rule {
     pub from: String,
     pub to: String,
+    pub mode: RuleMode,
+    pub kind: RuleKind,
+    /// Regex selecting the files a content rule reads; empty selects all.
+    pub path_filter: String,
+    pub transcoding: Transcoding,
+    pub engine: Engine,
+    pub limits: RegexLimits,
+    /// Form both the matched paths and the destinations are normalized to.
+    pub normalization: Normalization,
+    /// Rename rules replace every match in the path instead of the first,
+    /// like Perl's `s///g`.
+    pub replace_all: bool,
+    /// Rename rules only match the file name, the last component of each
+    /// path, so `^` anchors at its start and directories are never renamed.
+    pub name_only: bool,
+    /// Paths the rule is limited to, such as the files of a search; empty
+    /// applies the rule to every match. Compared byte for byte, so names
+    /// that are not valid UTF-8 are never confused.
+    #[serde(with = "path_list")]
+    pub paths: Vec<PathBuf>,
+    /// Match counts of the last count, never saved.
+    #[serde(skip)]
     pub file_match_count: Option<usize>,
+    #[serde(skip)]
     pub dir_match_count: Option<usize>,
 }
 
+/// Writes paths as strings, or as arrays of their bytes where they are not
+/// valid UTF-8, so saved rules keep names that are not Unicode.
+mod path_list {
+    use std::path::PathBuf;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum StoredPath {
+        Text(String),
+        Bytes(Vec<u8>),
+    }
+
+    impl From<&PathBuf> for StoredPath {
+        fn from(path: &PathBuf) -> Self {
+            match path.to_str() {
+                Some(text) => StoredPath::Text(text.to_string()),
+                #[cfg(unix)]
+                None => {
+                    use std::os::unix::ffi::OsStrExt;
+                    StoredPath::Bytes(path.as_os_str().as_bytes().to_vec())
+                }
+                #[cfg(not(unix))]
+                None => StoredPath::Text(path.to_string_lossy().into_owned()),
+            }
+        }
+    }
+
+    impl From<StoredPath> for PathBuf {
+        fn from(path: StoredPath) -> Self {
+            match path {
+                StoredPath::Text(text) => PathBuf::from(text),
+                #[cfg(unix)]
+                StoredPath::Bytes(bytes) => {
+                    use std::os::unix::ffi::OsStringExt;
+                    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+                }
+                #[cfg(not(unix))]
+                StoredPath::Bytes(bytes) => PathBuf::from(String::from_utf8_lossy(&bytes).as_ref()),
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(StoredPath::from))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<PathBuf>, D::Error> {
+        let paths = Vec::<StoredPath>::deserialize(deserializer)?;
+        Ok(paths.into_iter().map(PathBuf::from).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -15,7 +201,91 @@ mod tests {
         let rule = Rule::default();
         assert!(rule.from.is_empty());
         assert!(rule.to.is_empty());
+        assert_eq!(rule.mode, RuleMode::Rename);
+        assert_eq!(rule.kind, RuleKind::Regex);
+        assert!(rule.path_filter.is_empty());
+        assert_eq!(rule.transcoding.source, None);
+        assert_eq!(rule.transcoding.target, "UTF-8");
+        assert!(!rule.transcoding.reencode_utf8);
+        assert_eq!(rule.engine, Engine::Regex);
+        assert_eq!(rule.limits.size_limit, 10 << 20);
+        assert_eq!(rule.normalization, Normalization::None);
+        assert!(!rule.replace_all);
+        assert!(!rule.name_only);
+        assert!(rule.paths.is_empty());
         assert!(rule.file_match_count.is_none());
         assert!(rule.dir_match_count.is_none());
     }
+
+    fn sample_rule() -> Rule {
+        Rule {
+            from: r"^\./(\w+)\.JPG$".into(),
+            to: "${slug(1)}.jpg".into(),
+            mode: RuleMode::Transcode,
+            kind: RuleKind::Glob,
+            path_filter: r"\.txt$".into(),
+            transcoding: Transcoding {
+                source: Some("windows-1252".into()),
+                target: "UTF-8".into(),
+                reencode_utf8: true,
+            },
+            engine: Engine::Fancy,
+            limits: RegexLimits {
+                size_limit: 1 << 24,
+                dfa_size_limit: 1 << 20,
+            },
+            normalization: Normalization::Nfkc,
+            replace_all: true,
+            name_only: true,
+            paths: vec!["./IMG_1.JPG".into()],
+            file_match_count: None,
+            dir_match_count: None,
+        }
+    }
+
+    #[test]
+    fn rules_round_trip_through_toml_and_json() {
+        let rule = sample_rule();
+        let toml = toml::to_string(&rule).unwrap();
+        assert!(toml.contains("engine = \"fancy\""), "{toml}");
+        assert_eq!(toml::from_str::<Rule>(&toml).unwrap(), rule);
+        let json = serde_json::to_string(&rule).unwrap();
+        assert_eq!(serde_json::from_str::<Rule>(&json).unwrap(), rule);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_that_are_not_utf8_round_trip_as_bytes() {
+        use std::os::unix::ffi::OsStringExt;
+        let rule = Rule {
+            paths: vec![
+                PathBuf::from("./a.txt"),
+                PathBuf::from(std::ffi::OsString::from_vec(b"./caf\xE9".to_vec())),
+            ],
+            ..Rule::default()
+        };
+        let toml = toml::to_string(&rule).unwrap();
+        assert!(
+            toml.contains("[\"./a.txt\", [46, 47, 99, 97, 102, 233]]"),
+            "{toml}"
+        );
+        assert_eq!(toml::from_str::<Rule>(&toml).unwrap(), rule);
+        let json = serde_json::to_string(&rule).unwrap();
+        assert_eq!(serde_json::from_str::<Rule>(&json).unwrap(), rule);
+    }
+
+    #[test]
+    fn missing_fields_take_defaults_and_counts_are_not_saved() {
+        let rule: Rule = toml::from_str("from = 'a'\nto = 'b'\nunknown = 1").unwrap();
+        assert_eq!(rule.from, "a");
+        assert_eq!(rule.engine, Engine::Regex);
+        assert_eq!(rule.transcoding.target, "UTF-8");
+
+        let counted = Rule {
+            file_match_count: Some(3),
+            ..Rule::default()
+        };
+        let json = serde_json::to_string(&counted).unwrap();
+        assert!(!json.contains("count"), "{json}");
+    }
 }
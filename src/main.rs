@@ -3,13 +3,15 @@
 //! Runs natively and in the browser (wasm32‑unknown‑unknown).
 //==========================================================================
 #![allow(clippy::needless_return)]
+// `DEV_MODE` is a `cfg!` constant its test asserts on.
+#![cfg_attr(test, allow(clippy::assertions_on_constants))]
 
 use eframe::egui::{
-    self, Align, Button, CentralPanel, Context, Key, Layout, Modifiers, RichText, TopBottomPanel,
-    Vec2,
+    self, Align, Button, CentralPanel, Context, Key, Layout, Modifiers, RichText, SidePanel,
+    TopBottomPanel, Vec2,
 };
-use eframe::{App, Frame};
-use egui_extras::{Column, TableBuilder};
+use eframe::{App, Frame, Storage};
+use serde::{Deserialize, Serialize};
 
 #[cfg(target_arch = "wasm32")]
 use console_error_panic_hook;
@@ -25,45 +27,136 @@ mod application;
 mod domain;
 mod telemetry;
 mod theme;
+mod ui;
 
 use ansi::ansi_to_job;
-use application::{Renamer, StdFileSystem};
+use application::{
+    AppError, ExecuteOptions, PROFILES, PortabilityProfile, Renamer, RuleSet, RuleSetFormat,
+    STRATEGIES, SanitizeOptions, StdFileSystem, UndoJournal, rule_regex,
+};
 use domain::Rule;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::Arc;
 use telemetry::Logger;
 use telemetry::{MemoryWriter, TracingLogger, init_tracing};
-use theme::apply_catppuccin;
-use tracing::{info, warn};
+use theme::{Theme, apply_catppuccin};
+use tracing::{error, info, warn};
 use tracing_subscriber::filter::LevelFilter;
+use ui::diagnostics::DiagnosticsWindow;
+use ui::explainer::show_explainer;
+use ui::file::{FileAction, FilePrompt, FileWindow};
+use ui::import::{ImportAction, ImportWindow};
+use ui::playground::Playground;
+use ui::railroad::RailroadWindow;
+use ui::recipes::{RecipeAction, RecipeWindow};
+use ui::report::ReportWindow;
+use ui::rules::{RuleAction, RuleState, RuleTable};
+use ui::search::SearchPanel;
 
 /// Returns `true` when the application is compiled in development mode.
 const DEV_MODE: bool = cfg!(debug_assertions);
 
+/// Storage key of the rules and options of the last session, kept as a JSON
+/// rule set so restoring goes through the same migrations as rule set files.
+const SESSION_RULES_KEY: &str = "session_rules";
+/// Storage key of the [`ViewState`] of the last session.
+const SESSION_VIEW_KEY: &str = "session_view";
+
+/// Interface settings restored on the next start. The window size is kept by
+/// eframe itself.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ViewState {
+    show_log: bool,
+    theme: Theme,
+    rule_file: Option<PathBuf>,
+    recipe_dir: String,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            show_log: true,
+            theme: Theme::default(),
+            rule_file: None,
+            recipe_dir: String::new(),
+        }
+    }
+}
+
 //==========================================================================
 // RegexApp – the eframe::App implementation
 //==========================================================================
 
+/// Top-level view shown in the central panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Tab {
+    #[default]
+    Renamer,
+    Playground,
+    Search,
+}
+
 pub struct RegexApp {
+    /// View shown in the central panel.
+    tab: Tab,
+    /// Free-text regex tester.
+    playground: Playground,
+    /// Content search over the working directory.
+    search: SearchPanel,
     /// If `true`, a rename is simulated only (no file operations).
     dry_run: bool,
+    /// If `true`, a failed move does not abort the remaining batch.
+    continue_on_error: bool,
+    /// If `true`, directories emptied by a batch are removed afterwards.
+    remove_empty_dirs: bool,
+    /// Portability and length checks applied to destinations.
+    sanitize: SanitizeOptions,
+    /// Root edited in the 🛡 menu, applied to the renamer's confinement.
+    confine_root: String,
+    /// Protected paths edited in the 🛡 menu, one per line.
+    protected_paths: String,
     /// All currently‑defined rules.
     rules: Vec<Rule>,
+    /// Per‑rule GUI state, index‑aligned with `rules`.
+    rule_states: Vec<RuleState>,
+    /// Rule whose pattern is explained in the side panel.
+    selected_rule: Option<usize>,
+    /// Span of the selected rule's pattern picked in the explainer.
+    pattern_highlight: Option<Range<usize>>,
     /// Handles counting / executing renames.
     renamer: Renamer,
     /// In‑memory log buffer displayed in the UI.
     log_writer: MemoryWriter,
     /// Whether the log panel is visible.
     show_log: bool,
+    /// Color theme of the interface.
+    theme: Theme,
+    /// Results of the last execution, shown until dismissed.
+    report_window: Option<ReportWindow>,
+    /// Changes made by the last executed batch, until undone.
+    last_journal: Option<UndoJournal>,
+    /// Railroad diagram of one rule's pattern.
+    railroad_window: Option<RailroadWindow>,
+    /// Size and performance report of one rule's pattern.
+    diagnostics_window: Option<DiagnosticsWindow>,
+    /// Rule set file last opened or saved, used by File → Save.
+    rule_file: Option<PathBuf>,
+    /// Prompt for the path of a rule set file to open or save.
+    file_window: Option<FileWindow>,
+    /// Shared directory holding the team recipes.
+    recipe_dir: String,
+    /// Browser of the built-in and team recipes.
+    recipe_window: Option<RecipeWindow>,
+    /// Translator of pasted Perl `rename` and `mmv` commands.
+    import_window: Option<ImportWindow>,
 }
 
 impl RegexApp {
-    /// App entry‑point used in production (with tracing, real FS, etc.)
-    pub fn new(ctx: &Context) -> Self {
-        // -----------------------------------------------------------------
-        // Theme (apply once during setup)
-        // -----------------------------------------------------------------
-        apply_catppuccin(ctx);
-
+    /// App entry‑point used in production (with tracing, real FS, etc.).
+    /// Restores the last session from `storage` when there is one.
+    pub fn new(ctx: &Context, storage: Option<&dyn Storage>) -> Self {
         let log_writer = init_tracing(LevelFilter::INFO);
         info!("RegexApp started");
 
@@ -72,13 +165,42 @@ impl RegexApp {
         let fs: Arc<StdFileSystem> = Arc::new(StdFileSystem);
         let renamer = Renamer::new(logger, fs);
 
-        Self {
+        let mut app = Self {
+            tab: Tab::default(),
+            playground: Playground::default(),
+            search: SearchPanel::default(),
             dry_run: true,
+            continue_on_error: false,
+            remove_empty_dirs: false,
+            sanitize: SanitizeOptions::default(),
+            confine_root: ".".to_string(),
+            protected_paths: ".git".to_string(),
             rules: vec![Rule::default()],
+            rule_states: vec![RuleState::default()],
+            selected_rule: None,
+            pattern_highlight: None,
             renamer,
             log_writer,
             show_log: true,
+            theme: Theme::default(),
+            report_window: None,
+            last_journal: None,
+            railroad_window: None,
+            diagnostics_window: None,
+            rule_file: None,
+            file_window: None,
+            recipe_dir: String::new(),
+            recipe_window: None,
+            import_window: None,
+        };
+        if let Some(storage) = storage {
+            app.restore(storage);
         }
+        // -----------------------------------------------------------------
+        // Theme (restored with the session)
+        // -----------------------------------------------------------------
+        apply_catppuccin(ctx, app.theme);
+        app
     }
 
     /// Test‑friendly constructor: no tracing overhead, no real FS.
@@ -88,23 +210,471 @@ impl RegexApp {
         let fs: Arc<StdFileSystem> = Arc::new(StdFileSystem);
         let renamer = Renamer::new(logger, fs);
         Self {
+            tab: Tab::default(),
+            playground: Playground::default(),
+            search: SearchPanel::default(),
             dry_run: false,
+            continue_on_error: false,
+            remove_empty_dirs: false,
+            sanitize: SanitizeOptions::default(),
+            confine_root: ".".to_string(),
+            protected_paths: ".git".to_string(),
             rules: vec![Rule::default()],
+            rule_states: vec![RuleState::default()],
+            selected_rule: None,
+            pattern_highlight: None,
             renamer,
             log_writer: MemoryWriter::default(),
             show_log: true,
+            theme: Theme::default(),
+            report_window: None,
+            last_journal: None,
+            railroad_window: None,
+            diagnostics_window: None,
+            rule_file: None,
+            file_window: None,
+            recipe_dir: String::new(),
+            recipe_window: None,
+            import_window: None,
         }
     }
 
     fn add_rule(&mut self) {
         self.rules.push(Rule::default());
+        self.rule_states.push(RuleState::default());
+    }
+
+    /// Adds a rename rule for the paths found by a search and shows it.
+    fn add_search_rule(&mut self, paths: Vec<PathBuf>) {
+        info!("Added rule for {} files of the search results", paths.len());
+        self.rules.push(Rule {
+            from: "^.*$".to_string(),
+            to: "$0".to_string(),
+            paths,
+            ..Rule::default()
+        });
+        self.rule_states.push(RuleState::default());
+        self.tab = Tab::Renamer;
+    }
+
+    /// Appends the rules of a recipe or an import. A lone empty rule, as on a fresh
+    /// start, is replaced instead.
+    fn insert_rules(&mut self, rules: Vec<Rule>) {
+        if self.rules.len() == 1 && self.rules[0] == Rule::default() {
+            self.rules.clear();
+            self.rule_states.clear();
+        }
+        info!("Inserted {} rules", rules.len());
+        self.rule_states
+            .extend(rules.iter().map(|_| RuleState::default()));
+        self.rules.extend(rules);
+        self.tab = Tab::Renamer;
     }
 
     fn remove_rule(&mut self, index: usize) {
         if index < self.rules.len() {
             self.rules.remove(index);
+            self.rule_states.remove(index);
+            self.selected_rule = match self.selected_rule {
+                Some(selected) if selected == index => None,
+                Some(selected) if selected > index => Some(selected - 1),
+                other => other,
+            };
+            if self.selected_rule.is_none() {
+                self.pattern_highlight = None;
+            }
+            self.railroad_window = self
+                .railroad_window
+                .take()
+                .filter(|window| window.rule != index)
+                .map(|mut window| {
+                    window.rule -= usize::from(window.rule > index);
+                    window
+                });
+            self.diagnostics_window = self
+                .diagnostics_window
+                .take()
+                .filter(|window| window.rule != index)
+                .map(|mut window| {
+                    window.rule -= usize::from(window.rule > index);
+                    window
+                });
+        }
+    }
+
+    fn clear_rule_errors(&mut self) {
+        self.rule_states.iter_mut().for_each(|s| s.error = None);
+    }
+
+    /// The rules and the options of the session, as saved to a file.
+    fn rule_set(&self) -> RuleSet {
+        RuleSet {
+            options: ExecuteOptions {
+                dry_run: self.dry_run,
+                continue_on_error: self.continue_on_error,
+                sanitize: self.sanitize,
+                remove_empty_dirs: self.remove_empty_dirs,
+            },
+            confinement: self.renamer.confinement().clone(),
+            rules: self.rules.clone(),
+            ..RuleSet::default()
+        }
+    }
+
+    /// Replaces the rules and the options of the session with `set`.
+    fn apply_rule_set(&mut self, set: RuleSet) {
+        self.dry_run = set.options.dry_run;
+        self.continue_on_error = set.options.continue_on_error;
+        self.sanitize = set.options.sanitize;
+        self.remove_empty_dirs = set.options.remove_empty_dirs;
+        self.confine_root = set.confinement.root.display().to_string();
+        self.protected_paths = set
+            .confinement
+            .protected
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.renamer.set_confinement(set.confinement);
+        self.rules = set.rules;
+        if self.rules.is_empty() {
+            self.rules.push(Rule::default());
+        }
+        self.rule_states = self.rules.iter().map(|_| RuleState::default()).collect();
+        self.selected_rule = None;
+        self.pattern_highlight = None;
+        self.railroad_window = None;
+        self.diagnostics_window = None;
+    }
+
+    fn open_rules(&mut self, path: PathBuf) -> Result<(), AppError> {
+        let set = RuleSet::load(&path)?.within_safety_of(&self.rule_set());
+        info!("Opened {} rules from {}", set.rules.len(), path.display());
+        self.apply_rule_set(set);
+        self.rule_file = Some(path);
+        Ok(())
+    }
+
+    fn save_rules(&mut self, path: PathBuf) -> Result<(), AppError> {
+        self.rule_set().save(&path)?;
+        info!("Saved {} rules to {}", self.rules.len(), path.display());
+        self.rule_file = Some(path);
+        Ok(())
+    }
+
+    /// Restores the session saved by [`App::save`]. Parts that are missing
+    /// or cannot be read keep their defaults.
+    fn restore(&mut self, storage: &dyn Storage) {
+        if let Some(text) = storage.get_string(SESSION_RULES_KEY) {
+            match RuleSet::from_text(&text, RuleSetFormat::Json) {
+                Ok(set) => self.apply_rule_set(set),
+                Err(e) => warn!("Could not restore the rules of the last session: {e}"),
+            }
+        }
+        if let Some(view) = eframe::get_value::<ViewState>(storage, SESSION_VIEW_KEY) {
+            self.show_log = view.show_log;
+            self.theme = view.theme;
+            self.rule_file = view.rule_file;
+            self.recipe_dir = view.recipe_dir;
+        }
+    }
+
+    /// Replaces the rules, options and view settings with those of a fresh
+    /// start.
+    fn reset_to_defaults(&mut self) {
+        info!("Reset to defaults");
+        self.apply_rule_set(RuleSet {
+            options: ExecuteOptions {
+                dry_run: true,
+                ..ExecuteOptions::default()
+            },
+            ..RuleSet::default()
+        });
+        let view = ViewState::default();
+        self.show_log = view.show_log;
+        self.theme = view.theme;
+        self.rule_file = view.rule_file;
+        self.recipe_dir = view.recipe_dir;
+        self.recipe_window = None;
+    }
+
+    /// Path the file prompt starts with.
+    fn rule_file_hint(&self) -> String {
+        self.rule_file
+            .as_ref()
+            .map_or("rules.toml".to_string(), |path| path.display().to_string())
+    }
+
+    /// Handles a click in the rule table.
+    fn apply_rule_action(&mut self, action: RuleAction) {
+        match action {
+            RuleAction::Add => {
+                self.add_rule();
+                info!("Added new rule");
+            }
+            RuleAction::Remove(idx) => self.remove_rule(idx),
+            RuleAction::Explain(idx) => {
+                self.selected_rule = (self.selected_rule != Some(idx)).then_some(idx);
+                self.pattern_highlight = None;
+            }
+            RuleAction::Railroad(idx) => {
+                let open = self.railroad_window.as_ref().is_some_and(|w| w.rule == idx);
+                self.railroad_window = (!open).then(|| RailroadWindow::new(idx));
+            }
+            RuleAction::Diagnostics(idx) => {
+                let open = self
+                    .diagnostics_window
+                    .as_ref()
+                    .is_some_and(|w| w.rule == idx);
+                self.diagnostics_window = (!open).then(|| DiagnosticsWindow::new(idx));
+            }
         }
     }
+
+    /// File and recipe menus, title and tabs above the central panel.
+    fn show_menu_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.menu_button("File", |ui| {
+                if ui.button("📂 Open…").clicked() {
+                    self.file_window =
+                        Some(FileWindow::new(FileAction::Open, self.rule_file_hint()));
+                    ui.close_menu();
+                }
+                if ui.button("💾 Save").clicked() {
+                    match self.rule_file.clone() {
+                        Some(path) => {
+                            if let Err(e) = self.save_rules(path) {
+                                error!("{e}");
+                            }
+                        }
+                        None => {
+                            self.file_window =
+                                Some(FileWindow::new(FileAction::SaveAs, self.rule_file_hint()));
+                        }
+                    }
+                    ui.close_menu();
+                }
+                if ui.button("💾 Save As…").clicked() {
+                    self.file_window =
+                        Some(FileWindow::new(FileAction::SaveAs, self.rule_file_hint()));
+                    ui.close_menu();
+                }
+                if ui
+                    .button("📥 Import commands…")
+                    .on_hover_text("Turn Perl rename and mmv commands into rules")
+                    .clicked()
+                {
+                    self.import_window = Some(ImportWindow::default());
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui
+                    .button("↺ Reset to defaults")
+                    .on_hover_text("Start over with one empty rule and the default options")
+                    .clicked()
+                {
+                    self.reset_to_defaults();
+                    apply_catppuccin(ui.ctx(), self.theme);
+                    ui.close_menu();
+                }
+            })
+            .response
+            .on_hover_text("Open or save the rules and options as TOML or JSON");
+            if ui
+                .button("📚 Recipes")
+                .on_hover_text("Insert ready-made rules or share yours with the team")
+                .clicked()
+            {
+                self.recipe_window = Some(RecipeWindow::new(&self.recipe_dir));
+            }
+            ui.heading(RichText::new("Regex Renamer").size(20.0));
+            ui.separator();
+            ui.selectable_value(&mut self.tab, Tab::Renamer, "📁 Rename rules");
+            ui.selectable_value(&mut self.tab, Tab::Playground, "🧪 Playground");
+            ui.selectable_value(&mut self.tab, Tab::Search, "🔎 Search");
+        });
+    }
+
+    /// Count, execute and undo buttons with the options of a batch.
+    fn show_batch_controls(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            let button_size = Vec2::new((ui.available_width() / 2.0) - 6.0, 42.0);
+
+            // Count all button
+            if ui
+                .add_sized(
+                    button_size,
+                    Button::new(RichText::new("🔍 Count All").size(18.0)),
+                )
+                .clicked()
+            {
+                info!("Count all clicked");
+                self.clear_rule_errors();
+                for error in self.renamer.count_all_matches(&mut self.rules) {
+                    self.rule_states[error.rule].error = Some(error.error);
+                }
+            }
+
+            // Execute button (disabled while any rule is invalid)
+            let all_valid = self
+                .rules
+                .iter()
+                .zip(&mut self.rule_states)
+                .all(|(rule, state)| state.validation(rule).valid);
+            if ui
+                .add_enabled_ui(all_valid, |ui| {
+                    ui.add_sized(
+                        button_size,
+                        Button::new(RichText::new("▶ Execute").size(18.0)),
+                    )
+                })
+                .inner
+                .on_disabled_hover_text("Fix the invalid rules before executing")
+                .clicked()
+            {
+                info!("Execute clicked");
+                if self.dry_run {
+                    warn!("Dry‑run mode enabled – no filesystem changes will be applied");
+                }
+                let options = ExecuteOptions {
+                    dry_run: self.dry_run,
+                    continue_on_error: self.continue_on_error,
+                    sanitize: self.sanitize,
+                    remove_empty_dirs: self.remove_empty_dirs,
+                };
+                self.clear_rule_errors();
+                match self.renamer.execute(&self.rules, options) {
+                    Ok(mut report) => {
+                        let journal = std::mem::take(&mut report.journal);
+                        if !journal.is_empty() {
+                            self.last_journal = Some(journal);
+                        }
+                        self.report_window = Some(ReportWindow::new(report));
+                    }
+                    Err(e) => {
+                        error!("Execution failed: {e}");
+                        self.rule_states[e.rule].error = Some(e.error);
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.dry_run, "Dry Run")
+                .on_hover_text("Simulate renames without touching the file system");
+            ui.checkbox(&mut self.continue_on_error, "Continue on error")
+                .on_hover_text("Keep applying the batch after a failed move");
+            ui.checkbox(&mut self.remove_empty_dirs, "Remove emptied dirs")
+                .on_hover_text("Remove the directories the batch left empty; undo recreates them");
+            let before = self.sanitize;
+            ui.label("Portability:");
+            egui::ComboBox::from_id_salt("portability_profile")
+                .width(100.0)
+                .selected_text(self.sanitize.profile.name())
+                .show_ui(ui, |ui| {
+                    for profile in PROFILES {
+                        ui.selectable_value(&mut self.sanitize.profile, profile, profile.name())
+                            .on_hover_text(profile.description());
+                    }
+                })
+                .response
+                .on_hover_text(self.sanitize.profile.description());
+            ui.add_enabled(
+                self.sanitize.profile != PortabilityProfile::Off,
+                egui::Checkbox::new(&mut self.sanitize.rewrite, "Rewrite names"),
+            )
+            .on_hover_text("Replace offending characters instead of skipping the entries");
+            ui.menu_button(
+                format!("📏 {}", self.sanitize.length.strategy.name()),
+                |ui| {
+                    let length = &mut self.sanitize.length;
+                    ui.label("Over-long destinations:");
+                    for strategy in STRATEGIES {
+                        ui.radio_value(&mut length.strategy, strategy, strategy.name())
+                            .on_hover_text(strategy.description());
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Max name bytes:");
+                        ui.add(egui::DragValue::new(&mut length.max_name).range(16..=1024));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Max path bytes:");
+                        ui.add(egui::DragValue::new(&mut length.max_path).range(64..=32767));
+                    });
+                },
+            )
+            .response
+            .on_hover_text("Length limits of destination names and paths");
+            let mut confinement = self.renamer.confinement().clone();
+            let shield = if confinement.enabled {
+                "🛡 Confined"
+            } else {
+                "🛡 Unconfined"
+            };
+            ui.menu_button(shield, |ui| {
+                ui.checkbox(&mut confinement.enabled, "Confine to root")
+                    .on_hover_text(
+                        "Skip entries whose source or destination resolves outside the root",
+                    );
+                ui.horizontal(|ui| {
+                    ui.label("Root:");
+                    ui.add_enabled(
+                        confinement.enabled,
+                        egui::TextEdit::singleline(&mut self.confine_root).desired_width(160.0),
+                    );
+                });
+                ui.label("Protected paths, one per line:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.protected_paths)
+                        .desired_rows(3)
+                        .desired_width(200.0)
+                        .font(egui::TextStyle::Monospace),
+                )
+                .on_hover_text("Never moved, renamed or rewritten, nor anything below them");
+            })
+            .response
+            .on_hover_text("Where a batch may move files");
+            confinement.root = self.confine_root.trim().into();
+            confinement.protected = self
+                .protected_paths
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(Into::into)
+                .collect();
+            if confinement != *self.renamer.confinement() {
+                self.renamer.set_confinement(confinement);
+                self.rule_states.iter_mut().for_each(RuleState::collapse);
+            }
+            if self.sanitize != before {
+                // Previews show sanitized destinations.
+                self.rule_states.iter_mut().for_each(RuleState::collapse);
+            }
+            let undo_hint = self
+                .last_journal
+                .as_ref()
+                .map_or("Nothing to undo".to_string(), |journal| {
+                    format!("Revert the {} changes of the last batch", journal.len())
+                });
+            if ui
+                .add_enabled(
+                    self.last_journal.is_some(),
+                    Button::new("↶ Undo last batch"),
+                )
+                .on_hover_text(&undo_hint)
+                .on_disabled_hover_text(&undo_hint)
+                .clicked()
+            {
+                if let Some(journal) = self.last_journal.take() {
+                    info!("Undo clicked");
+                    let report = self.renamer.undo(journal);
+                    self.report_window = Some(ReportWindow::new(report));
+                }
+            }
+        });
+    }
 }
 
 //==========================================================================
@@ -112,6 +682,20 @@ impl RegexApp {
 //==========================================================================
 
 impl App for RegexApp {
+    fn save(&mut self, storage: &mut dyn Storage) {
+        match self.rule_set().to_text(RuleSetFormat::Json) {
+            Ok(text) => storage.set_string(SESSION_RULES_KEY, text),
+            Err(e) => warn!("Could not save the session: {e}"),
+        }
+        let view = ViewState {
+            show_log: self.show_log,
+            theme: self.theme,
+            rule_file: self.rule_file.clone(),
+            recipe_dir: self.recipe_dir.clone(),
+        };
+        eframe::set_value(storage, SESSION_VIEW_KEY, &view);
+    }
+
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
         // -----------------------------------------------------------------
         // Toggle log visibility via hot‑key (press "L") in development mode
@@ -162,138 +746,135 @@ impl App for RegexApp {
                 });
         }
 
+        // ═════════════════════════ Explainer panel ═══════════════════════
+        if let Some(idx) = self.selected_rule.filter(|_| self.tab == Tab::Renamer) {
+            SidePanel::right("explainer_panel")
+                .resizable(true)
+                .default_width(380.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.heading(format!("Rule #{}", idx + 1));
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button("✖").on_hover_text("Close explainer").clicked() {
+                                self.selected_rule = None;
+                                self.pattern_highlight = None;
+                            }
+                        });
+                    });
+                    ui.weak("Click a node to highlight it in the pattern.");
+                    ui.separator();
+                    let rule = &self.rules[idx];
+                    let pattern = rule_regex(&rule.from, rule.kind)
+                        .unwrap_or(std::borrow::Cow::Borrowed(&rule.from));
+                    show_explainer(ui, &pattern, &mut self.pattern_highlight);
+                });
+        }
+
         // ═════════════════════════ Central panel ═════════════════════════
         CentralPanel::default().show(ctx, |ui| {
-            ui.heading(RichText::new("Regex Renamer").size(20.0));
+            self.show_menu_bar(ui);
             ui.separator();
 
-            //--------------------------- Rule table ------------------------
-            TableBuilder::new(ui)
-                .striped(true)
-                .column(Column::auto()) // regex
-                .column(Column::auto()) // to path
-                .column(Column::auto()) // dirs
-                .column(Column::auto()) // files
-                .column(Column::remainder()) // actions (+/count/✖)
-                .header(24.0, |mut header| {
-                    header.col(|ui| {
-                        ui.strong("From Regex");
-                    });
-                    header.col(|ui| {
-                        ui.strong("To Path");
-                    });
-                    header.col(|ui| {
-                        ui.strong("Dirs");
-                    });
-                    header.col(|ui| {
-                        ui.strong("Files");
-                    });
-                    header.col(|ui| {
-                        if ui.button("➕ Add").on_hover_text("Add rule").clicked() {
-                            self.add_rule();
-                            info!("Added new rule");
-                        }
-                    });
-                })
-                .body(|mut body| {
-                    let regex_width = 220.0;
-                    let path_width = 220.0;
-
-                    let mut idx = 0usize;
-                    while idx < self.rules.len() {
-                        let rule = &mut self.rules[idx];
-                        let mut should_remove = false;
-
-                        body.row(24.0, |mut row| {
-                            // Regex pattern
-                            row.col(|ui| {
-                                ui.add_sized(
-                                    [regex_width, 0.0],
-                                    egui::TextEdit::singleline(&mut rule.from).hint_text("regex"),
-                                );
-                            });
-
-                            // Destination path
-                            row.col(|ui| {
-                                ui.add_sized(
-                                    [path_width, 0.0],
-                                    egui::TextEdit::singleline(&mut rule.to)
-                                        .hint_text("destination"),
-                                );
-                            });
-
-                            // Dir counter
-                            row.col(|ui| {
-                                let dir_text =
-                                    rule.dir_match_count.map_or("—".into(), |n| n.to_string());
-                                ui.label(dir_text);
-                            });
+            match self.tab {
+                Tab::Renamer => {}
+                Tab::Playground => {
+                    self.playground.show(ui);
+                    return;
+                }
+                Tab::Search => {
+                    if let Some(paths) = self.search.show(ui, &self.renamer) {
+                        self.add_search_rule(paths);
+                    }
+                    return;
+                }
+            }
 
-                            // File counter
-                            row.col(|ui| {
-                                let file_text =
-                                    rule.file_match_count.map_or("—".into(), |n| n.to_string());
-                                ui.label(file_text);
-                            });
+            let table = RuleTable {
+                renamer: &self.renamer,
+                sanitize: self.sanitize,
+                selected: self.selected_rule,
+                highlight: &mut self.pattern_highlight,
+                railroad: self.railroad_window.as_ref().map(|w| w.rule),
+                diagnostics: self.diagnostics_window.as_ref().map(|w| w.rule),
+            };
+            if let Some(action) = table.show(ui, &mut self.rules, &mut self.rule_states) {
+                self.apply_rule_action(action);
+            }
 
-                            // Actions
-                            row.col(|ui| {
-                                ui.horizontal(|ui| {
-                                    if ui.button("🔍").on_hover_text("Count matches").clicked() {
-                                        let _ = self.renamer.count_matches(rule);
-                                    }
-                                    if ui.button("❌").on_hover_text("Remove rule").clicked() {
-                                        should_remove = true;
-                                    }
-                                });
-                            });
-                        });
+            ui.add_space(12.0);
+            ui.with_layout(Layout::bottom_up(Align::Min), |ui| {
+                self.show_batch_controls(ui)
+            });
+        });
 
-                        if should_remove {
-                            self.remove_rule(idx);
-                        } else {
-                            idx += 1; // only advance if rule wasn’t removed
+        // ═════════════════════════ Rule set file ═════════════════════════
+        if let Some(window) = &mut self.file_window {
+            match window.show(ctx) {
+                FilePrompt::Pending => {}
+                FilePrompt::Closed => self.file_window = None,
+                FilePrompt::Confirmed(path) => {
+                    let action = window.action;
+                    let result = match action {
+                        FileAction::Open => self.open_rules(path),
+                        FileAction::SaveAs => self.save_rules(path),
+                    };
+                    match result {
+                        Ok(()) => self.file_window = None,
+                        Err(e) => {
+                            error!("{e}");
+                            if let Some(window) = &mut self.file_window {
+                                window.set_error(e.to_string());
+                            }
                         }
                     }
-                });
+                }
+            }
+        }
 
-            ui.add_space(12.0);
+        // ═════════════════════════ Recipes ═══════════════════════════════
+        if let Some(window) = &mut self.recipe_window {
+            match window.show(ctx, &self.rules, &mut self.recipe_dir) {
+                RecipeAction::Pending => {}
+                RecipeAction::Closed => self.recipe_window = None,
+                RecipeAction::Insert(rules) => self.insert_rules(rules),
+            }
+        }
 
-            //---------------------- Global actions -------------------------
-            ui.with_layout(Layout::bottom_up(Align::Min), |ui| {
-                ui.add_space(4.0);
-                ui.horizontal(|ui| {
-                    let button_size = Vec2::new((ui.available_width() / 2.0) - 6.0, 42.0);
+        // ═════════════════════════ Command import ════════════════════════
+        if let Some(window) = &mut self.import_window {
+            match window.show(ctx) {
+                ImportAction::Pending => {}
+                ImportAction::Closed => self.import_window = None,
+                ImportAction::Insert(rules) => {
+                    self.insert_rules(rules);
+                    self.import_window = None;
+                }
+            }
+        }
 
-                    // Count all button
-                    if ui
-                        .add_sized(
-                            button_size,
-                            Button::new(RichText::new("🔍 Count All").size(18.0)),
-                        )
-                        .clicked()
-                    {
-                        info!("Count all clicked");
-                        let _ = self.renamer.count_all_matches(&mut self.rules);
-                    }
+        // ═════════════════════════ Results dialog ════════════════════════
+        if let Some(window) = &mut self.report_window {
+            if !window.show(ctx) {
+                self.report_window = None;
+            }
+        }
 
-                    // Execute button
-                    if ui
-                        .add_sized(
-                            button_size,
-                            Button::new(RichText::new("▶ Execute").size(18.0)),
-                        )
-                        .clicked()
-                    {
-                        info!("Execute clicked");
-                        if self.dry_run {
-                            warn!("Dry‑run mode enabled – no filesystem changes will be applied");
-                        }
-                        let _ = self.renamer.execute(&self.rules, self.dry_run);
-                    }
-                });
-            });
-        });
+        // ═════════════════════════ Railroad diagram ══════════════════════
+        if let Some(window) = &mut self.railroad_window {
+            let rule = &self.rules[window.rule];
+            let pattern =
+                rule_regex(&rule.from, rule.kind).unwrap_or(std::borrow::Cow::Borrowed(&rule.from));
+            if !window.show(ctx, &pattern) {
+                self.railroad_window = None;
+            }
+        }
+
+        // ═════════════════════════ Diagnostics ═══════════════════════════
+        if let Some(window) = &mut self.diagnostics_window {
+            if !window.show(ctx, &self.renamer, &mut self.rules[window.rule]) {
+                self.diagnostics_window = None;
+            }
+        }
     }
 }
 
@@ -306,7 +887,7 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Regex GUI",
         native_opts,
-        Box::new(|cc| Ok(Box::new(RegexApp::new(&cc.egui_ctx)))),
+        Box::new(|cc| Ok(Box::new(RegexApp::new(&cc.egui_ctx, cc.storage)))),
     )
 }
 
@@ -338,7 +919,7 @@ fn main() {
             .start(
                 canvas,
                 eframe::WebOptions::default(),
-                Box::new(|cc| Ok(Box::new(RegexApp::new(&cc.egui_ctx)))),
+                Box::new(|cc| Ok(Box::new(RegexApp::new(&cc.egui_ctx, cc.storage)))),
             )
             .await
             .expect("eframe start failed");
@@ -351,6 +932,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use application::{Confinement, compile_rule};
 
     #[test]
     fn add_rule_appends_default_rule() {
@@ -362,6 +944,19 @@ mod tests {
         assert!(rule.from.is_empty() && rule.to.is_empty());
     }
 
+    #[test]
+    fn add_search_rule_appends_identity_rule() {
+        let mut app = RegexApp::new_for_tests();
+        app.tab = Tab::Search;
+        app.add_search_rule(vec![PathBuf::from("./a.txt")]);
+        assert_eq!(app.rules.len(), 2);
+        assert_eq!(app.rule_states.len(), 2);
+        assert_eq!(app.rules[1].to, "$0");
+        assert_eq!(app.rules[1].paths, vec![PathBuf::from("./a.txt")]);
+        assert!(compile_rule(&app.rules[1]).is_ok());
+        assert_eq!(app.tab, Tab::Renamer);
+    }
+
     #[test]
     fn remove_rule_deletes_correct_index() {
         let mut app = RegexApp::new_for_tests();
@@ -370,6 +965,144 @@ mod tests {
         app.remove_rule(0);
         assert_eq!(app.rules.len(), 1);
         assert_eq!(app.rules[0].from, second_from);
+        assert_eq!(app.rule_states.len(), app.rules.len());
+    }
+
+    #[test]
+    fn remove_rule_keeps_selection_on_same_rule() {
+        let mut app = RegexApp::new_for_tests();
+        app.add_rule();
+        app.add_rule();
+        app.selected_rule = Some(2);
+        app.remove_rule(0);
+        assert_eq!(app.selected_rule, Some(1));
+        app.remove_rule(1);
+        assert_eq!(app.selected_rule, None);
+    }
+
+    #[test]
+    fn remove_rule_closes_its_railroad_window() {
+        let mut app = RegexApp::new_for_tests();
+        app.add_rule();
+        app.railroad_window = Some(RailroadWindow::new(1));
+        app.remove_rule(0);
+        assert_eq!(app.railroad_window.as_ref().map(|w| w.rule), Some(0));
+        app.remove_rule(0);
+        assert!(app.railroad_window.is_none());
+    }
+
+    #[test]
+    fn remove_rule_keeps_diagnostics_on_other_rule() {
+        let mut app = RegexApp::new_for_tests();
+        app.add_rule();
+        app.diagnostics_window = Some(DiagnosticsWindow::new(0));
+        app.remove_rule(1);
+        assert_eq!(app.diagnostics_window.as_ref().map(|w| w.rule), Some(0));
+        app.remove_rule(0);
+        assert!(app.diagnostics_window.is_none());
+    }
+
+    #[test]
+    fn rule_sets_round_trip_through_a_file() {
+        let path = std::env::temp_dir().join(format!("rules-{}.toml", std::process::id()));
+        let mut app = RegexApp::new_for_tests();
+        app.rules[0].from = r"(\d+)\.JPG$".to_string();
+        app.rules[0].to = "$1.jpg".to_string();
+        app.add_rule();
+        app.dry_run = true;
+        app.protected_paths = ".git\ntarget".to_string();
+        app.renamer.set_confinement(Confinement {
+            protected: vec![".git".into(), "target".into()],
+            ..Confinement::default()
+        });
+        app.save_rules(path.clone()).unwrap();
+
+        let mut other = RegexApp::new_for_tests();
+        other.selected_rule = Some(0);
+        other.open_rules(path.clone()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(other.rules, app.rules);
+        assert_eq!(other.rule_states.len(), 2);
+        assert!(other.dry_run);
+        assert_eq!(other.protected_paths, app.protected_paths);
+        assert_eq!(other.renamer.confinement(), app.renamer.confinement());
+        assert_eq!(other.selected_rule, None);
+        assert_eq!(other.rule_file, Some(path.clone()));
+        assert!(other.open_rules(path).is_err());
+    }
+
+    /// In-memory stand-in for eframe's file and localStorage backends.
+    #[derive(Default)]
+    struct MemoryStorage(std::collections::HashMap<String, String>);
+
+    impl Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn session_is_restored_and_reset() {
+        let mut app = RegexApp::new_for_tests();
+        app.rules[0].from = "a".to_string();
+        app.add_rule();
+        app.continue_on_error = true;
+        app.show_log = false;
+        app.recipe_dir = "/shared/recipes".to_string();
+        app.confine_root = "photos".to_string();
+        app.renamer.set_confinement(Confinement {
+            root: "photos".into(),
+            ..Confinement::default()
+        });
+        let mut storage = MemoryStorage::default();
+        app.save(&mut storage);
+
+        let mut restored = RegexApp::new_for_tests();
+        restored.restore(&storage);
+        assert_eq!(restored.rules, app.rules);
+        assert_eq!(restored.rule_states.len(), 2);
+        assert!(restored.continue_on_error);
+        assert!(!restored.show_log);
+        assert_eq!(restored.theme, Theme::Mocha);
+        assert_eq!(restored.confine_root, "photos");
+        assert_eq!(restored.recipe_dir, "/shared/recipes");
+
+        restored.reset_to_defaults();
+        assert_eq!(restored.rules, vec![Rule::default()]);
+        assert!(restored.dry_run && !restored.continue_on_error);
+        assert!(restored.show_log);
+        assert_eq!(restored.renamer.confinement(), &Confinement::default());
+    }
+
+    #[test]
+    fn recipes_replace_a_lone_empty_rule() {
+        let recipe = application::Recipe::built_in().remove(0);
+        let mut app = RegexApp::new_for_tests();
+        app.tab = Tab::Search;
+        app.insert_rules(recipe.rules.clone());
+        assert_eq!(app.rules, recipe.rules);
+        assert_eq!(app.rule_states.len(), app.rules.len());
+        assert_eq!(app.tab, Tab::Renamer);
+
+        app.insert_rules(recipe.rules.clone());
+        assert_eq!(app.rules.len(), 2 * recipe.rules.len());
+        assert_eq!(app.rule_states.len(), app.rules.len());
+    }
+
+    #[test]
+    fn unreadable_session_keeps_the_defaults() {
+        let mut storage = MemoryStorage::default();
+        storage.set_string(SESSION_RULES_KEY, "{\"version\": 99}".to_string());
+        let mut app = RegexApp::new_for_tests();
+        app.restore(&storage);
+        assert_eq!(app.rules, vec![Rule::default()]);
+        assert!(app.show_log);
     }
 
     #[test]
@@ -7,6 +7,18 @@ use tracing_subscriber::{EnvFilter, Registry, layer::SubscriberExt};
 /// specific logging frameworks.
 pub trait Logger: Send + Sync {
     fn log(&self, message: &str);
+
+    /// Records a failure. `kind` is a stable, machine-readable category
+    /// and `fields` the context of the failure, such as its paths; both are
+    /// attached as fields by structured loggers.
+    fn error(
+        &self,
+        _kind: &str,
+        _fields: &[(&'static str, String)],
+        error: &dyn std::error::Error,
+    ) {
+        self.log(&error.to_string());
+    }
 }
 
 /// Logger implementation that forwards logs to the [`tracing`] facade.
@@ -16,6 +28,32 @@ impl Logger for TracingLogger {
     fn log(&self, message: &str) {
         tracing::info!(target: "app", "{}", message);
     }
+
+    fn error(&self, kind: &str, fields: &[(&'static str, String)], error: &dyn std::error::Error) {
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        let source = error.source().map(ToString::to_string);
+        tracing::error!(
+            target: "app",
+            kind,
+            path = field("path"),
+            pattern = field("pattern"),
+            template = field("template"),
+            span = field("span"),
+            from = field("from"),
+            to = field("to"),
+            destination = field("destination"),
+            sources = field("sources"),
+            label = field("label"),
+            io_kind = field("io_kind"),
+            source,
+            "{error}"
+        );
+    }
 }
 
 /// Writer that stores formatted log lines in memory so they can be displayed
@@ -71,10 +109,11 @@ pub fn init_tracing(level: tracing_subscriber::filter::LevelFilter) -> MemoryWri
         .with_writer(writer.clone())
         .with_ansi(true);
 
+    // `app` is the target used by [`TracingLogger`] for application events.
+    let level = level.to_string().to_lowercase();
     let filter = EnvFilter::new(format!(
-        "{}={}",
+        "{}={level},app={level}",
         env!("CARGO_PKG_NAME").replace('-', "_"),
-        level.to_string().to_lowercase()
     ));
 
     let subscriber = Registry::default().with(filter).with(layer);
@@ -95,6 +134,29 @@ mod tests {
         assert_eq!(writer.logs(), vec!["first", "second"]);
     }
 
+    #[test]
+    fn tracing_logger_attaches_fields() {
+        let writer = MemoryWriter::default();
+        let subscriber = Registry::default().with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer.clone())
+                .with_ansi(false),
+        );
+        let error = io::Error::from(io::ErrorKind::NotFound);
+        tracing::subscriber::with_default(subscriber, || {
+            TracingLogger.error(
+                "move",
+                &[("from", "./a".into()), ("io_kind", "NotFound".into())],
+                &error,
+            );
+        });
+        let line = writer.logs().join("\n");
+        assert!(line.contains("kind=\"move\""), "{line}");
+        assert!(line.contains("from=\"./a\""), "{line}");
+        assert!(line.contains("io_kind=\"NotFound\""), "{line}");
+        assert!(!line.contains("pattern="), "{line}");
+    }
+
     #[test]
     fn make_writer_produces_shared_buffer() {
         let writer = MemoryWriter::default();
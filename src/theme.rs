@@ -1,5 +1,22 @@
 use eframe::egui::{self, Color32, FontFamily, FontId, TextStyle, Visuals};
 use eframe::egui::{Context, Style};
+use serde::{Deserialize, Serialize};
+
+/// Catppuccin flavor the interface is drawn in, kept with the session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Mocha,
+}
+
+impl Theme {
+    pub fn visuals(self) -> Visuals {
+        match self {
+            Theme::Mocha => catppuccin_visuals(),
+        }
+    }
+}
 
 /// Catppuccin Mocha inspired visuals.
 pub fn catppuccin_visuals() -> Visuals {
@@ -16,9 +33,9 @@ pub fn catppuccin_visuals() -> Visuals {
     v
 }
 
-/// Apply Catppuccin visuals and font styling to the given [`Context`].
-pub fn apply_catppuccin(ctx: &Context) {
-    ctx.set_visuals(catppuccin_visuals());
+/// Apply Catppuccin visuals of `theme` and font styling to the given [`Context`].
+pub fn apply_catppuccin(ctx: &Context, theme: Theme) {
+    ctx.set_visuals(theme.visuals());
 
     let mut style: Style = (*ctx.style()).clone();
     style.spacing.item_spacing = egui::vec2(10.0, 8.0);
@@ -58,7 +75,7 @@ mod tests {
     #[test]
     fn apply_catppuccin_sets_heading_size() {
         let ctx = Context::default();
-        apply_catppuccin(&ctx);
+        apply_catppuccin(&ctx, Theme::Mocha);
         let style = ctx.style();
         let heading = style.text_styles.get(&TextStyle::Heading).unwrap();
         assert_eq!(heading.size, 24.0);
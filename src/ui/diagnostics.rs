@@ -0,0 +1,220 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+use eframe::egui::{self, Color32, Context, DragValue, RichText, Ui};
+
+use super::ERROR_COLOR;
+use crate::application::{AppError, BENCHMARK_BUDGET, Diagnostics, Renamer, engine_name};
+use crate::domain::{Engine, RegexLimits, Rule};
+
+const GOOD_COLOR: Color32 = Color32::from_rgb(166, 227, 161); // green
+const WARN_COLOR: Color32 = Color32::from_rgb(249, 226, 175); // yellow
+
+/// How often a running diagnosis is checked for its report.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Report = Result<Diagnostics, AppError>;
+
+/// Window reporting compile size, engine choice and match speed of a rule.
+pub struct DiagnosticsWindow {
+    /// Index of the rule being diagnosed.
+    pub rule: usize,
+    /// Last report, cleared whenever the limits change.
+    report: Option<Report>,
+    /// Receives the report of the diagnosis in progress.
+    running: Option<Receiver<Report>>,
+}
+
+impl DiagnosticsWindow {
+    pub fn new(rule: usize) -> Self {
+        Self {
+            rule,
+            report: None,
+            running: None,
+        }
+    }
+
+    /// Draws the window for `rule`. Returns `false` once the user has closed it.
+    pub fn show(&mut self, ctx: &Context, renamer: &Renamer, rule: &mut Rule) -> bool {
+        if let Some(receiver) = &self.running {
+            match receiver.try_recv() {
+                Ok(report) => {
+                    self.report = Some(report);
+                    self.running = None;
+                }
+                Err(TryRecvError::Empty) => ctx.request_repaint_after(POLL_INTERVAL),
+                Err(TryRecvError::Disconnected) => self.running = None,
+            }
+        }
+        let mut open = true;
+        egui::Window::new(format!("Diagnostics · Rule #{}", self.rule + 1))
+            .id(egui::Id::new("diagnostics_window"))
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.monospace(&rule.from);
+                ui.separator();
+                if rule.engine != Engine::Regex {
+                    ui.weak(format!(
+                        "Diagnostics inspect the regex crate's engines; this rule uses {}.",
+                        engine_name(rule.engine)
+                    ));
+                    return;
+                }
+                if limits_ui(ui, &mut rule.limits) {
+                    self.report = None;
+                    self.running = None;
+                }
+                ui.horizontal(|ui| {
+                    let button = egui::Button::new("▶ Run diagnostics");
+                    if ui.add_enabled(self.running.is_none(), button).clicked() {
+                        self.report = None;
+                        self.running = Some(diagnose(renamer, rule));
+                    }
+                    if self.running.is_some() {
+                        ui.spinner();
+                    }
+                });
+                ui.separator();
+                match &self.report {
+                    None if self.running.is_some() => {
+                        ui.weak("Walking the tree and benchmarking the pattern…");
+                    }
+                    None => {
+                        ui.weak(format!(
+                            "Compiles the pattern and matches it against every path in the \
+                             tree for {} ms.",
+                            BENCHMARK_BUDGET.as_millis()
+                        ));
+                    }
+                    Some(Ok(report)) => report_ui(ui, report),
+                    Some(Err(e)) => {
+                        ui.label(RichText::new(e.to_string()).color(ERROR_COLOR));
+                    }
+                }
+            });
+        open
+    }
+}
+
+/// Starts diagnosing `rule` on a background thread, so the walk and the
+/// benchmark do not freeze the window. The web build has no threads and
+/// runs it in place.
+fn diagnose(renamer: &Renamer, rule: &Rule) -> Receiver<Report> {
+    let (sender, receiver) = mpsc::channel();
+    let (renamer, rule) = (renamer.clone(), rule.clone());
+    // The window may have been closed by the time the report is ready.
+    let job = move || {
+        let _ = sender.send(renamer.diagnose(&rule));
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(job);
+    #[cfg(target_arch = "wasm32")]
+    job();
+    receiver
+}
+
+/// Edits the compile limits in KiB. Returns `true` when they changed.
+fn limits_ui(ui: &mut Ui, limits: &mut RegexLimits) -> bool {
+    let before = *limits;
+    egui::Grid::new("diagnostics_limits")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("size_limit")
+                .on_hover_text("Maximum size of the compiled program");
+            kib_field(ui, &mut limits.size_limit);
+            ui.end_row();
+            ui.label("dfa_size_limit")
+                .on_hover_text("Cache capacity of the lazy DFA");
+            kib_field(ui, &mut limits.dfa_size_limit);
+            ui.end_row();
+        });
+    if ui
+        .add_enabled(
+            *limits != RegexLimits::default(),
+            egui::Button::new("Reset"),
+        )
+        .on_hover_text("Restore the regex crate defaults")
+        .clicked()
+    {
+        *limits = RegexLimits::default();
+    }
+    *limits != before
+}
+
+fn kib_field(ui: &mut Ui, bytes: &mut usize) {
+    let mut kib = *bytes / 1024;
+    if ui
+        .add(DragValue::new(&mut kib).range(1..=1 << 20).suffix(" KiB"))
+        .changed()
+    {
+        *bytes = kib * 1024;
+    }
+}
+
+fn report_ui(ui: &mut Ui, report: &Diagnostics) {
+    let benchmark = &report.benchmark;
+    egui::Grid::new("diagnostics_report")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Compile time");
+            ui.monospace(format!("{:.2?}", report.compile_time));
+            ui.end_row();
+            ui.label("NFA states");
+            ui.monospace(report.nfa_states.to_string());
+            ui.end_row();
+            ui.label("Regex memory");
+            ui.monospace(format_bytes(report.regex_memory));
+            ui.end_row();
+            ui.label("Search cache");
+            ui.monospace(format_bytes(report.cache_memory));
+            ui.end_row();
+            ui.label("Lazy DFA");
+            match &report.lazy_dfa_error {
+                None if report.dfa_fallbacks == 0 => {
+                    ui.colored_label(GOOD_COLOR, "used for every path");
+                }
+                None => {
+                    ui.colored_label(
+                        WARN_COLOR,
+                        format!(
+                            "gave up on {} of {} paths; those fall back to the PikeVM",
+                            report.dfa_fallbacks, benchmark.paths
+                        ),
+                    );
+                }
+                Some(e) => {
+                    ui.colored_label(WARN_COLOR, "unavailable; every search uses the PikeVM")
+                        .on_hover_text(e);
+                }
+            }
+            ui.end_row();
+            ui.label("One-pass DFA");
+            ui.label(if report.one_pass {
+                "resolves capture groups"
+            } else {
+                "not applicable"
+            });
+            ui.end_row();
+            ui.label("Throughput");
+            ui.monospace(format!(
+                "{:.0} searches/s · {:.0} matches/s",
+                benchmark.searches_per_second(),
+                benchmark.matches_per_second()
+            ));
+            ui.end_row();
+        });
+    ui.weak(format!(
+        "{} of {} paths matched; {} rounds in {:.0?}",
+        benchmark.matched, benchmark.paths, benchmark.rounds, benchmark.elapsed
+    ));
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MiB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KiB", b as f64 / (1 << 10) as f64),
+        b => format!("{b} B"),
+    }
+}
@@ -0,0 +1,71 @@
+use eframe::egui::{
+    self, Color32, FontId, RichText, Ui,
+    text::{LayoutJob, TextFormat},
+};
+
+use super::ERROR_COLOR;
+use crate::application::{ContentPreview, display_path};
+
+const ADDED_COLOR: Color32 = Color32::from_rgb(166, 227, 161); // green
+const REMOVED_COLOR: Color32 = Color32::from_rgb(243, 139, 168); // red
+const HUNK_COLOR: Color32 = Color32::from_rgb(137, 180, 250); // blue
+const HEADER_COLOR: Color32 = Color32::from_rgb(127, 132, 156); // overlay1
+
+/// Lists the files a content rule would rewrite, each with a collapsible
+/// unified diff, followed by the files it skipped.
+pub fn show_content_preview(ui: &mut Ui, preview: &ContentPreview) {
+    ui.label(format!(
+        "{} file(s) would change · {} skipped · {} unreadable",
+        preview.changes.len(),
+        preview.skipped.len(),
+        preview.unreadable.len()
+    ));
+    for change in &preview.changes {
+        let title = format!(
+            "{} · {} replacement(s) · {}",
+            display_path(&change.path),
+            change.replacements,
+            change.encoding.name()
+        );
+        egui::CollapsingHeader::new(RichText::new(title).monospace())
+            .id_salt(&change.path)
+            .show(ui, |ui| {
+                let default_color = ui.visuals().text_color();
+                ui.label(diff_job(&change.diff(), default_color));
+            });
+    }
+    for (path, reason) in &preview.skipped {
+        ui.weak(format!(
+            "⏭ {} ({})",
+            display_path(path),
+            reason.description()
+        ));
+    }
+    for (path, message) in &preview.unreadable {
+        ui.label(RichText::new(format!("✖ {}: {message}", display_path(path))).color(ERROR_COLOR));
+    }
+}
+
+/// Colors a unified diff line by line.
+fn diff_job(diff: &str, default_color: Color32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for line in diff.split_inclusive('\n') {
+        let color = if line.starts_with("+++") || line.starts_with("---") {
+            HEADER_COLOR
+        } else if line.starts_with('+') {
+            ADDED_COLOR
+        } else if line.starts_with('-') {
+            REMOVED_COLOR
+        } else if line.starts_with("@@") {
+            HUNK_COLOR
+        } else {
+            default_color
+        };
+        job.append(
+            line,
+            0.0,
+            TextFormat::simple(FontId::monospace(13.0), color),
+        );
+    }
+    job
+}
@@ -0,0 +1,53 @@
+use std::hash::Hash;
+
+use eframe::egui::{self, Color32, Ui};
+
+use crate::application::{ENGINES, SYNTAX_FEATURES, engine_available, engine_name};
+use crate::domain::Engine;
+
+const SUPPORTED_COLOR: Color32 = Color32::from_rgb(166, 227, 161); // green
+const UNSUPPORTED_COLOR: Color32 = Color32::from_rgb(127, 132, 156); // overlay1
+
+/// Combo box choosing a rule's engine among those compiled into this build.
+/// Returns `true` when the selection changed.
+pub fn engine_selector(ui: &mut Ui, id_salt: impl Hash, engine: &mut Engine) -> bool {
+    let before = *engine;
+    egui::ComboBox::from_id_salt(id_salt)
+        .width(110.0)
+        .selected_text(engine_name(*engine))
+        .show_ui(ui, |ui| {
+            for candidate in ENGINES.into_iter().filter(|e| engine_available(*e)) {
+                ui.selectable_value(engine, candidate, engine_name(candidate));
+            }
+        })
+        .response
+        .on_hover_ui(syntax_table);
+    *engine != before
+}
+
+/// Table of the syntax features each engine supports.
+pub fn syntax_table(ui: &mut Ui) {
+    egui::Grid::new("engine_syntax")
+        .num_columns(ENGINES.len() + 2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            ui.label("");
+            for engine in ENGINES {
+                ui.strong(engine_name(engine));
+            }
+            ui.end_row();
+            for feature in &SYNTAX_FEATURES {
+                ui.label(feature.name);
+                ui.weak(egui::RichText::new(feature.example).monospace());
+                for supported in feature.supported {
+                    if supported {
+                        ui.colored_label(SUPPORTED_COLOR, "✔");
+                    } else {
+                        ui.colored_label(UNSUPPORTED_COLOR, "✖");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
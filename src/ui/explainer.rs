@@ -0,0 +1,65 @@
+use std::ops::Range;
+
+use eframe::egui::{self, Id, RichText, Ui, collapsing_header::CollapsingState};
+
+use super::ERROR_COLOR;
+use crate::application::{ExplainNode, explain};
+
+/// Draws the syntax tree of `pattern` as collapsible nodes. Clicking a node
+/// stores its span in `highlight` so the pattern field can mark it.
+pub fn show_explainer(ui: &mut Ui, pattern: &str, highlight: &mut Option<Range<usize>>) {
+    if pattern.is_empty() {
+        ui.weak("The pattern is empty; it matches every path.");
+        return;
+    }
+    match explain(pattern) {
+        Ok(tree) => {
+            egui::ScrollArea::vertical()
+                .id_salt("explainer")
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    node_ui(ui, pattern, &tree, Id::new("explain_node"), 0, highlight);
+                });
+        }
+        Err(e) => {
+            ui.label(RichText::new(e.to_string()).color(ERROR_COLOR));
+        }
+    }
+}
+
+fn node_ui(
+    ui: &mut Ui,
+    pattern: &str,
+    node: &ExplainNode,
+    id: Id,
+    depth: usize,
+    highlight: &mut Option<Range<usize>>,
+) {
+    let snippet = pattern.get(node.span.clone()).unwrap_or_default();
+    let selected = highlight.as_ref() == Some(&node.span);
+    let mut header = |ui: &mut Ui| {
+        let clicked = ui
+            .selectable_label(selected, RichText::new(snippet).monospace())
+            .clicked();
+        ui.label(&node.description);
+        if clicked {
+            *highlight = (!selected).then(|| node.span.clone());
+        }
+    };
+
+    if node.children.is_empty() {
+        ui.horizontal(|ui| {
+            // Align leaves with the headers of their collapsible siblings.
+            ui.add_space(ui.spacing().indent);
+            header(ui);
+        });
+        return;
+    }
+    CollapsingState::load_with_default_open(ui.ctx(), id, depth < 3)
+        .show_header(ui, header)
+        .body(|ui| {
+            for (i, child) in node.children.iter().enumerate() {
+                node_ui(ui, pattern, child, id.with(i), depth + 1, highlight);
+            }
+        });
+}
@@ -0,0 +1,58 @@
+use std::ops::Range;
+
+use eframe::egui::{Color32, Response, RichText, TextEdit, Ui};
+
+use super::ERROR_COLOR;
+use super::highlight::{SpanStyle, span_job};
+use crate::application::AppError;
+
+/// Background of a field whose contents fail validation.
+const INVALID_FILL: Color32 = Color32::from_rgb(84, 44, 58);
+
+/// Fill behind a span selected elsewhere, e.g. in the explainer (lavender).
+const HIGHLIGHT_FILL: Color32 = Color32::from_rgb(180, 190, 254);
+
+/// Single-line monospace editor showing the validation `error` of its
+/// contents. An invalid value tints the field, underlines the offending span
+/// and shows the error message when hovered. `highlight` marks an additional
+/// span.
+pub fn validated_field(
+    ui: &mut Ui,
+    text: &mut String,
+    width: f32,
+    hint: &str,
+    highlight: Option<Range<usize>>,
+    error: Option<&AppError>,
+) -> Response {
+    let default_color = ui.visuals().text_color();
+    let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+        let highlight = highlight
+            .clone()
+            .map(|span| (span, SpanStyle::Fill(HIGHLIGHT_FILL)));
+        let error = error
+            .and_then(AppError::span)
+            .map(|span| (span, SpanStyle::Error(ERROR_COLOR)));
+        let spans: Vec<_> = highlight.into_iter().chain(error).collect();
+        let mut job = span_job(text, default_color, &spans);
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|f| f.layout_job(job))
+    };
+
+    let mut edit = TextEdit::singleline(text)
+        .hint_text(hint)
+        .layouter(&mut layouter);
+    if error.is_some() {
+        edit = edit.background_color(INVALID_FILL);
+    }
+    let response = ui.add_sized([width, 0.0], edit);
+
+    match error {
+        Some(error) => response.on_hover_ui(|ui| {
+            ui.label(RichText::new(error.to_string()).color(ERROR_COLOR));
+            if let Some(pointer) = error.pointer() {
+                ui.label(RichText::new(pointer).monospace());
+            }
+        }),
+        None => response,
+    }
+}
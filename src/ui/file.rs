@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Context, Key, RichText};
+
+use super::ERROR_COLOR;
+
+/// What the rule set prompt does with the entered path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileAction {
+    Open,
+    SaveAs,
+}
+
+/// State of a [`FileWindow`] after a frame.
+pub enum FilePrompt {
+    Pending,
+    Closed,
+    Confirmed(PathBuf),
+}
+
+/// Small window asking for the path of a rule set file to open or save.
+pub struct FileWindow {
+    pub action: FileAction,
+    path: String,
+    /// Why the last attempt failed, shown until the path is confirmed again.
+    error: Option<String>,
+    /// Existing file the user was warned about; confirming it again
+    /// replaces it.
+    overwrite: Option<PathBuf>,
+}
+
+impl FileWindow {
+    pub fn new(action: FileAction, path: String) -> Self {
+        Self {
+            action,
+            path,
+            error: None,
+            overwrite: None,
+        }
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn show(&mut self, ctx: &Context) -> FilePrompt {
+        let path = PathBuf::from(self.path.trim());
+        let replacing = self.overwrite.as_ref() == Some(&path);
+        let (title, button) = match self.action {
+            FileAction::Open => ("Open rule set", "📂 Open"),
+            FileAction::SaveAs if replacing => ("Save rule set as", "⚠ Replace"),
+            FileAction::SaveAs => ("Save rule set as", "💾 Save"),
+        };
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new(title)
+            .id(egui::Id::new("rule_file_window"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Path of a .toml or .json file:");
+                ui.horizontal(|ui| {
+                    let field = ui.add(
+                        egui::TextEdit::singleline(&mut self.path)
+                            .desired_width(280.0)
+                            .hint_text("rules.toml"),
+                    );
+                    let entered = field.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                    let clicked = ui
+                        .add_enabled(!self.path.trim().is_empty(), egui::Button::new(button))
+                        .clicked();
+                    confirmed = (entered || clicked) && !self.path.trim().is_empty();
+                });
+                if replacing {
+                    ui.label(
+                        RichText::new("This file already exists. Replace it?").color(ERROR_COLOR),
+                    );
+                }
+                if let Some(error) = &self.error {
+                    ui.label(RichText::new(error).color(ERROR_COLOR));
+                }
+            });
+        if !open {
+            FilePrompt::Closed
+        } else if confirmed && self.action == FileAction::SaveAs && !replacing && exists(&path) {
+            self.overwrite = Some(path);
+            FilePrompt::Pending
+        } else if confirmed {
+            self.error = None;
+            FilePrompt::Confirmed(path)
+        } else {
+            FilePrompt::Pending
+        }
+    }
+}
+
+/// Whether `path` names an existing file. The web build has no files.
+fn exists(path: &Path) -> bool {
+    cfg!(not(target_arch = "wasm32")) && path.exists()
+}
@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use eframe::egui::{
+    Color32, FontId, Stroke,
+    text::{LayoutJob, TextFormat},
+};
+
+/// How a highlighted byte range is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpanStyle {
+    /// Underline in the given color and recolor the text.
+    Error(Color32),
+    /// Fill the background and draw the text dark for contrast.
+    Fill(Color32),
+}
+
+/// Dark text drawn on top of [`SpanStyle::Fill`] backgrounds (Catppuccin base).
+pub const FILL_TEXT: Color32 = Color32::from_rgb(30, 30, 46);
+
+/// Catppuccin accents cycled through for capture groups 1, 2, …
+const GROUP_PALETTE: [Color32; 8] = [
+    Color32::from_rgb(250, 179, 135), // peach
+    Color32::from_rgb(166, 227, 161), // green
+    Color32::from_rgb(137, 180, 250), // blue
+    Color32::from_rgb(203, 166, 247), // mauve
+    Color32::from_rgb(249, 226, 175), // yellow
+    Color32::from_rgb(148, 226, 213), // teal
+    Color32::from_rgb(245, 194, 231), // pink
+    Color32::from_rgb(137, 220, 235), // sky
+];
+
+/// Color for capture group `index`; group 0 (the whole match) is neutral.
+pub fn group_color(index: usize) -> Color32 {
+    match index {
+        0 => Color32::from_rgb(127, 132, 156), // overlay1
+        i => GROUP_PALETTE[(i - 1) % GROUP_PALETTE.len()],
+    }
+}
+
+/// Highlights every participating capture group of a match in its
+/// [`group_color`]. Inner groups are drawn over the groups enclosing them.
+pub fn captures_job(
+    text: &str,
+    default_color: Color32,
+    groups: &[Option<Range<usize>>],
+) -> LayoutJob {
+    let spans: Vec<_> = groups
+        .iter()
+        .enumerate()
+        .filter_map(|(i, range)| Some((range.clone()?, SpanStyle::Fill(group_color(i)))))
+        .collect();
+    span_job(text, default_color, &spans)
+}
+
+/// Lays out `text` in monospace and applies `spans` on top of it. Later spans
+/// win where ranges overlap; ranges are clamped to the text and widened to
+/// char boundaries, and empty ranges cover the following (or last) char so
+/// that they remain visible.
+pub fn span_job(
+    text: &str,
+    default_color: Color32,
+    spans: &[(Range<usize>, SpanStyle)],
+) -> LayoutJob {
+    let base = TextFormat::simple(FontId::monospace(14.0), default_color);
+    let spans: Vec<_> = spans
+        .iter()
+        .filter_map(|(range, style)| Some((visible_range(text, range.clone())?, *style)))
+        .collect();
+
+    let mut bounds: Vec<usize> = vec![0, text.len()];
+    for (range, _) in &spans {
+        bounds.push(range.start);
+        bounds.push(range.end);
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut job = LayoutJob::default();
+    for pair in bounds.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let mut format = base.clone();
+        for (range, style) in &spans {
+            if range.start <= start && end <= range.end {
+                match style {
+                    SpanStyle::Error(color) => {
+                        format.color = *color;
+                        format.underline = Stroke::new(1.5, *color);
+                    }
+                    SpanStyle::Fill(color) => {
+                        format.color = FILL_TEXT;
+                        format.background = *color;
+                    }
+                }
+            }
+        }
+        job.append(&text[start..end], 0.0, format);
+    }
+    job
+}
+
+fn visible_range(text: &str, range: Range<usize>) -> Option<Range<usize>> {
+    if text.is_empty() {
+        return None;
+    }
+    let mut start = range.start.min(text.len());
+    let mut end = range.end.clamp(start, text.len());
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    if start == end {
+        match text[start..].chars().next() {
+            Some(c) => end = start + c.len_utf8(),
+            None => start = text.char_indices().next_back()?.0,
+        }
+    }
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color32 = Color32::RED;
+
+    #[test]
+    fn splits_text_around_error_span() {
+        let job = span_job("ab(cd", Color32::WHITE, &[(2..3, SpanStyle::Error(RED))]);
+        assert_eq!(job.text, "ab(cd");
+        assert_eq!(job.sections.len(), 3);
+        assert_eq!(job.sections[1].byte_range, 2..3);
+        assert_eq!(job.sections[1].format.color, RED);
+        assert_eq!(job.sections[1].format.underline.color, RED);
+        assert_eq!(job.sections[2].format.color, Color32::WHITE);
+    }
+
+    #[test]
+    fn empty_span_at_end_marks_last_char() {
+        let job = span_job("a(", Color32::WHITE, &[(2..2, SpanStyle::Error(RED))]);
+        assert_eq!(job.sections.last().unwrap().byte_range, 1..2);
+        assert_eq!(job.sections.last().unwrap().format.color, RED);
+    }
+
+    #[test]
+    fn later_spans_override_earlier_ones() {
+        let blue = Color32::BLUE;
+        let job = span_job(
+            "abcd",
+            Color32::WHITE,
+            &[
+                (0..4, SpanStyle::Error(RED)),
+                (1..2, SpanStyle::Error(blue)),
+            ],
+        );
+        let colors: Vec<_> = job.sections.iter().map(|s| s.format.color).collect();
+        assert_eq!(colors, vec![RED, blue, RED]);
+    }
+
+    #[test]
+    fn captures_color_groups_by_index() {
+        let job = captures_job("ab-cd", Color32::WHITE, &[Some(0..5), Some(3..5), None]);
+        let backgrounds: Vec<_> = job.sections.iter().map(|s| s.format.background).collect();
+        assert_eq!(backgrounds, vec![group_color(0), group_color(1)]);
+        assert_eq!(job.sections[1].byte_range, 3..5);
+    }
+
+    #[test]
+    fn group_palette_cycles() {
+        assert_eq!(group_color(1), group_color(1 + GROUP_PALETTE.len()));
+        assert_ne!(group_color(1), group_color(2));
+    }
+
+    #[test]
+    fn spans_are_widened_to_char_boundaries() {
+        let job = span_job("é(", Color32::WHITE, &[(1..1, SpanStyle::Error(RED))]);
+        assert_eq!(job.sections[0].byte_range, 0..2);
+        assert_eq!(job.sections[0].format.color, RED);
+    }
+}
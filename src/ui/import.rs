@@ -0,0 +1,76 @@
+use eframe::egui::{self, Context, RichText};
+
+use super::ERROR_COLOR;
+use crate::application::{Import, import_commands};
+use crate::domain::Rule;
+
+/// State of an [`ImportWindow`] after a frame.
+pub enum ImportAction {
+    Pending,
+    Closed,
+    /// Rules translated from the pasted commands, to append to the table.
+    Insert(Vec<Rule>),
+}
+
+/// Window translating pasted Perl `rename` and `mmv` commands into rules,
+/// previewed as they are typed.
+#[derive(Default)]
+pub struct ImportWindow {
+    commands: String,
+    import: Import,
+}
+
+impl ImportWindow {
+    pub fn show(&mut self, ctx: &Context) -> ImportAction {
+        let mut open = true;
+        let mut inserted = false;
+        egui::Window::new("Import rename and mmv commands")
+            .open(&mut open)
+            .default_size([640.0, 400.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("One command per line:");
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut self.commands)
+                        .code_editor()
+                        .desired_rows(6)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("rename 's/foo(\\d+)/bar$1/g' *\nmmv '*.jpeg' '#1.jpg'"),
+                );
+                if response.changed() {
+                    self.import = import_commands(&self.commands);
+                }
+
+                egui::Grid::new("import_preview")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for rule in &self.import.rules {
+                            ui.monospace(&rule.from);
+                            ui.label("→");
+                            let global = if rule.replace_all { "  (g)" } else { "" };
+                            ui.monospace(format!("{}{global}", rule.to));
+                            ui.end_row();
+                        }
+                    });
+                for warning in &self.import.warnings {
+                    ui.label(RichText::new(format!("⚠ {warning}")).color(ERROR_COLOR));
+                }
+
+                let count = self.import.rules.len();
+                inserted = ui
+                    .add_enabled(
+                        count > 0,
+                        egui::Button::new(format!("➕ Add {count} rules")),
+                    )
+                    .clicked();
+            });
+        if !open {
+            ImportAction::Closed
+        } else if inserted {
+            ImportAction::Insert(std::mem::take(&mut self.import.rules))
+        } else {
+            ImportAction::Pending
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Reusable GUI components shown by [`crate::RegexApp`].
+
+use eframe::egui::Color32;
+
+pub mod diagnostics;
+pub mod diff;
+pub mod engine;
+pub mod explainer;
+pub mod field;
+pub mod file;
+pub mod highlight;
+pub mod import;
+pub mod playground;
+pub mod railroad;
+pub mod recipes;
+pub mod report;
+pub mod rules;
+pub mod samples;
+pub mod search;
+pub mod transcode;
+
+/// Color used for inline errors (Catppuccin red).
+pub const ERROR_COLOR: Color32 = Color32::from_rgb(243, 139, 168);
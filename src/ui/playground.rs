@@ -0,0 +1,323 @@
+use std::ops::Range;
+
+use eframe::egui::{self, Color32, RichText, TextEdit, Ui};
+use egui_extras::{Column, TableBuilder};
+use regex::Regex;
+
+use super::field::validated_field;
+use super::highlight::{SpanStyle, group_color, span_job};
+use crate::application::{ApiCall, MATCH_LIMIT, PatternFlags, match_groups, validate_template};
+use crate::domain::RuleMode;
+
+/// Free-text regex tester, independent of the rename rules.
+pub struct Playground {
+    pattern: String,
+    flags: PatternFlags,
+    input: String,
+    template: String,
+    /// Limit passed to `splitn` in the API explorer.
+    splitn_limit: usize,
+    /// Extra `RegexSet` patterns, one per line.
+    set_patterns: String,
+}
+
+impl Default for Playground {
+    fn default() -> Self {
+        Self {
+            pattern: r"(?P<year>\d{4})-(?P<month>\d{2})-(\d{2})".to_string(),
+            flags: PatternFlags::default(),
+            input: "Invoices from 2024-01-15 and 2024-02-03.\nNo date here.\n".to_string(),
+            template: "$month/$year".to_string(),
+            splitn_limit: 2,
+            set_patterns: "\\d+\nInvoice".to_string(),
+        }
+    }
+}
+
+impl Playground {
+    pub fn show(&mut self, ui: &mut Ui) {
+        egui::ScrollArea::vertical()
+            .id_salt("playground")
+            .auto_shrink([false, false])
+            .show(ui, |ui| self.contents(ui));
+    }
+
+    fn contents(&mut self, ui: &mut Ui) {
+        let flags = self.flags;
+        ui.horizontal(|ui| {
+            ui.strong("Pattern");
+            let width = ui.available_width();
+            let error = flags.compile(&self.pattern).err();
+            validated_field(ui, &mut self.pattern, width, "regex", None, error.as_ref());
+        });
+        self.flags_ui(ui);
+        let regex = self.flags.compile(&self.pattern).ok();
+
+        ui.add_space(4.0);
+        ui.strong("Input");
+        let default_color = ui.visuals().text_color();
+        let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+            let spans = regex
+                .as_ref()
+                .map(|re| match_spans(&match_groups(re, text)))
+                .unwrap_or_default();
+            let mut job = span_job(text, default_color, &spans);
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|f| f.layout_job(job))
+        };
+        egui::ScrollArea::vertical()
+            .id_salt("playground_input")
+            .max_height(180.0)
+            .show(ui, |ui| {
+                ui.add(
+                    TextEdit::multiline(&mut self.input)
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(6)
+                        .layouter(&mut layouter),
+                );
+            });
+
+        let Some(regex) = regex else {
+            return;
+        };
+        let matches = match_groups(&regex, &self.input);
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.strong("Matches");
+            if matches.len() == MATCH_LIMIT {
+                ui.weak(format!("showing the first {MATCH_LIMIT}"));
+            } else {
+                ui.weak(matches.len().to_string());
+            }
+        });
+        self.match_table(ui, &regex, &matches);
+
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.strong("Replace with");
+            let width = ui.available_width();
+            // The playground replaces inside text, like a content rule.
+            let error = validate_template(&self.template, &regex, RuleMode::Content).err();
+            validated_field(
+                ui,
+                &mut self.template,
+                width,
+                "template",
+                None,
+                error.as_ref(),
+            );
+        });
+        if let Ok(template) = validate_template(&self.template, &regex, RuleMode::Content) {
+            let mut preview = template
+                .replace_all(&regex, &self.input)
+                .unwrap_or_else(|e| e.to_string());
+            egui::ScrollArea::vertical()
+                .id_salt("playground_replacement")
+                .max_height(180.0)
+                .show(ui, |ui| {
+                    ui.add(
+                        TextEdit::multiline(&mut preview)
+                            .interactive(false)
+                            .code_editor()
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(4),
+                    );
+                });
+        }
+
+        ui.add_space(4.0);
+        egui::CollapsingHeader::new(RichText::new("regex crate API").strong())
+            .default_open(true)
+            .show(ui, |ui| self.api_ui(ui, &regex));
+    }
+
+    fn api_ui(&mut self, ui: &mut Ui, regex: &Regex) {
+        ui.horizontal(|ui| {
+            ui.label("splitn limit:");
+            ui.add(egui::DragValue::new(&mut self.splitn_limit).range(0..=100));
+        });
+        ui.label("RegexSet patterns after the pattern above, one per line:");
+        ui.add(
+            TextEdit::multiline(&mut self.set_patterns)
+                .code_editor()
+                .desired_width(f32::INFINITY)
+                .desired_rows(2),
+        );
+
+        let set_patterns: Vec<&str> = self
+            .set_patterns
+            .lines()
+            .filter(|l| !l.is_empty())
+            .collect();
+        let call = ApiCall {
+            pattern: &self.pattern,
+            flags: self.flags,
+            haystack: &self.input,
+            replacement: &self.template,
+            limit: self.splitn_limit,
+            set_patterns: &set_patterns,
+        };
+        let results = call.run(regex);
+        let limit = self.splitn_limit;
+        let set_result = match &results.set_matches {
+            Ok(indices) => format!("{indices:?}"),
+            Err(e) => e.to_string(),
+        };
+        let rows = [
+            ("re.is_match(h)".to_string(), results.is_match.to_string()),
+            (
+                "re.find_iter(h)".to_string(),
+                results
+                    .find_iter
+                    .iter()
+                    .map(|(range, text)| format!("{range:?} {text:?}"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            (
+                "re.captures_iter(h)".to_string(),
+                format!("{:?}", results.captures_iter),
+            ),
+            ("re.split(h)".to_string(), format!("{:?}", results.split)),
+            (
+                format!("re.splitn(h, {limit})"),
+                format!("{:?}", results.splitn),
+            ),
+            (
+                "re.replace(h, r)".to_string(),
+                format!("{:?}", results.replace),
+            ),
+            (
+                "re.replace_all(h, r)".to_string(),
+                format!("{:?}", results.replace_all),
+            ),
+            ("RegexSet::matches(h)".to_string(), set_result),
+        ];
+        egui::Grid::new("api_results")
+            .num_columns(2)
+            .striped(true)
+            .spacing([16.0, 6.0])
+            .show(ui, |ui| {
+                for (method, result) in rows {
+                    ui.monospace(method);
+                    ui.add(egui::Label::new(RichText::new(result).monospace()).wrap());
+                    ui.end_row();
+                }
+            });
+
+        ui.add_space(4.0);
+        let snippet = call.snippet();
+        ui.horizontal(|ui| {
+            ui.strong("Rust snippet");
+            if ui.button("📋 Copy").clicked() {
+                ui.ctx().copy_text(snippet.clone());
+            }
+        });
+        ui.add(
+            TextEdit::multiline(&mut snippet.as_str())
+                .code_editor()
+                .desired_width(f32::INFINITY),
+        );
+    }
+
+    fn flags_ui(&mut self, ui: &mut Ui) {
+        let flags = &mut self.flags;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Flags:");
+            for (value, label, hint) in [
+                (&mut flags.case_insensitive, "i", "Case-insensitive"),
+                (
+                    &mut flags.multi_line,
+                    "m",
+                    "^ and $ match at line boundaries",
+                ),
+                (&mut flags.dot_matches_new_line, "s", ". matches \\n"),
+                (
+                    &mut flags.ignore_whitespace,
+                    "x",
+                    "Ignore whitespace and # comments",
+                ),
+                (&mut flags.swap_greed, "U", "Swap the meaning of x* and x*?"),
+                (&mut flags.unicode, "u", "Unicode-aware classes"),
+                (&mut flags.crlf, "R", "Treat \\r\\n as a line terminator"),
+            ] {
+                ui.checkbox(value, RichText::new(label).monospace())
+                    .on_hover_text(hint);
+            }
+        });
+    }
+
+    fn match_table(&self, ui: &mut Ui, regex: &Regex, matches: &[Vec<Option<Range<usize>>>]) {
+        let names: Vec<Option<&str>> = regex.capture_names().collect();
+        // One row per participating group of every match.
+        let rows: Vec<(usize, usize, Range<usize>)> = matches
+            .iter()
+            .enumerate()
+            .flat_map(|(m, groups)| {
+                groups
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(g, range)| Some((m, g, range.clone()?)))
+            })
+            .collect();
+
+        TableBuilder::new(ui)
+            .id_salt("playground_matches")
+            .striped(true)
+            .max_scroll_height(180.0)
+            .column(Column::auto()) // match
+            .column(Column::auto()) // group
+            .column(Column::auto()) // start
+            .column(Column::auto()) // end
+            .column(Column::remainder()) // text
+            .header(20.0, |mut header| {
+                for title in ["Match", "Group", "Start", "End", "Text"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(20.0, rows.len(), |mut row| {
+                    let (m, g, range) = rows[row.index()].clone();
+                    row.col(|ui| {
+                        ui.label((m + 1).to_string());
+                    });
+                    row.col(|ui| {
+                        let label = match names.get(g).copied().flatten() {
+                            Some(name) => format!(" {g} {name} "),
+                            None => format!(" {g} "),
+                        };
+                        ui.label(
+                            RichText::new(label)
+                                .monospace()
+                                .color(Color32::from_rgb(30, 30, 46))
+                                .background_color(group_color(g)),
+                        );
+                    });
+                    row.col(|ui| {
+                        ui.monospace(range.start.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.monospace(range.end.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!("{:?}", &self.input[range]));
+                    });
+                });
+            });
+    }
+}
+
+/// Fills every non-empty group of every match with its group color.
+fn match_spans(matches: &[Vec<Option<Range<usize>>>]) -> Vec<(Range<usize>, SpanStyle)> {
+    matches
+        .iter()
+        .flat_map(|groups| {
+            groups.iter().enumerate().filter_map(|(g, range)| {
+                let range = range.clone().filter(|r| !r.is_empty())?;
+                Some((range, SpanStyle::Fill(group_color(g))))
+            })
+        })
+        .collect()
+}
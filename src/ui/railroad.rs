@@ -0,0 +1,206 @@
+use eframe::egui::{
+    self, Align2, Color32, Context, FontId, Pos2, Rect, RichText, Sense, Stroke, StrokeKind, Ui,
+    Vec2,
+};
+
+use super::ERROR_COLOR;
+use crate::application::{AppError, BoxKind, Diagram, Shape, railroad};
+
+const LITERAL_COLOR: Color32 = Color32::from_rgb(166, 227, 161); // green
+const CLASS_COLOR: Color32 = Color32::from_rgb(137, 180, 250); // blue
+const ASSERTION_COLOR: Color32 = Color32::from_rgb(249, 226, 175); // yellow
+const FRAME_COLOR: Color32 = Color32::from_rgb(127, 132, 156); // overlay1
+const BOX_FILL: Color32 = Color32::from_rgb(49, 50, 68); // surface0
+const SUCCESS_COLOR: Color32 = Color32::from_rgb(166, 227, 161); // green
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+
+/// Window drawing a rule's pattern as a railroad diagram.
+pub struct RailroadWindow {
+    /// Index of the rule whose pattern is drawn.
+    pub rule: usize,
+    zoom: f32,
+    /// Diagram of the pattern it was laid out from, kept until the pattern
+    /// changes.
+    diagram: Option<(String, Result<Diagram, AppError>)>,
+    /// Files cannot be written from the browser; the web build copies the
+    /// SVG instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    export_path: String,
+    /// Result of the last export, shown below the toolbar.
+    status: Option<Result<String, String>>,
+}
+
+impl RailroadWindow {
+    pub fn new(rule: usize) -> Self {
+        Self {
+            rule,
+            zoom: 1.0,
+            diagram: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_path: format!("rule-{}.svg", rule + 1),
+            status: None,
+        }
+    }
+
+    /// Draws the window for `pattern`. Returns `false` once the user has closed it.
+    pub fn show(&mut self, ctx: &Context, pattern: &str) -> bool {
+        let (drawn, diagram) = match self.diagram.take() {
+            Some((drawn, diagram)) if drawn == pattern => (drawn, diagram),
+            _ => (pattern.to_string(), railroad(pattern)),
+        };
+        let mut open = true;
+        egui::Window::new(format!("Railroad diagram · Rule #{}", self.rule + 1))
+            .id(egui::Id::new("railroad_window"))
+            .open(&mut open)
+            .default_size([720.0, 320.0])
+            .show(ctx, |ui| match &diagram {
+                Ok(diagram) => {
+                    self.toolbar(ui, diagram);
+                    ui.separator();
+                    egui::ScrollArea::both()
+                        .id_salt("railroad")
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| self.paint(ui, diagram));
+                }
+                Err(e) => {
+                    ui.label(RichText::new(e.to_string()).color(ERROR_COLOR));
+                }
+            });
+        self.diagram = Some((drawn, diagram));
+        open
+    }
+
+    fn toolbar(&mut self, ui: &mut Ui, diagram: &Diagram) {
+        ui.horizontal(|ui| {
+            ui.label("Zoom:");
+            ui.add(
+                egui::Slider::new(&mut self.zoom, MIN_ZOOM..=MAX_ZOOM)
+                    .custom_formatter(|z, _| format!("{:.0}%", z * 100.0)),
+            );
+            if ui.button("Reset").clicked() {
+                self.zoom = 1.0;
+            }
+            ui.separator();
+            #[cfg(not(target_arch = "wasm32"))]
+            self.export(ui, diagram);
+            if ui.button("📋 Copy SVG").clicked() {
+                ui.ctx().copy_text(diagram.to_svg());
+                self.status = Some(Ok("SVG copied to the clipboard".to_string()));
+            }
+        });
+        match &self.status {
+            Some(Ok(message)) => {
+                ui.label(RichText::new(message).color(SUCCESS_COLOR));
+            }
+            Some(Err(message)) => {
+                ui.label(RichText::new(message).color(ERROR_COLOR));
+            }
+            None => {}
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export(&mut self, ui: &mut Ui, diagram: &Diagram) {
+        ui.add(
+            egui::TextEdit::singleline(&mut self.export_path)
+                .desired_width(180.0)
+                .hint_text("diagram.svg"),
+        );
+        if ui.button("💾 Export SVG").clicked() {
+            self.status = Some(
+                std::fs::write(&self.export_path, diagram.to_svg())
+                    .map(|()| format!("Saved {}", self.export_path))
+                    .map_err(|e| format!("Could not save {}: {e}", self.export_path)),
+            );
+        }
+    }
+
+    fn paint(&mut self, ui: &mut Ui, diagram: &Diagram) {
+        let zoom = self.zoom;
+        let size = Vec2::new(diagram.width, diagram.height) * zoom;
+        let (response, painter) = ui.allocate_painter(size, Sense::hover());
+        if response.hovered() {
+            // Ctrl + scroll or pinch zooms the diagram.
+            let delta = ui.input(|i| i.zoom_delta());
+            if delta != 1.0 {
+                self.zoom = (self.zoom * delta).clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+        }
+
+        let origin = response.rect.min;
+        let at = |[x, y]: [f32; 2]| origin + Vec2::new(x, y) * zoom;
+        let text_color = ui.visuals().text_color();
+        let line = Stroke::new(1.5 * zoom, text_color);
+        let label_font = FontId::monospace(13.0 * zoom);
+        let caption_font = FontId::proportional(11.0 * zoom);
+
+        for shape in &diagram.shapes {
+            match shape {
+                Shape::Path(points) => {
+                    painter.line(points.iter().copied().map(at).collect(), line);
+                }
+                Shape::Box {
+                    min,
+                    max,
+                    label,
+                    kind,
+                } => {
+                    let rect = Rect::from_min_max(at(*min), at(*max));
+                    let (color, radius) = match kind {
+                        BoxKind::Literal => (LITERAL_COLOR, rect.height() / 2.0),
+                        BoxKind::Class => (CLASS_COLOR, 0.0),
+                        BoxKind::Assertion => (ASSERTION_COLOR, 4.0 * zoom),
+                    };
+                    painter.rect(
+                        rect,
+                        radius,
+                        BOX_FILL,
+                        Stroke::new(1.5 * zoom, color),
+                        StrokeKind::Middle,
+                    );
+                    painter.text(
+                        rect.center(),
+                        Align2::CENTER_CENTER,
+                        label,
+                        label_font.clone(),
+                        text_color,
+                    );
+                }
+                Shape::Frame { min, max, label } => {
+                    let rect = Rect::from_min_max(at(*min), at(*max));
+                    let outline: Vec<Pos2> = vec![
+                        rect.left_top(),
+                        rect.right_top(),
+                        rect.right_bottom(),
+                        rect.left_bottom(),
+                        rect.left_top(),
+                    ];
+                    painter.extend(egui::Shape::dashed_line(
+                        &outline,
+                        Stroke::new(zoom, FRAME_COLOR),
+                        4.0 * zoom,
+                        3.0 * zoom,
+                    ));
+                    painter.text(
+                        rect.left_top() + Vec2::new(8.0, 3.0) * zoom,
+                        Align2::LEFT_TOP,
+                        label,
+                        caption_font.clone(),
+                        FRAME_COLOR,
+                    );
+                }
+                Shape::Caption { center, text } => {
+                    painter.text(
+                        at(*center),
+                        Align2::CENTER_CENTER,
+                        text,
+                        caption_font.clone(),
+                        FRAME_COLOR,
+                    );
+                }
+            }
+        }
+    }
+}
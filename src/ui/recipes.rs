@@ -0,0 +1,205 @@
+use std::path::Path;
+
+use eframe::egui::{self, Context, RichText};
+
+use super::ERROR_COLOR;
+use crate::application::{Recipe, load_recipes};
+use crate::domain::Rule;
+
+/// State of a [`RecipeWindow`] after a frame.
+pub enum RecipeAction {
+    Pending,
+    Closed,
+    /// Rules of the recipe whose ➕ Insert button was pressed.
+    Insert(Vec<Rule>),
+}
+
+/// Browser of the built-in recipes and the team recipes of a shared
+/// directory, which can also save the current rules as a new recipe.
+pub struct RecipeWindow {
+    built_in: Vec<Recipe>,
+    team: Vec<Recipe>,
+    /// Files of the team directory that could not be read.
+    team_errors: Vec<String>,
+    name: String,
+    description: String,
+    /// Example paths of the recipe being saved, one per line.
+    examples: String,
+    /// Outcome of the last save, shown below the save form.
+    saved: Option<Result<String, String>>,
+}
+
+impl RecipeWindow {
+    /// Opens the browser with the team recipes of `dir`, if it is set.
+    pub fn new(dir: &str) -> Self {
+        let mut window = Self {
+            built_in: Recipe::built_in(),
+            team: Vec::new(),
+            team_errors: Vec::new(),
+            name: String::new(),
+            description: String::new(),
+            examples: String::new(),
+            saved: None,
+        };
+        window.reload(dir);
+        window
+    }
+
+    fn reload(&mut self, dir: &str) {
+        self.team.clear();
+        self.team_errors.clear();
+        if dir.trim().is_empty() {
+            return;
+        }
+        let (recipes, errors) = load_recipes(Path::new(dir.trim()));
+        self.team = recipes;
+        self.team_errors = errors.iter().map(ToString::to_string).collect();
+    }
+
+    /// Draws the window. `rules` are saved by **Save as team recipe** to
+    /// `dir`, which the user edits here.
+    pub fn show(&mut self, ctx: &Context, rules: &[Rule], dir: &mut String) -> RecipeAction {
+        let mut open = true;
+        let mut inserted = None;
+        egui::Window::new("Recipes")
+            .open(&mut open)
+            .default_size([640.0, 480.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Built-in");
+                    for (index, recipe) in self.built_in.iter().enumerate() {
+                        if show_recipe(ui, ("built_in", index), recipe) {
+                            inserted = Some(recipe.rules.clone());
+                        }
+                    }
+
+                    ui.separator();
+                    ui.heading("Team");
+                    ui.horizontal(|ui| {
+                        ui.label("Directory:");
+                        ui.add(
+                            egui::TextEdit::singleline(dir)
+                                .desired_width(320.0)
+                                .hint_text("/shared/rename-recipes"),
+                        );
+                        if ui
+                            .button("🔄 Reload")
+                            .on_hover_text("Read the .toml and .json recipes of the directory")
+                            .clicked()
+                        {
+                            self.reload(dir);
+                        }
+                    });
+                    for error in &self.team_errors {
+                        ui.label(RichText::new(error).color(ERROR_COLOR));
+                    }
+                    if self.team.is_empty() && self.team_errors.is_empty() {
+                        ui.weak("No team recipes yet.");
+                    }
+                    for (index, recipe) in self.team.iter().enumerate() {
+                        if show_recipe(ui, ("team", index), recipe) {
+                            inserted = Some(recipe.rules.clone());
+                        }
+                    }
+
+                    ui.separator();
+                    self.show_save_form(ui, rules, dir);
+                });
+            });
+        if !open {
+            RecipeAction::Closed
+        } else if let Some(rules) = inserted {
+            RecipeAction::Insert(rules)
+        } else {
+            RecipeAction::Pending
+        }
+    }
+
+    fn show_save_form(&mut self, ui: &mut egui::Ui, rules: &[Rule], dir: &str) {
+        egui::CollapsingHeader::new(RichText::new("Save current rules as team recipe").strong())
+            .show(ui, |ui| {
+                egui::Grid::new("recipe_save_form")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.name);
+                        ui.end_row();
+                        ui.label("Description:");
+                        ui.add(egui::TextEdit::multiline(&mut self.description).desired_rows(2));
+                        ui.end_row();
+                        ui.label("Example paths:")
+                            .on_hover_text("One path per line; the after column is computed");
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.examples)
+                                .desired_rows(2)
+                                .hint_text("./photos/IMG_0001.JPG"),
+                        );
+                        ui.end_row();
+                    });
+                let ready = !self.name.trim().is_empty() && !dir.trim().is_empty();
+                if ui
+                    .add_enabled(ready, egui::Button::new("💾 Save recipe"))
+                    .on_disabled_hover_text("Enter a name and a team directory first")
+                    .clicked()
+                {
+                    let before: Vec<String> = self
+                        .examples
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(String::from)
+                        .collect();
+                    let recipe = Recipe::new(
+                        self.name.trim().to_string(),
+                        self.description.trim().to_string(),
+                        rules.to_vec(),
+                        &before,
+                    );
+                    self.saved = Some(
+                        recipe
+                            .save(Path::new(dir.trim()))
+                            .map(|path| format!("Saved {}", path.display()))
+                            .map_err(|e| e.to_string()),
+                    );
+                    self.reload(dir);
+                }
+                match &self.saved {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(error)) => {
+                        ui.label(RichText::new(error).color(ERROR_COLOR));
+                    }
+                    None => {}
+                }
+            });
+    }
+}
+
+/// Draws one recipe with its examples. Returns `true` when its rules should
+/// be inserted.
+fn show_recipe(ui: &mut egui::Ui, id: (&str, usize), recipe: &Recipe) -> bool {
+    let mut insert = false;
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(&recipe.name).strong());
+            let rules = recipe.rules.len();
+            let hint = format!("Add the recipe's {rules} rule(s) to the rule table");
+            insert = ui.button("➕ Insert").on_hover_text(hint).clicked();
+        });
+        ui.label(&recipe.description);
+        egui::Grid::new(id)
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for example in &recipe.examples {
+                    ui.monospace(&example.before);
+                    ui.label("→");
+                    ui.monospace(&example.after);
+                    ui.end_row();
+                }
+            });
+    });
+    insert
+}
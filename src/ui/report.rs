@@ -0,0 +1,187 @@
+use std::io;
+
+use eframe::egui::{self, Color32, ComboBox, Context, RichText};
+use egui_extras::{Column, TableBuilder};
+
+use crate::application::{ExecutionReport, MoveOutcome, MoveStatus, Operation, display_path};
+
+const SUCCESS_COLOR: Color32 = Color32::from_rgb(166, 227, 161); // green
+const SKIPPED_COLOR: Color32 = Color32::from_rgb(249, 226, 175); // yellow
+const FAILED_COLOR: Color32 = Color32::from_rgb(243, 139, 168); // red
+
+/// Modal-style window summarizing the outcome of the last execution.
+pub struct ReportWindow {
+    report: ExecutionReport,
+    /// `None` shows every record.
+    status_filter: Option<MoveStatus>,
+    /// Restricts failed records to a single error kind.
+    kind_filter: Option<io::ErrorKind>,
+}
+
+impl ReportWindow {
+    pub fn new(report: ExecutionReport) -> Self {
+        // Jump straight to the failures when there are any.
+        let status_filter = (report.count(MoveStatus::Failed) > 0).then_some(MoveStatus::Failed);
+        Self {
+            report,
+            status_filter,
+            kind_filter: None,
+        }
+    }
+
+    /// Draws the window. Returns `false` once the user has closed it.
+    pub fn show(&mut self, ctx: &Context) -> bool {
+        let mut open = true;
+        egui::Window::new("Execution results")
+            .open(&mut open)
+            .default_size([720.0, 420.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!("✔ {}", self.report.count(MoveStatus::Succeeded)))
+                            .color(SUCCESS_COLOR),
+                    );
+                    ui.label(
+                        RichText::new(format!("⏭ {}", self.report.count(MoveStatus::Skipped)))
+                            .color(SKIPPED_COLOR),
+                    );
+                    ui.label(
+                        RichText::new(format!("✖ {}", self.report.count(MoveStatus::Failed)))
+                            .color(FAILED_COLOR),
+                    );
+                    let removed = self.report.removed_dirs();
+                    if removed > 0 {
+                        ui.label(format!(
+                            "🗑 {removed} empty director{} removed",
+                            if removed == 1 { "y" } else { "ies" }
+                        ));
+                    }
+                    if self.report.aborted {
+                        ui.label(
+                            RichText::new("Batch aborted after the first failure")
+                                .color(FAILED_COLOR),
+                        );
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Show:");
+                    ui.selectable_value(&mut self.status_filter, None, "All");
+                    ui.selectable_value(
+                        &mut self.status_filter,
+                        Some(MoveStatus::Succeeded),
+                        "Succeeded",
+                    );
+                    ui.selectable_value(
+                        &mut self.status_filter,
+                        Some(MoveStatus::Skipped),
+                        "Skipped",
+                    );
+                    ui.selectable_value(
+                        &mut self.status_filter,
+                        Some(MoveStatus::Failed),
+                        "Failed",
+                    );
+
+                    if self.status_filter == Some(MoveStatus::Failed) {
+                        let kinds = self.report.failure_kinds();
+                        ComboBox::from_label("Error kind")
+                            .selected_text(
+                                self.kind_filter
+                                    .map_or("All kinds".to_string(), |k| format!("{k:?}")),
+                            )
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.kind_filter, None, "All kinds");
+                                for kind in kinds {
+                                    ui.selectable_value(
+                                        &mut self.kind_filter,
+                                        Some(kind),
+                                        format!("{kind:?}"),
+                                    );
+                                }
+                            });
+                    }
+                });
+                ui.separator();
+
+                let records: Vec<_> = self
+                    .report
+                    .records
+                    .iter()
+                    .filter(|r| {
+                        self.status_filter
+                            .is_none_or(|status| r.outcome.status() == status)
+                    })
+                    .filter(|r| match self.kind_filter {
+                        Some(wanted) if self.status_filter == Some(MoveStatus::Failed) => {
+                            matches!(r.outcome, MoveOutcome::Failed { kind, .. } if kind == wanted)
+                        }
+                        _ => true,
+                    })
+                    .collect();
+
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .column(Column::auto()) // status
+                    .column(Column::auto().resizable(true)) // source
+                    .column(Column::auto().resizable(true)) // destination
+                    .column(Column::remainder()) // details
+                    .header(20.0, |mut header| {
+                        for title in ["Status", "Source", "Destination", "Details"] {
+                            header.col(|ui| {
+                                ui.strong(title);
+                            });
+                        }
+                    })
+                    .body(|body| {
+                        body.rows(20.0, records.len(), |mut row| {
+                            let record = records[row.index()];
+                            let (icon, color, details) = match &record.outcome {
+                                MoveOutcome::Succeeded => ("✔", SUCCESS_COLOR, String::new()),
+                                MoveOutcome::Skipped(reason) => {
+                                    ("⏭", SKIPPED_COLOR, reason.description().to_string())
+                                }
+                                MoveOutcome::Failed { kind, message } => {
+                                    ("✖", FAILED_COLOR, format!("{kind:?}: {message}"))
+                                }
+                            };
+                            row.col(|ui| {
+                                ui.label(RichText::new(icon).color(color));
+                            });
+                            row.col(|ui| {
+                                ui.monospace(display_path(&record.source));
+                            });
+                            row.col(|ui| {
+                                ui.monospace(display_path(&record.destination));
+                            });
+                            let details = match record.operation {
+                                Operation::Move => details,
+                                Operation::Rewrite { replacements } if details.is_empty() => {
+                                    format!("{replacements} replacement(s)")
+                                }
+                                Operation::Rewrite { replacements } if replacements > 0 => {
+                                    format!("{replacements} replacement(s); {details}")
+                                }
+                                Operation::Rewrite { .. } => details,
+                                Operation::Restore if details.is_empty() => {
+                                    "original contents restored".to_string()
+                                }
+                                Operation::Restore => details,
+                                Operation::RemoveDir if details.is_empty() => {
+                                    "empty directory removed".to_string()
+                                }
+                                Operation::CreateDir if details.is_empty() => {
+                                    "directory recreated".to_string()
+                                }
+                                Operation::RemoveDir | Operation::CreateDir => details,
+                            };
+                            row.col(|ui| {
+                                ui.label(details);
+                            });
+                        });
+                    });
+            });
+        open
+    }
+}
@@ -0,0 +1,577 @@
+use std::ops::Range;
+
+use eframe::egui::{self, RichText, Ui, Vec2};
+use egui_extras::{Column, TableBuilder, TableRow};
+use tracing::info;
+
+use super::ERROR_COLOR;
+use super::diff::show_content_preview;
+use super::engine::{engine_selector, syntax_table};
+use super::field::validated_field;
+use super::samples::{EXPANDED_ROW_HEIGHT, SAMPLE_LIMIT, show_samples};
+use super::transcode::{encoding_pickers, show_transcode_preview};
+use crate::application::{
+    AppError, ContentPreview, NORMALIZATIONS, Renamer, RuleSamples, SanitizeOptions, Template,
+    TranscodePreview, compile_engine, compile_pattern, compile_rule, normalization_description,
+    normalization_name, rule_regex, validate_template,
+};
+use crate::domain::{Engine, Normalization, RegexLimits, Rule, RuleKind, RuleMode};
+
+const REGEX_WIDTH: f32 = 220.0;
+const PATH_WIDTH: f32 = 220.0;
+const FILTER_WIDTH: f32 = 160.0;
+
+/// Transient GUI state attached to a rule row.
+#[derive(Default)]
+pub struct RuleState {
+    /// Last error reported for the rule.
+    pub error: Option<AppError>,
+    /// Sample matches, present while a rename rule is expanded.
+    samples: Option<RuleSamples>,
+    /// Pending rewrites, present while a content rule is expanded.
+    preview: Option<ContentPreview>,
+    /// Pending name changes, present while a transcode rule is expanded.
+    transcode: Option<TranscodePreview>,
+    /// Compile results of the rule's fields, see [`RuleState::validation`].
+    validation: Option<Validation>,
+}
+
+/// Fields of a rule its compile results depend on.
+#[derive(PartialEq)]
+struct ValidationKey {
+    from: String,
+    to: String,
+    kind: RuleKind,
+    mode: RuleMode,
+    engine: Engine,
+    limits: RegexLimits,
+    normalization: Normalization,
+    path_filter: String,
+}
+
+impl ValidationKey {
+    fn of(rule: &Rule) -> Self {
+        Self {
+            from: rule.from.clone(),
+            to: rule.to.clone(),
+            kind: rule.kind,
+            mode: rule.mode,
+            engine: rule.engine,
+            limits: rule.limits,
+            normalization: rule.normalization,
+            path_filter: rule.path_filter.clone(),
+        }
+    }
+}
+
+/// Errors of a rule's fields, computed once per edit rather than on every
+/// frame.
+pub struct Validation {
+    key: ValidationKey,
+    pub pattern: Option<AppError>,
+    pub template: Option<AppError>,
+    pub path_filter: Option<AppError>,
+    /// Whether [`compile_rule`] succeeds.
+    pub valid: bool,
+}
+
+impl Validation {
+    fn new(rule: &Rule) -> Self {
+        // Template groups are only checked once the pattern compiles.
+        let (pattern, template) = match rule_regex(&rule.from, rule.kind)
+            .and_then(|p| compile_engine(&p, rule.engine, rule.limits))
+        {
+            Ok(regex) => (
+                None,
+                validate_template(&rule.to, regex.as_ref(), rule.mode).err(),
+            ),
+            Err(e) => (Some(e), Template::parse(&rule.to).err()),
+        };
+        Self {
+            key: ValidationKey::of(rule),
+            pattern,
+            template,
+            path_filter: compile_pattern(&rule.path_filter).err(),
+            valid: compile_rule(rule).is_ok(),
+        }
+    }
+}
+
+impl RuleState {
+    /// Compile results of `rule`, recomputed only when a field they depend
+    /// on changed since the last call.
+    pub fn validation(&mut self, rule: &Rule) -> &Validation {
+        let key = ValidationKey::of(rule);
+        if self.validation.as_ref().is_some_and(|v| v.key == key) {
+            return self.validation.as_ref().unwrap();
+        }
+        self.validation.insert(Validation::new(rule))
+    }
+
+    fn is_expanded(&self) -> bool {
+        self.samples.is_some() || self.preview.is_some() || self.transcode.is_some()
+    }
+
+    /// Loads the sample matches, the content preview or the new names,
+    /// depending on the rule's mode.
+    fn expand(&mut self, renamer: &Renamer, rule: &Rule, sanitize: SanitizeOptions) {
+        self.collapse();
+        let result = match rule.mode {
+            RuleMode::Rename => renamer
+                .sample_matches(rule, SAMPLE_LIMIT, sanitize)
+                .map(|samples| self.samples = Some(samples)),
+            RuleMode::Content => renamer
+                .preview_content(rule)
+                .map(|preview| self.preview = Some(preview)),
+            RuleMode::Transcode => renamer
+                .preview_transcode(rule, sanitize)
+                .map(|preview| self.transcode = Some(preview)),
+        };
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+
+    pub fn collapse(&mut self) {
+        self.samples = None;
+        self.preview = None;
+        self.transcode = None;
+    }
+
+    /// Collapses the row and forgets the counts of `rule` after an edit
+    /// that changes what it matches.
+    fn reset(&mut self, rule: &mut Rule) {
+        self.collapse();
+        rule.file_match_count = None;
+        rule.dir_match_count = None;
+    }
+
+    /// Draws the samples, content preview or new names of an expanded rule.
+    fn show_expanded(
+        &mut self,
+        ui: &mut Ui,
+        idx: usize,
+        renamer: &Renamer,
+        rule: &Rule,
+        sanitize: SanitizeOptions,
+    ) {
+        egui::ScrollArea::vertical()
+            .id_salt(("expanded_rule", idx))
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                if ui.small_button("⟳ Refresh").clicked() {
+                    self.expand(renamer, rule, sanitize);
+                }
+                if let Some(samples) = &self.samples {
+                    show_samples(ui, samples);
+                }
+                if let Some(preview) = &self.preview {
+                    show_content_preview(ui, preview);
+                }
+                if let Some(preview) = &self.transcode {
+                    show_transcode_preview(ui, idx, preview);
+                }
+            });
+    }
+}
+
+/// What the [`RuleTable`] asks the app to do after a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleAction {
+    Add,
+    Remove(usize),
+    /// Show or hide the explainer of the rule.
+    Explain(usize),
+    /// Open or close the railroad diagram of the rule.
+    Railroad(usize),
+    /// Open or close the diagnostics of the rule.
+    Diagnostics(usize),
+}
+
+/// The editable table of rules, one row per rule plus a row below each
+/// expanded one.
+pub struct RuleTable<'a> {
+    pub renamer: &'a Renamer,
+    pub sanitize: SanitizeOptions,
+    /// Rule shown in the explainer.
+    pub selected: Option<usize>,
+    /// Span of the selected rule's pattern picked in the explainer.
+    pub highlight: &'a mut Option<Range<usize>>,
+    /// Rules whose railroad diagram and diagnostics are open.
+    pub railroad: Option<usize>,
+    pub diagnostics: Option<usize>,
+}
+
+impl RuleTable<'_> {
+    /// Draws the table. `states` is index-aligned with `rules`.
+    pub fn show(
+        mut self,
+        ui: &mut Ui,
+        rules: &mut [Rule],
+        states: &mut [RuleState],
+    ) -> Option<RuleAction> {
+        let mut action = None;
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(Column::auto()) // mode
+            .column(Column::auto()) // engine
+            .column(Column::auto()) // kind
+            .column(Column::auto()) // unicode normalization
+            .column(Column::auto()) // regex
+            .column(Column::auto()) // to path / replacement
+            .column(Column::auto()) // path filter
+            .column(Column::auto()) // dirs
+            .column(Column::auto()) // files
+            .column(Column::remainder()) // actions (+/count/✖)
+            .header(24.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong("Mode");
+                });
+                header.col(|ui| {
+                    ui.strong("Engine").on_hover_ui(syntax_table);
+                });
+                header.col(|ui| {
+                    ui.strong("Kind")
+                        .on_hover_text("Regex, or glob matched against the whole path");
+                });
+                header.col(|ui| {
+                    ui.strong("Unicode").on_hover_text(
+                        "Normalization form paths are matched in and destinations written in",
+                    );
+                });
+                header.col(|ui| {
+                    ui.strong("From");
+                });
+                header.col(|ui| {
+                    ui.strong("To")
+                        .on_hover_text("Destination path, or replacement text for content rules");
+                });
+                header.col(|ui| {
+                    ui.strong("Path Filter")
+                        .on_hover_text("Files whose contents a content rule rewrites");
+                });
+                header.col(|ui| {
+                    ui.strong("Dirs");
+                });
+                header.col(|ui| {
+                    ui.strong("Files");
+                });
+                header.col(|ui| {
+                    if ui.button("➕ Add").on_hover_text("Add rule").clicked() {
+                        action = Some(RuleAction::Add);
+                    }
+                });
+            })
+            .body(|mut body| {
+                let expanded_width = body.max_rect().width();
+                for (idx, (rule, state)) in rules.iter_mut().zip(states.iter_mut()).enumerate() {
+                    body.row(24.0, |row| {
+                        if let Some(clicked) = self.show_row(row, idx, rule, state) {
+                            action = Some(clicked);
+                        }
+                    });
+
+                    // Expanded rules show their samples in a row of their own.
+                    if state.is_expanded() {
+                        body.row(EXPANDED_ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                let rect = egui::Rect::from_min_size(
+                                    ui.max_rect().min,
+                                    Vec2::new(expanded_width, EXPANDED_ROW_HEIGHT),
+                                );
+                                // Not allocated in the cell, so the first
+                                // column keeps its width.
+                                let mut ui = ui.new_child(egui::UiBuilder::new().max_rect(rect));
+                                state.show_expanded(
+                                    &mut ui,
+                                    idx,
+                                    self.renamer,
+                                    rule,
+                                    self.sanitize,
+                                );
+                            });
+                        });
+                    }
+                }
+            });
+        action
+    }
+
+    fn show_row(
+        &mut self,
+        mut row: TableRow,
+        idx: usize,
+        rule: &mut Rule,
+        state: &mut RuleState,
+    ) -> Option<RuleAction> {
+        row.col(|ui| mode_cell(ui, idx, rule, state));
+        row.col(|ui| {
+            if engine_selector(ui, ("rule_engine", idx), &mut rule.engine) {
+                state.reset(rule);
+            }
+        });
+        row.col(|ui| kind_cell(ui, idx, rule, state));
+        row.col(|ui| normalization_cell(ui, idx, rule, state));
+
+        // Pattern
+        let selected = self.selected == Some(idx);
+        row.col(|ui| {
+            // Explainer spans refer to the regex, not the glob.
+            let highlight = self
+                .highlight
+                .clone()
+                .filter(|_| selected && rule.kind == RuleKind::Regex);
+            let hint = match rule.kind {
+                RuleKind::Regex => "regex",
+                RuleKind::Glob => "glob, e.g. **/*.JPG",
+            };
+            let error = state.validation(rule).pattern.as_ref();
+            let response = validated_field(ui, &mut rule.from, REGEX_WIDTH, hint, highlight, error);
+            if selected && response.changed() {
+                *self.highlight = None;
+            }
+        });
+
+        row.col(|ui| destination_cell(ui, idx, rule, state));
+        row.col(|ui| path_filter_cell(ui, rule, state));
+        row.col(|ui| {
+            ui.label(rule.dir_match_count.map_or("—".into(), |n| n.to_string()));
+        });
+        row.col(|ui| {
+            ui.label(rule.file_match_count.map_or("—".into(), |n| n.to_string()));
+        });
+
+        let mut action = None;
+        row.col(|ui| {
+            ui.horizontal(|ui| action = self.actions_cell(ui, idx, rule, state));
+        });
+        action
+    }
+
+    /// Count, expand, explain, diagram, diagnose and remove buttons, then
+    /// the last error of the rule.
+    fn actions_cell(
+        &self,
+        ui: &mut Ui,
+        idx: usize,
+        rule: &mut Rule,
+        state: &mut RuleState,
+    ) -> Option<RuleAction> {
+        let mut action = None;
+        if ui.button("🔍").on_hover_text("Count matches").clicked() {
+            state.error = self.renamer.count_matches(rule).err();
+        }
+        let expanded = state.is_expanded();
+        let hint = match rule.mode {
+            RuleMode::Rename => "Show sample matches",
+            RuleMode::Content => "Preview content changes",
+            RuleMode::Transcode => "Preview new names",
+        };
+        if ui
+            .selectable_label(expanded, "🔬")
+            .on_hover_text(hint)
+            .clicked()
+        {
+            if expanded {
+                state.collapse();
+            } else {
+                state.expand(self.renamer, rule, self.sanitize);
+            }
+        }
+        if ui
+            .selectable_label(self.selected == Some(idx), "🧩")
+            .on_hover_text("Explain pattern")
+            .clicked()
+        {
+            action = Some(RuleAction::Explain(idx));
+        }
+        if ui
+            .selectable_label(self.railroad == Some(idx), "🛤")
+            .on_hover_text("Show railroad diagram")
+            .clicked()
+        {
+            action = Some(RuleAction::Railroad(idx));
+        }
+        if ui
+            .selectable_label(self.diagnostics == Some(idx), "⏱")
+            .on_hover_text("Show size and performance diagnostics")
+            .clicked()
+        {
+            action = Some(RuleAction::Diagnostics(idx));
+        }
+        if ui.button("❌").on_hover_text("Remove rule").clicked() {
+            action = Some(RuleAction::Remove(idx));
+        }
+        if let Some(error) = &state.error {
+            let label = ui.label(RichText::new(format!("⚠ {error}")).color(ERROR_COLOR));
+            if let Some(pointer) = error.pointer() {
+                label.on_hover_text(RichText::new(pointer).monospace());
+            }
+        }
+        action
+    }
+}
+
+fn mode_cell(ui: &mut Ui, idx: usize, rule: &mut Rule, state: &mut RuleState) {
+    let before = rule.mode;
+    egui::ComboBox::from_id_salt(("rule_mode", idx))
+        .width(90.0)
+        .selected_text(match rule.mode {
+            RuleMode::Rename => "Rename",
+            RuleMode::Content => "Content",
+            RuleMode::Transcode => "Transcode",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut rule.mode, RuleMode::Rename, "Rename");
+            ui.selectable_value(&mut rule.mode, RuleMode::Content, "Content");
+            ui.selectable_value(&mut rule.mode, RuleMode::Transcode, "Transcode")
+                .on_hover_text("Convert the names of matched paths to another encoding");
+        });
+    if rule.mode != before {
+        // Globs only describe paths, not file contents.
+        if rule.mode == RuleMode::Content {
+            rule.kind = RuleKind::Regex;
+        }
+        state.reset(rule);
+    }
+}
+
+fn kind_cell(ui: &mut Ui, idx: usize, rule: &mut Rule, state: &mut RuleState) {
+    let before = rule.kind;
+    ui.add_enabled_ui(rule.mode != RuleMode::Content, |ui| {
+        egui::ComboBox::from_id_salt(("rule_kind", idx))
+            .width(60.0)
+            .selected_text(match rule.kind {
+                RuleKind::Regex => "Regex",
+                RuleKind::Glob => "Glob",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.kind, RuleKind::Regex, "Regex");
+                ui.selectable_value(&mut rule.kind, RuleKind::Glob, "Glob");
+            });
+    });
+    if rule.kind == RuleKind::Glob {
+        if let Ok(regex) = rule_regex(&rule.from, rule.kind) {
+            let regex = regex.into_owned();
+            let response = ui.small_button("⇄").on_hover_ui(|ui| {
+                ui.label("Convert to regex:");
+                ui.monospace(&regex);
+            });
+            if response.clicked() {
+                info!("Converted glob '{}' to '{regex}'", rule.from);
+                rule.from = regex;
+                rule.kind = RuleKind::Regex;
+            }
+        }
+    }
+    if rule.kind != before {
+        state.reset(rule);
+    }
+}
+
+/// Unicode normalization, for rename rules only.
+fn normalization_cell(ui: &mut Ui, idx: usize, rule: &mut Rule, state: &mut RuleState) {
+    let before = rule.normalization;
+    ui.add_enabled_ui(rule.mode == RuleMode::Rename, |ui| {
+        egui::ComboBox::from_id_salt(("rule_normalization", idx))
+            .width(60.0)
+            .selected_text(normalization_name(rule.normalization))
+            .show_ui(ui, |ui| {
+                for form in NORMALIZATIONS {
+                    ui.selectable_value(&mut rule.normalization, form, normalization_name(form))
+                        .on_hover_text(normalization_description(form));
+                }
+            })
+            .response
+            .on_hover_text(normalization_description(rule.normalization));
+    });
+    if rule.normalization != before {
+        state.reset(rule);
+    }
+}
+
+/// Destination template, replacement text or target encoding, depending
+/// on the mode.
+fn destination_cell(ui: &mut Ui, idx: usize, rule: &mut Rule, state: &mut RuleState) {
+    let hint = match rule.mode {
+        RuleMode::Rename => "destination",
+        RuleMode::Content => "replacement",
+        RuleMode::Transcode => {
+            if encoding_pickers(ui, idx, &mut rule.transcoding) {
+                state.reset(rule);
+            }
+            return;
+        }
+    };
+    let error = state.validation(rule).template.as_ref();
+    validated_field(ui, &mut rule.to, PATH_WIDTH, hint, None, error);
+    if rule.mode == RuleMode::Rename {
+        let global = ui
+            .toggle_value(&mut rule.replace_all, "g")
+            .on_hover_text("Replace every match in the path, not just the first");
+        let name = ui
+            .toggle_value(&mut rule.name_only, "name")
+            .on_hover_text("Match only the file name, never its directories");
+        if global.changed() || name.changed() {
+            state.reset(rule);
+        }
+    }
+}
+
+/// Path filter of a content rule, and the files a rule created from a
+/// search is limited to.
+fn path_filter_cell(ui: &mut Ui, rule: &mut Rule, state: &mut RuleState) {
+    if rule.mode == RuleMode::Content {
+        let error = state.validation(rule).path_filter.as_ref();
+        validated_field(
+            ui,
+            &mut rule.path_filter,
+            FILTER_WIDTH,
+            "all files",
+            None,
+            error,
+        );
+    } else if rule.paths.is_empty() {
+        ui.weak("—");
+    }
+    // Rules created from a search only apply to its files.
+    if !rule.paths.is_empty()
+        && ui
+            .small_button(format!("✖ {} files", rule.paths.len()))
+            .on_hover_text(
+                "Limited to the files of a search; click to apply the rule to every match",
+            )
+            .clicked()
+    {
+        rule.paths.clear();
+        state.reset(rule);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_is_kept_until_the_rule_changes() {
+        let mut state = RuleState::default();
+        let mut rule = Rule {
+            from: "(a".to_string(),
+            ..Rule::default()
+        };
+        assert!(state.validation(&rule).pattern.is_some());
+        assert!(!state.validation(&rule).valid);
+
+        rule.file_match_count = Some(1);
+        assert!(state.validation(&rule).pattern.is_some());
+
+        rule.from = "(a)".to_string();
+        rule.to = "$2".to_string();
+        let validation = state.validation(&rule);
+        assert!(validation.pattern.is_none());
+        assert!(validation.template.is_some());
+
+        rule.to = "$1".to_string();
+        assert!(state.validation(&rule).valid);
+    }
+}
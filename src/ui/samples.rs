@@ -0,0 +1,69 @@
+use eframe::egui::{self, Color32, RichText, Ui};
+
+use super::ERROR_COLOR;
+use super::highlight::{FILL_TEXT, captures_job, group_color};
+use crate::application::{RuleSamples, describe_issues};
+
+const SANITIZED_COLOR: Color32 = Color32::from_rgb(250, 179, 135); // peach
+
+/// Number of matched paths sampled when a rule row is expanded.
+pub const SAMPLE_LIMIT: usize = 20;
+
+/// Height of the row the samples of an expanded rule are shown in.
+pub const EXPANDED_ROW_HEIGHT: f32 = 220.0;
+
+/// Draws the legend and the highlighted sample matches of one rule.
+pub fn show_samples(ui: &mut Ui, samples: &RuleSamples) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Groups:");
+        for (index, name) in samples.group_names.iter().enumerate() {
+            let label = match name {
+                Some(name) => format!(" {index} {name} "),
+                None if index == 0 => " 0 match ".to_string(),
+                None => format!(" {index} "),
+            };
+            ui.label(
+                RichText::new(label)
+                    .monospace()
+                    .color(FILL_TEXT)
+                    .background_color(group_color(index)),
+            );
+        }
+    });
+
+    if samples.samples.is_empty() {
+        ui.weak("No matches.");
+        return;
+    }
+    ui.weak(format!(
+        "Showing {} of {} matches",
+        samples.samples.len(),
+        samples.total
+    ));
+
+    let default_color = ui.visuals().text_color();
+    egui::Grid::new(ui.next_auto_id())
+        .striped(true)
+        .num_columns(4)
+        .show(ui, |ui| {
+            for sample in &samples.samples {
+                ui.label(captures_job(&sample.path, default_color, &sample.groups));
+                ui.label("→");
+                ui.monospace(&sample.destination);
+                let issues = describe_issues(&sample.issues);
+                if let Some(reason) = sample.skip {
+                    let text = if issues.is_empty() {
+                        reason.description().to_string()
+                    } else {
+                        format!("{}: {issues}", reason.description())
+                    };
+                    ui.colored_label(ERROR_COLOR, format!("⚠ skipped: {text}"));
+                } else if !issues.is_empty() {
+                    ui.colored_label(SANITIZED_COLOR, format!("✎ sanitized: {issues}"));
+                } else {
+                    ui.label("");
+                }
+                ui.end_row();
+            }
+        });
+}
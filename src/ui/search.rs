@@ -0,0 +1,189 @@
+use std::path::PathBuf;
+
+use eframe::egui::{
+    self, Button, Color32, FontId, Key, RichText, Ui,
+    text::{LayoutJob, TextFormat},
+};
+
+use super::ERROR_COLOR;
+use super::field::validated_field;
+use super::highlight::{SpanStyle, span_job};
+use crate::application::{
+    AppError, HIT_LIMIT, LineHit, Renamer, SearchResults, SearchRow, compile_pattern, display_path,
+};
+
+const MATCH_COLOR: Color32 = Color32::from_rgb(249, 226, 175); // yellow
+const PATH_COLOR: Color32 = Color32::from_rgb(137, 180, 250); // blue
+const LINE_NUMBER_COLOR: Color32 = Color32::from_rgb(127, 132, 156); // overlay1
+
+/// Font of every result row; rows must share one height to be virtualized.
+const ROW_FONT_SIZE: f32 = 14.0;
+
+/// Content search across the working directory, grep style.
+#[derive(Default)]
+pub struct SearchPanel {
+    pattern: String,
+    path_filter: String,
+    results: Option<SearchResults>,
+    /// Flattened `results`, rebuilt after every search.
+    rows: Vec<SearchRow>,
+    error: Option<AppError>,
+}
+
+impl SearchPanel {
+    /// Draws the search form and results. Returns the paths of the files with
+    /// hits when the user asks for a rename rule limited to them.
+    pub fn show(&mut self, ui: &mut Ui, renamer: &Renamer) -> Option<Vec<PathBuf>> {
+        let mut submitted = false;
+        ui.horizontal(|ui| {
+            ui.strong("Pattern");
+            let pattern_error = compile_pattern(&self.pattern).err();
+            let response = validated_field(
+                ui,
+                &mut self.pattern,
+                320.0,
+                "regex",
+                None,
+                pattern_error.as_ref(),
+            );
+            submitted |= response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            ui.strong("in files matching");
+            let filter_error = compile_pattern(&self.path_filter).err();
+            let response = validated_field(
+                ui,
+                &mut self.path_filter,
+                200.0,
+                "all files",
+                None,
+                filter_error.as_ref(),
+            );
+            submitted |= response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            let valid = pattern_error.is_none() && filter_error.is_none();
+            submitted |= ui.add_enabled(valid, Button::new("🔎 Search")).clicked();
+            submitted &= valid && !self.pattern.is_empty();
+        });
+        if submitted {
+            self.run(renamer);
+        }
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error.to_string()).color(ERROR_COLOR));
+        }
+
+        let results = self.results.as_ref()?;
+        let mut create_rule = None;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} matches on {} lines in {} files",
+                results.match_count(),
+                results.line_count(),
+                results.files.len()
+            ));
+            if results.truncated {
+                ui.label(
+                    RichText::new(format!("stopped after {HIT_LIMIT} lines")).color(ERROR_COLOR),
+                );
+            }
+            skipped_label(ui, results);
+            let button = Button::new(format!(
+                "➕ Create rename rule from these {} files",
+                results.files.len()
+            ));
+            if ui
+                .add_enabled(!results.files.is_empty(), button)
+                .on_hover_text("Adds a rule limited to exactly these paths")
+                .clicked()
+            {
+                create_rule = Some(results.paths());
+            }
+        });
+        ui.separator();
+
+        let default_color = ui.visuals().text_color();
+        let row_height = ui.fonts(|f| f.row_height(&FontId::monospace(ROW_FONT_SIZE)));
+        egui::ScrollArea::both()
+            .id_salt("search_results")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, self.rows.len(), |ui, range| {
+                for row in &self.rows[range] {
+                    let job = match *row {
+                        SearchRow::File(i) => file_job(results, i),
+                        SearchRow::Line(i, j) => {
+                            line_job(&results.files[i].lines[j], default_color)
+                        }
+                    };
+                    ui.add(egui::Label::new(job).extend());
+                }
+            });
+        create_rule
+    }
+
+    fn run(&mut self, renamer: &Renamer) {
+        match renamer.search(&self.pattern, &self.path_filter) {
+            Ok(results) => {
+                self.rows = results.rows();
+                self.results = Some(results);
+                self.error = None;
+            }
+            Err(e) => {
+                self.rows.clear();
+                self.results = None;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Counts the files that were not searched, listing them on hover.
+fn skipped_label(ui: &mut Ui, results: &SearchResults) {
+    let count = results.skipped.len() + results.unreadable.len();
+    if count == 0 {
+        return;
+    }
+    ui.weak(format!("· {count} files not searched"))
+        .on_hover_ui(|ui| {
+            for (path, reason) in &results.skipped {
+                ui.label(format!("{} ({})", display_path(path), reason.description()));
+            }
+            for (path, message) in &results.unreadable {
+                ui.label(
+                    RichText::new(format!("{}: {message}", display_path(path))).color(ERROR_COLOR),
+                );
+            }
+        });
+}
+
+fn file_job(results: &SearchResults, index: usize) -> LayoutJob {
+    let file = &results.files[index];
+    let mut job = LayoutJob::default();
+    job.append(
+        &display_path(&file.path),
+        0.0,
+        TextFormat::simple(FontId::monospace(ROW_FONT_SIZE), PATH_COLOR),
+    );
+    job.append(
+        &format!("  ({} matches)", file.match_count),
+        0.0,
+        TextFormat::simple(FontId::monospace(ROW_FONT_SIZE), LINE_NUMBER_COLOR),
+    );
+    job
+}
+
+/// Lays out a hit as `line│ text` with its matches filled.
+fn line_job(hit: &LineHit, default_color: Color32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    job.append(
+        &format!("{:>7}│ ", hit.line_number),
+        0.0,
+        TextFormat::simple(FontId::monospace(ROW_FONT_SIZE), LINE_NUMBER_COLOR),
+    );
+    let spans: Vec<_> = hit
+        .matches
+        .iter()
+        .map(|m| (m.clone(), SpanStyle::Fill(MATCH_COLOR)))
+        .collect();
+    let line = span_job(&hit.text, default_color, &spans);
+    for section in line.sections {
+        job.append(&line.text[section.byte_range], 0.0, section.format);
+    }
+    job
+}
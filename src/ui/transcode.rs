@@ -0,0 +1,87 @@
+use std::hash::Hash;
+
+use eframe::egui::{self, Color32, RichText, Ui};
+
+use super::ERROR_COLOR;
+use crate::application::{NAME_ENCODINGS, TranscodePreview, describe_issues, display_path};
+use crate::domain::Transcoding;
+
+const NEW_NAME_COLOR: Color32 = Color32::from_rgb(166, 227, 161); // green
+const ENCODING_COLOR: Color32 = Color32::from_rgb(127, 132, 156); // overlay1
+
+/// Source and target encoding pickers of a transcode rule. Returns `true`
+/// when either changed.
+pub fn encoding_pickers(
+    ui: &mut Ui,
+    id_salt: impl Hash + Copy,
+    transcoding: &mut Transcoding,
+) -> bool {
+    let before = transcoding.clone();
+    egui::ComboBox::from_id_salt(("transcode_source", id_salt))
+        .width(100.0)
+        .selected_text(transcoding.source.as_deref().unwrap_or("Detect"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut transcoding.source, None, "Detect")
+                .on_hover_text("Keep UTF-8 names and guess the encoding of the others");
+            for label in NAME_ENCODINGS {
+                ui.selectable_value(&mut transcoding.source, Some(label.to_string()), label);
+            }
+        })
+        .response
+        .on_hover_text("Encoding of the current names");
+    ui.label("→");
+    egui::ComboBox::from_id_salt(("transcode_target", id_salt))
+        .width(100.0)
+        .selected_text(&transcoding.target)
+        .show_ui(ui, |ui| {
+            for label in NAME_ENCODINGS {
+                ui.selectable_value(&mut transcoding.target, label.to_string(), label);
+            }
+        })
+        .response
+        .on_hover_text("Encoding of the new names");
+    ui.checkbox(&mut transcoding.reencode_utf8, "UTF-8 too")
+        .on_hover_text("Also convert names that are already valid UTF-8");
+    *transcoding != before
+}
+
+/// Lists the names a transcode rule would change, old name first, followed
+/// by the names it cannot convert.
+pub fn show_transcode_preview(ui: &mut Ui, id_salt: impl Hash, preview: &TranscodePreview) {
+    ui.label(format!(
+        "{} name(s) would change · {} cannot be converted",
+        preview.changes.len(),
+        preview.skipped.len()
+    ));
+    egui::Grid::new(("transcode_preview", id_salt))
+        .num_columns(5)
+        .striped(true)
+        .show(ui, |ui| {
+            for change in &preview.changes {
+                ui.monospace(display_path(&change.path));
+                ui.label("→");
+                ui.label(
+                    RichText::new(display_path(&change.new_path))
+                        .monospace()
+                        .color(NEW_NAME_COLOR),
+                );
+                ui.label(RichText::new(change.source.name()).color(ENCODING_COLOR));
+                if change.issues.is_empty() {
+                    ui.label("");
+                } else {
+                    ui.label(format!("✎ sanitized: {}", describe_issues(&change.issues)));
+                }
+                ui.end_row();
+            }
+        });
+    for (path, reason) in &preview.skipped {
+        ui.label(
+            RichText::new(format!(
+                "✖ {} ({})",
+                display_path(path),
+                reason.description()
+            ))
+            .color(ERROR_COLOR),
+        );
+    }
+}